@@ -0,0 +1,160 @@
+//! Measures parse and compile throughput for a few representative programs, to catch compile time
+//! regressions.  Run with `cargo bench --bench compile`.
+//!
+//! Baseline on the machine this was written on (release profile, mean of each run):
+//!
+//! ```text
+//! nested_if              2.740 ms/iter    17.43 MB/s (47770 bytes)
+//! numeric_for            5.151 ms/iter    18.43 MB/s (94948 bytes)
+//! flat_assignments     116.609 ms/iter     7.58 MB/s (884463 bytes)
+//! functions             10.680 ms/iter    13.07 MB/s (139590 bytes)
+//! shared_chunks          1.221 ms/iter    21.93 MB/s (26770 bytes)
+//! shared_chunks_cached   1.058 ms/iter    25.31 MB/s (26770 bytes)
+//! ```
+//!
+//! `shared_chunks` compiles 100 small chunks with common string literals, `shared_chunks_cached`
+//! compiles the same chunks sharing one `CompilerCache`.
+
+use std::time::{Duration, Instant};
+
+use luster::{
+    compile, compile_chunk_with_cache, compile_chunk_with_options, parse_chunk, CompilerCache,
+    CompilerOptions, Lua,
+};
+
+// Every benchmark runs for at least this long, after a single warm-up compile.
+const MIN_DURATION: Duration = Duration::from_secs(2);
+
+fn nested_if() -> String {
+    let mut source = String::from("local a, b, c = ...\n");
+    for i in 0..40 {
+        source.push_str(&format!("if a > {} then\n", i));
+        for j in 0..20 {
+            source.push_str(&format!(
+                "if b == {} then c = c + {} elseif b < {} then c = c - a end\n",
+                j, i, j
+            ));
+        }
+    }
+    source.push_str(&"end\n".repeat(40));
+    source
+}
+
+fn numeric_for() -> String {
+    let mut source = String::from("local t, sum = {}, 0\nfor i = 1, 1000000 do\n");
+    for i in 0..2000 {
+        source.push_str(&format!(
+            "do local v = t[i] or {} sum = sum + v * i end\n",
+            i
+        ));
+    }
+    source.push_str("end\nreturn sum\n");
+    source
+}
+
+fn flat_assignments() -> String {
+    let mut source = String::from("local t = {}\n");
+    for i in 0..20000 {
+        source.push_str(&format!(
+            "g{} = {} + t.x * {}\nt[{}] = g{}\n",
+            i, i, i, i, i
+        ));
+    }
+    source
+}
+
+fn functions() -> String {
+    // A function can only have 256 inner functions, so the functions are spread over two levels.
+    let mut source = String::new();
+    for i in 0..200 {
+        source.push_str(&format!("function f{}(a, b, ...)\n", i));
+        for j in 0..10 {
+            source.push_str(&format!(
+                "    local function g{}(x, y) return x + a * {}, y or b, f{} end\n",
+                j, j, i
+            ));
+        }
+        source.push_str("    return g0(1, 2), g9(3), select('#', ...)\nend\n");
+    }
+    source
+}
+
+// Many small chunks of the kind a host loads as separate scripts, sharing most of their string
+// literals.
+fn shared_chunks() -> Vec<String> {
+    (0..100)
+        .map(|i| {
+            format!(
+                "local ui, log = ...\n\
+                 ui.register('button_{}', function(event)\n\
+                 \x20   log.info('handler ' .. '{}' .. ' fired', event.name, event.target)\n\
+                 \x20   if event.kind == 'click' then return ui.state.clicked + {} end\n\
+                 \x20   return ui.state.default, 'unhandled event kind', event.kind\n\
+                 end)\n",
+                i, i, i
+            )
+        })
+        .collect()
+}
+
+fn measure(name: &str, bytes: usize, mut run_once: impl FnMut()) {
+    run_once();
+    let mut iterations = 0;
+    let start = Instant::now();
+    while start.elapsed() < MIN_DURATION {
+        run_once();
+        iterations += 1;
+    }
+    let mean = start.elapsed() / iterations;
+
+    println!(
+        "{:<20} {:>10.3} ms/iter {:>8.2} MB/s ({} bytes, {} iterations)",
+        name,
+        mean.as_secs_f64() * 1e3,
+        bytes as f64 / mean.as_secs_f64() / 1e6,
+        bytes,
+        iterations,
+    );
+}
+
+fn bench(lua: &mut Lua, name: &str, source: &str) {
+    measure(name, source.len(), || {
+        lua.mutate(|mc, root| {
+            compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+        })
+    });
+}
+
+// Compiles every chunk in a single arena mutation, with or without a shared `CompilerCache`.
+fn bench_chunks(lua: &mut Lua, name: &str, chunks: &[String], use_cache: bool) {
+    let bytes = chunks.iter().map(|chunk| chunk.len()).sum();
+    measure(name, bytes, || {
+        lua.mutate(|mc, root| {
+            let mut cache = CompilerCache::new();
+            for source in chunks {
+                let chunk = parse_chunk(source.as_bytes(), |s| {
+                    root.interned_strings.new_string(mc, s)
+                })
+                .unwrap();
+                if use_cache {
+                    compile_chunk_with_cache(mc, &chunk, CompilerOptions::default(), &mut cache)
+                        .unwrap();
+                } else {
+                    compile_chunk_with_options(mc, &chunk, CompilerOptions::default()).unwrap();
+                }
+            }
+        })
+    });
+}
+
+fn main() {
+    let mut lua = Lua::new();
+    bench(&mut lua, "nested_if", &nested_if());
+    bench(&mut lua, "numeric_for", &numeric_for());
+    bench(&mut lua, "flat_assignments", &flat_assignments());
+    bench(&mut lua, "functions", &functions());
+
+    let chunks = shared_chunks();
+    bench_chunks(&mut lua, "shared_chunks", &chunks, false);
+    bench_chunks(&mut lua, "shared_chunks_cached", &chunks, true);
+}
@@ -0,0 +1,22 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use luster::{compile, verify, CompilerError, Error, Lua};
+
+// Runs arbitrary input through the lexer, parser and compiler.  Any input must either compile to a
+// valid prototype or produce an error, so this should run clean: a panic or an internal compiler
+// error is always a bug.
+fuzz_target!(|data: &[u8]| {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| match compile(mc, root.interned_strings, data) {
+        Ok(proto) => {
+            if let Err(err) = verify(&proto) {
+                panic!("compiled prototype is invalid: {}", err);
+            }
+        }
+        Err(Error::CompilerError(CompilerError::Internal(invariant))) => {
+            panic!("internal compiler error: {}", invariant);
+        }
+        Err(_) => {}
+    });
+});
@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use luster::{parser::parse_bytes, LexerError, ParserError};
+
+// Runs arbitrary input through the lexer and parser only.  Any input must either parse or produce
+// an error, so this should run clean: a panic or an internal lexer error is always a bug.
+fuzz_target!(|data: &[u8]| {
+    if let Err(ParserError::LexerError(err @ LexerError::Internal { .. })) = parse_bytes(data) {
+        panic!("{}", err);
+    }
+});
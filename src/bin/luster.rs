@@ -7,11 +7,16 @@ use rustyline::Editor;
 
 use gc_sequence::{self as sequence, SequenceExt, SequenceResultExt};
 use luster::{
-    compile, io, Closure, Error, Function, Lua, ParserError, StaticError, ThreadSequence,
+    compile, io, Closure, Error, Function, Lua, ParserError, ReplCompiler, StaticError,
+    ThreadSequence, Value,
 };
 
+// The registry key of the closure for the latest REPL input.
+const REPL_CLOSURE: i64 = 1;
+
 fn run_repl(lua: &mut Lua) {
     let mut editor = Editor::<()>::new();
+    let mut repl = ReplCompiler::new();
 
     loop {
         let mut prompt = "> ";
@@ -23,44 +28,52 @@ fn run_repl(lua: &mut Lua) {
                 Err(_) => return,
             }
 
-            let line_clone = line.clone();
+            // The closure for the latest input is kept in the registry, as it holds the REPL locals
+            // declared so far.
+            let compiled = lua.mutate(|mc, root| {
+                let proto = repl
+                    .compile(mc, root.interned_strings, line.as_bytes())
+                    .map_err(Error::to_static)?;
+                let previous = match root.registry.raw_get(REPL_CLOSURE) {
+                    Value::Function(Function::Closure(closure)) => Some(closure),
+                    _ => None,
+                };
+                let closure = repl.closure(mc, proto, root.globals, previous);
+                root.registry
+                    .raw_set(mc, REPL_CLOSURE, Function::Closure(closure))
+                    .map_err(|e| Error::from(e).to_static())?;
+                Ok(())
+            });
 
-            match lua.sequence(move |root| {
-                sequence::from_fn_with(root, move |mc, root| {
-                    let result = compile(mc, root.interned_strings, line_clone.as_bytes());
-                    let result = match result {
-                        Ok(res) => Ok(res),
-                        err @ Err(Error::ParserError(ParserError::EndOfStream { expected: _ })) => {
-                            err
+            match compiled.and_then(|()| {
+                lua.sequence(|root| {
+                    sequence::from_fn_with(root, |_, root| {
+                        match root.registry.raw_get(REPL_CLOSURE) {
+                            Value::Function(function) => Ok(function),
+                            _ => unreachable!("REPL input was not compiled"),
                         }
-                        Err(_) => compile(
+                    })
+                    .and_chain_with(root, |mc, root, function| {
+                        Ok(ThreadSequence::call_function(
                             mc,
-                            root.interned_strings,
-                            (String::new() + "return " + &line_clone).as_bytes(),
-                        ),
-                    };
-                    Ok(Closure::new(mc, result?, Some(root.globals))?)
-                })
-                .and_chain_with(root, |mc, root, closure| {
-                    Ok(ThreadSequence::call_function(
-                        mc,
-                        root.main_thread,
-                        Function::Closure(closure),
-                        &[],
-                    )?)
-                })
-                .map(|values| match values {
-                    Ok(values) => {
-                        let output = values
-                            .iter()
-                            .map(|value| format!("{:?}", value))
-                            .collect::<Vec<_>>()
-                            .join("\t");
-                        Ok(output)
-                    }
-                    Err(e) => Err(e.to_static()),
+                            root.main_thread,
+                            function,
+                            &[],
+                        )?)
+                    })
+                    .map(|values| match values {
+                        Ok(values) => {
+                            let output = values
+                                .iter()
+                                .map(|value| format!("{:?}", value))
+                                .collect::<Vec<_>>()
+                                .join("\t");
+                            Ok(output)
+                        }
+                        Err(e) => Err(e.to_static()),
+                    })
+                    .boxed()
                 })
-                .boxed()
             }) {
                 err @ Err(StaticError::ParserError(ParserError::EndOfStream { expected: _ })) => {
                     match line.chars().last() {
@@ -4,7 +4,10 @@ use std::hash::{Hash, Hasher};
 
 use gc_arena::{Collect, Gc, GcCell, MutationContext};
 
-use crate::{Constant, OpCode, RegisterIndex, Table, Thread, UpValueIndex, Value};
+use crate::{
+    Constant, OpCode, OwnedConstant, PackedOpCode, RegisterIndex, String, Table, Thread,
+    UpValueIndex, Value,
+};
 
 #[derive(Debug, Collect, Clone, Copy, PartialEq, Eq)]
 #[collect(require_static)]
@@ -17,15 +20,230 @@ pub enum UpValueDescriptor {
 #[derive(Debug, Collect)]
 #[collect(empty_drop)]
 pub struct FunctionProto<'gc> {
+    /// The number of named parameters the function takes.
     pub fixed_params: u8,
+    /// Whether the function accepts extra arguments with `...`.
     pub has_varargs: bool,
+    /// The number of registers the function needs, not counting extra arguments.
     pub stack_size: u16,
     pub constants: Vec<Constant<'gc>>,
     pub opcodes: Vec<OpCode>,
+    /// The same opcodes in their packed encoding.  Empty unless the function was compiled with
+    /// `CompilerOptions::packed_opcodes`.
+    pub packed_opcodes: Vec<PackedOpCode>,
     pub upvalues: Vec<UpValueDescriptor>,
+    /// The names of the upvalues, in the same order.  Empty unless the function was compiled with
+    /// `CompilerOptions::debug_info`.
+    pub upvalue_names: Vec<String<'gc>>,
+    /// The named locals of the function and the instructions they are in scope for.  Empty unless
+    /// the function was compiled with `CompilerOptions::debug_info`.
+    pub local_vars: Vec<LocalVarInfo<String<'gc>>>,
     pub prototypes: Vec<Gc<'gc, FunctionProto<'gc>>>,
 }
 
+/// A named local variable held in register `reg` for the instructions in `start_pc..end_pc`.
+#[derive(Debug, Clone, PartialEq, Eq, Collect)]
+#[collect(empty_drop)]
+pub struct LocalVarInfo<S> {
+    pub name: S,
+    pub reg: RegisterIndex,
+    pub start_pc: usize,
+    pub end_pc: usize,
+}
+
+// Structural equality, constants are compared bit for bit and nested prototypes are compared by
+// value rather than by pointer.
+impl<'gc> PartialEq for FunctionProto<'gc> {
+    fn eq(&self, other: &FunctionProto<'gc>) -> bool {
+        self.fixed_params == other.fixed_params
+            && self.has_varargs == other.has_varargs
+            && self.stack_size == other.stack_size
+            && self.constants == other.constants
+            && self.opcodes == other.opcodes
+            && self.packed_opcodes == other.packed_opcodes
+            && self.upvalues == other.upvalues
+            && self.upvalue_names == other.upvalue_names
+            && self.local_vars == other.local_vars
+            && self.prototypes.len() == other.prototypes.len()
+            && self
+                .prototypes
+                .iter()
+                .zip(&other.prototypes)
+                .all(|(a, b)| **a == **b)
+    }
+}
+
+impl<'gc> Eq for FunctionProto<'gc> {}
+
+impl<'gc> FunctionProto<'gc> {
+    /// The number of named parameters the function takes.
+    pub fn fixed_params(&self) -> u8 {
+        self.fixed_params
+    }
+
+    /// Whether the function accepts extra arguments with `...`.
+    pub fn has_varargs(&self) -> bool {
+        self.has_varargs
+    }
+
+    /// The number of upvalues a closure of the function captures.
+    pub fn upvalue_count(&self) -> usize {
+        self.upvalues.len()
+    }
+
+    /// The number of registers the function needs, not counting extra arguments.
+    pub fn stack_size(&self) -> u16 {
+        self.stack_size
+    }
+
+    /// The name of the given upvalue, if the function was compiled with debug info.
+    pub fn upvalue_name(&self, index: UpValueIndex) -> Option<&[u8]> {
+        self.upvalue_names
+            .get(index.0 as usize)
+            .map(|name| name.as_bytes())
+    }
+
+    /// The name of the local held in the given register at the given instruction, if the function
+    /// was compiled with debug info.
+    pub fn local_name(&self, reg: RegisterIndex, pc: usize) -> Option<&[u8]> {
+        self.local_vars
+            .iter()
+            .find(|local| local.reg == reg && local.start_pc <= pc && pc < local.end_pc)
+            .map(|local| local.name.as_bytes())
+    }
+
+    /// Lists this function and every nested function, with the constant operands of each opcode
+    /// resolved to their values.  The proto must have passed `verify`, otherwise this may panic.
+    pub fn to_listing(&self) -> Listing<'gc> {
+        Listing {
+            fixed_params: self.fixed_params,
+            has_varargs: self.has_varargs,
+            stack_size: self.stack_size,
+            upvalues: self.upvalues.clone(),
+            opcodes: self
+                .opcodes
+                .iter()
+                .map(|&opcode| ListedOpCode {
+                    opcode,
+                    constants: opcode
+                        .constant_operands()
+                        .iter()
+                        .flatten()
+                        .map(|c| self.constants[c.0 as usize])
+                        .collect(),
+                })
+                .collect(),
+            prototypes: self.prototypes.iter().map(|p| p.to_listing()).collect(),
+        }
+    }
+
+    /// Copies this function and every nested function out of the arena.
+    pub fn to_owned_proto(&self) -> OwnedProto {
+        OwnedProto {
+            fixed_params: self.fixed_params,
+            has_varargs: self.has_varargs,
+            stack_size: self.stack_size,
+            constants: self
+                .constants
+                .iter()
+                .map(|c| c.to_owned_constant())
+                .collect(),
+            opcodes: self.opcodes.clone(),
+            packed_opcodes: self.packed_opcodes.clone(),
+            upvalues: self.upvalues.clone(),
+            upvalue_names: self
+                .upvalue_names
+                .iter()
+                .map(|name| name.as_bytes().to_vec())
+                .collect(),
+            local_vars: self
+                .local_vars
+                .iter()
+                .map(|local| LocalVarInfo {
+                    name: local.name.as_bytes().to_vec(),
+                    reg: local.reg,
+                    start_pc: local.start_pc,
+                    end_pc: local.end_pc,
+                })
+                .collect(),
+            prototypes: self.prototypes.iter().map(|p| p.to_owned_proto()).collect(),
+        }
+    }
+
+    /// Materializes an `OwnedProto` back into the arena, allocating its strings and nested
+    /// functions.
+    pub fn reify(mc: MutationContext<'gc, '_>, owned: &OwnedProto) -> FunctionProto<'gc> {
+        FunctionProto {
+            fixed_params: owned.fixed_params,
+            has_varargs: owned.has_varargs,
+            stack_size: owned.stack_size,
+            constants: owned
+                .constants
+                .iter()
+                .map(|c| Constant::from_owned_constant(mc, c))
+                .collect(),
+            opcodes: owned.opcodes.clone(),
+            packed_opcodes: owned.packed_opcodes.clone(),
+            upvalues: owned.upvalues.clone(),
+            upvalue_names: owned
+                .upvalue_names
+                .iter()
+                .map(|name| String::new(mc, name))
+                .collect(),
+            local_vars: owned
+                .local_vars
+                .iter()
+                .map(|local| LocalVarInfo {
+                    name: String::new(mc, &local.name),
+                    reg: local.reg,
+                    start_pc: local.start_pc,
+                    end_pc: local.end_pc,
+                })
+                .collect(),
+            prototypes: owned
+                .prototypes
+                .iter()
+                .map(|p| Gc::allocate(mc, FunctionProto::reify(mc, p)))
+                .collect(),
+        }
+    }
+}
+
+/// An arena independent copy of a `FunctionProto`, see `FunctionProto::to_owned_proto` and
+/// `FunctionProto::reify`.
+#[derive(Debug, Clone, PartialEq, Collect)]
+#[collect(require_static)]
+pub struct OwnedProto {
+    pub fixed_params: u8,
+    pub has_varargs: bool,
+    pub stack_size: u16,
+    pub constants: Vec<OwnedConstant>,
+    pub opcodes: Vec<OpCode>,
+    pub packed_opcodes: Vec<PackedOpCode>,
+    pub upvalues: Vec<UpValueDescriptor>,
+    pub upvalue_names: Vec<Vec<u8>>,
+    pub local_vars: Vec<LocalVarInfo<Vec<u8>>>,
+    pub prototypes: Vec<OwnedProto>,
+}
+
+/// A structured disassembly of a `FunctionProto`, see `FunctionProto::to_listing`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing<'gc> {
+    pub fixed_params: u8,
+    pub has_varargs: bool,
+    pub stack_size: u16,
+    pub upvalues: Vec<UpValueDescriptor>,
+    pub opcodes: Vec<ListedOpCode<'gc>>,
+    pub prototypes: Vec<Listing<'gc>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedOpCode<'gc> {
+    pub opcode: OpCode,
+    /// The values of the constants the opcode reads, in operand order.
+    pub constants: Vec<Constant<'gc>>,
+}
+
 #[derive(Debug, Collect, Copy, Clone)]
 #[collect(require_copy)]
 pub enum UpValueState<'gc> {
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error as StdError;
 use std::{fmt, iter, mem};
 
@@ -15,14 +15,15 @@ use crate::parser::{
     WhileStatement,
 };
 use crate::{
-    Constant, ConstantIndex16, ConstantIndex8, FunctionProto, OpCode, Opt254, PrototypeIndex,
-    RegisterIndex, String, UpValueDescriptor, UpValueIndex, VarCount,
+    Constant, ConstantIndex16, ConstantIndex8, FunctionProto, LocalVarInfo, OpCode, Opt254,
+    PackedOpCode, PrototypeIndex, RegisterIndex, String, UpValueDescriptor, UpValueIndex, VarCount,
 };
 
 use super::operators::{
-    categorize_binop, comparison_binop_const_fold, comparison_binop_opcode,
-    simple_binop_const_fold, simple_binop_opcode, unop_const_fold, unop_opcode, BinOpCategory,
-    ComparisonBinOp, RegisterOrConstant, ShortCircuitBinOp, SimpleBinOp,
+    categorize_binop, comparison_binop_const_fold, comparison_binop_opcode, concat_const_fold,
+    get_table_opcode, set_table_opcode, simple_binop_const_fold, simple_binop_opcode,
+    unop_const_fold, unop_opcode, BinOpCategory, ComparisonBinOp, RegisterOrConstant,
+    ShortCircuitBinOp, SimpleBinOp, TableOperand,
 };
 use super::register_allocator::RegisterAllocator;
 
@@ -30,8 +31,18 @@ use super::register_allocator::RegisterAllocator;
 #[collect(require_static)]
 pub enum CompilerError {
     Registers,
-    UpValues,
+    /// A function captures more upvalues than allowed.  Holds the name of the variable that could
+    /// not be captured, and the nesting depth of the function, counting the main chunk as 0.
+    UpValues {
+        name: Box<[u8]>,
+        depth: u32,
+    },
     FixedParameters,
+    /// A call passes more arguments than a `VarCount` can hold.
+    Arguments,
+    /// A return statement or multi-value expression produces more values than a `VarCount` can
+    /// hold.
+    Returns,
     Functions,
     Constants,
     OpCodes,
@@ -39,16 +50,27 @@ pub enum CompilerError {
     GotoInvalid,
     JumpLocal,
     JumpOverflow,
+    NestingTooDeep,
+    /// An internal invariant of the compiler did not hold, this is always a compiler bug (or a
+    /// malformed hand-built AST).  Holds a description of the invariant and where it was violated.
+    Internal(std::string::String),
 }
 
 impl StdError for CompilerError {}
 
 impl fmt::Display for CompilerError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             CompilerError::Registers => write!(fmt, "insufficient available registers"),
-            CompilerError::UpValues => write!(fmt, "too many upvalues"),
+            CompilerError::UpValues { name, depth } => write!(
+                fmt,
+                "too many upvalues capturing '{}' in a function nested at depth {}",
+                std::string::String::from_utf8_lossy(name),
+                depth
+            ),
             CompilerError::FixedParameters => write!(fmt, "too many fixed parameters"),
+            CompilerError::Arguments => write!(fmt, "too many arguments"),
+            CompilerError::Returns => write!(fmt, "too many returned values"),
             CompilerError::Functions => write!(fmt, "too many inner functions"),
             CompilerError::Constants => write!(fmt, "too many constants"),
             CompilerError::OpCodes => write!(fmt, "too many opcodes"),
@@ -56,33 +78,330 @@ impl fmt::Display for CompilerError {
             CompilerError::GotoInvalid => write!(fmt, "goto target label not found"),
             CompilerError::JumpLocal => write!(fmt, "jump into scope of new local variable"),
             CompilerError::JumpOverflow => write!(fmt, "jump offset overflow"),
+            CompilerError::NestingTooDeep => write!(fmt, "expression nested too deeply"),
+            CompilerError::Internal(invariant) => {
+                write!(fmt, "internal compiler error: {}", invariant)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CompilerWarning<'gc> {
+    /// A local variable that is never referenced after being declared.  Locals whose names start
+    /// with `_` are never reported.
+    UnusedLocal(String<'gc>),
+    /// A call to a local function with a different number of arguments than the function has
+    /// parameters.  Only calls to `local function` definitions without varargs are checked, and only
+    /// if the local is never assigned to or captured as an upvalue.
+    ArgumentCount {
+        name: String<'gc>,
+        parameters: u8,
+        arguments: u8,
+    },
+    /// A read of a global variable which is not one of `CompilerOptions::known_globals`.
+    UnknownGlobal(String<'gc>),
+}
+
+impl<'gc> fmt::Display for CompilerWarning<'gc> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CompilerWarning::UnusedLocal(name) => write!(
+                fmt,
+                "unused local variable '{}'",
+                std::string::String::from_utf8_lossy(name.as_bytes())
+            ),
+            CompilerWarning::ArgumentCount {
+                name,
+                parameters,
+                arguments,
+            } => write!(
+                fmt,
+                "local function '{}' takes {} arguments but is called with {}",
+                std::string::String::from_utf8_lossy(name.as_bytes()),
+                parameters,
+                arguments
+            ),
+            CompilerWarning::UnknownGlobal(name) => write!(
+                fmt,
+                "read of unknown global variable '{}'",
+                std::string::String::from_utf8_lossy(name.as_bytes())
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CompilerOptions<'a> {
+    /// Always give the top-level function its `_ENV` upvalue, even if the chunk never references
+    /// it.  By default the upvalue is only added when `_ENV` is used.
+    pub always_emit_env: bool,
+    /// Collect `CompilerWarning`s while compiling.  When this is false no extra work is done.
+    pub collect_warnings: bool,
+    /// The maximum nesting depth of a single expression, counting every operator and suffix in a
+    /// chain like `a + b + c` or `a.b.c` as a level.  Compiling an expression recurses once per
+    /// level, so this bounds the native stack used.  Deeper expressions are a
+    /// `CompilerError::NestingTooDeep`.
+    pub max_expression_depth: usize,
+    /// Emit the dedicated `GetGlobal` / `SetGlobal` opcodes for global variable access, rather than
+    /// treating `_ENV` as any other upvalue table.
+    pub global_opcodes: bool,
+    /// Compute `CompileStats` for the compiled chunk.
+    pub collect_stats: bool,
+    /// The maximum number of registers a single function may use.  This can only lower the limit
+    /// of 256 imposed by the opcode encoding.
+    pub max_registers: u16,
+    /// The maximum number of constants a single function may use.  This can only lower the limit
+    /// of 65536 imposed by the opcode encoding.
+    pub max_constants: usize,
+    /// The maximum number of upvalues a single function may use.  This can only lower the limit of
+    /// 256 imposed by the opcode encoding.
+    pub max_upvalues: usize,
+    /// The maximum number of opcodes a single function may contain.  This can only lower the
+    /// built-in limit of 2^20.
+    pub max_opcodes: usize,
+    /// The names of the globals the code is expected to read.  When collecting warnings, reading
+    /// any other global is a `CompilerWarning::UnknownGlobal`.  Assigning to a global is always
+    /// allowed.
+    pub known_globals: Option<&'a HashSet<Vec<u8>>>,
+    /// Parse source in script mode, where a bare expression ending the chunk is returned as if it
+    /// were a `return` statement.  This only applies when compiling from source with
+    /// `compile_with_options`, as an already parsed chunk has no bare expressions.
+    pub implicit_return: bool,
+    /// Keep debug information in the compiled prototypes: the names of their upvalues and the
+    /// names and scopes of their locals, see `FunctionProto::upvalue_name` and
+    /// `FunctionProto::local_name`.
+    pub debug_info: bool,
+    /// Also store the opcodes of the compiled prototypes in their packed encoding, see
+    /// `FunctionProto::packed_opcodes`.
+    pub packed_opcodes: bool,
+}
+
+impl<'a> Default for CompilerOptions<'a> {
+    fn default() -> CompilerOptions<'a> {
+        CompilerOptions {
+            always_emit_env: false,
+            collect_warnings: false,
+            max_expression_depth: 200,
+            global_opcodes: false,
+            collect_stats: false,
+            max_registers: 256,
+            max_constants: 1 << 16,
+            max_upvalues: 256,
+            max_opcodes: 1 << 20,
+            known_globals: None,
+            implicit_return: false,
+            debug_info: false,
+            packed_opcodes: false,
+        }
+    }
+}
+
+/// Size statistics for a compiled chunk, totalled over the main function and every nested
+/// function.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct CompileStats {
+    pub opcodes: usize,
+    pub constants: usize,
+    /// The number of functions, including the main function of the chunk.
+    pub prototypes: usize,
+    /// The largest `stack_size` of any single function.
+    pub max_stack_size: u16,
+    pub upvalues: usize,
+}
+
+impl CompileStats {
+    pub fn new(proto: &FunctionProto) -> CompileStats {
+        let mut stats = CompileStats {
+            opcodes: proto.opcodes.len(),
+            constants: proto.constants.len(),
+            prototypes: 1,
+            max_stack_size: proto.stack_size,
+            upvalues: proto.upvalues.len(),
+        };
+        for inner in &proto.prototypes {
+            let inner = CompileStats::new(inner);
+            stats.opcodes += inner.opcodes;
+            stats.constants += inner.constants;
+            stats.prototypes += inner.prototypes;
+            stats.max_stack_size = stats.max_stack_size.max(inner.max_stack_size);
+            stats.upvalues += inner.upvalues;
+        }
+        stats
+    }
+}
+
+/// State shared between compilations in the same arena, see `compile_chunk_with_cache`.
+///
+/// String constants are interned in the cache, so identical strings in chunks compiled with the
+/// same cache share a single allocation even if the chunks were parsed without an
+/// `InternedStringSet`, and string constants produced by constant folding are shared as well.  The
+/// hash tables used to build each function's constant table are also kept and reused.
+#[derive(Default)]
+pub struct CompilerCache<'gc> {
+    strings: HashSet<String<'gc>>,
+    constant_tables: Vec<HashMap<Constant<'gc>, ConstantIndex16>>,
+}
+
+impl<'gc> CompilerCache<'gc> {
+    pub fn new() -> CompilerCache<'gc> {
+        CompilerCache::default()
+    }
+
+    fn intern(&mut self, constant: Constant<'gc>) -> Constant<'gc> {
+        match constant {
+            Constant::String(s) => {
+                if let Some(&found) = self.strings.get(&s) {
+                    Constant::String(found)
+                } else {
+                    self.strings.insert(s);
+                    Constant::String(s)
+                }
+            }
+            constant => constant,
         }
     }
+
+    fn take_constant_table(&mut self) -> HashMap<Constant<'gc>, ConstantIndex16> {
+        self.constant_tables.pop().unwrap_or_default()
+    }
+
+    fn return_constant_table(&mut self, mut table: HashMap<Constant<'gc>, ConstantIndex16>) {
+        table.clear();
+        self.constant_tables.push(table);
+    }
+}
+
+#[derive(Debug)]
+pub struct CompilerOutput<'gc> {
+    pub proto: FunctionProto<'gc>,
+    /// Empty unless `CompilerOptions::collect_warnings` is set.
+    pub warnings: Vec<CompilerWarning<'gc>>,
+    /// Only present if `CompilerOptions::collect_stats` is set.
+    pub stats: Option<CompileStats>,
 }
 
 pub fn compile_chunk<'gc>(
     mc: MutationContext<'gc, '_>,
     chunk: &Chunk<String<'gc>>,
 ) -> Result<FunctionProto<'gc>, CompilerError> {
-    let mut compiler = Compiler {
-        mutation_context: mc,
-        current_function: CompilerFunction::start(&[], true)?,
-        upper_functions: Vec::new(),
+    compile_chunk_with_options(mc, chunk, CompilerOptions::default()).map(|output| output.proto)
+}
+
+/// Compiles the given chunk, also returning any warnings and statistics requested in `options`.
+pub fn compile_chunk_with_options<'gc>(
+    mc: MutationContext<'gc, '_>,
+    chunk: &Chunk<String<'gc>>,
+    options: CompilerOptions,
+) -> Result<CompilerOutput<'gc>, CompilerError> {
+    compile_chunk_with_cache(mc, chunk, options, &mut CompilerCache::new())
+}
+
+/// Compiles the given chunk like `compile_chunk_with_options`, sharing string constants and
+/// internal allocations with every other chunk compiled with the same `CompilerCache`.
+pub fn compile_chunk_with_cache<'gc>(
+    mc: MutationContext<'gc, '_>,
+    chunk: &Chunk<String<'gc>>,
+    options: CompilerOptions,
+    cache: &mut CompilerCache<'gc>,
+) -> Result<CompilerOutput<'gc>, CompilerError> {
+    let mut compiler = Compiler::new(mc, chunk, &options, mem::take(cache))?;
+    let proto = compiler.main_function(&chunk.block, options.always_emit_env);
+    *cache = mem::take(&mut compiler.cache);
+    let proto = proto?;
+    let stats = if options.collect_stats {
+        Some(CompileStats::new(&proto))
+    } else {
+        None
     };
+    Ok(CompilerOutput {
+        proto,
+        warnings: compiler.warnings.unwrap_or_default(),
+        stats,
+    })
+}
+
+// Compiles a chunk of REPL input.  The top-level function always has `_ENV` as its first upvalue,
+// followed by one upvalue for each name in `repl_locals`, and the top-level locals the chunk
+// declares become further upvalues rather than registers so that they outlive the chunk.  Every
+// upvalue after `_ENV` is described as `Outer` of its own index, and `ReplCompiler::closure` fills
+// it with the matching upvalue of the previous input's closure (or a new one).  Returns the names of
+// the newly declared locals.
+pub(super) fn compile_repl_chunk<'gc>(
+    mc: MutationContext<'gc, '_>,
+    chunk: &Chunk<String<'gc>>,
+    repl_locals: &[String<'gc>],
+) -> Result<(FunctionProto<'gc>, Vec<String<'gc>>), CompilerError> {
+    let mut compiler = Compiler::new(mc, chunk, &CompilerOptions::default(), CompilerCache::new())?;
+    // `block_statements` only enters a block of its own for blocks without a return statement.
+    compiler.repl_scope = Some(if chunk.block.return_statement.is_some() {
+        1
+    } else {
+        2
+    });
+    compiler.current_function.push_upvalue(
+        String::new_static(b"_ENV"),
+        UpValueDescriptor::Environment,
+        0,
+    )?;
+    for &name in repl_locals {
+        compiler.push_repl_local(name)?;
+    }
     compiler.block(&chunk.block)?;
-    compiler.current_function.finish(mc)
+    let new_locals = compiler.current_function.upvalues[1 + repl_locals.len()..]
+        .iter()
+        .map(|&(name, _)| name)
+        .collect();
+    let (debug_info, packed_opcodes) = (compiler.debug_info, compiler.packed_opcodes);
+    Ok((
+        compiler
+            .current_function
+            .finish(mc, debug_info, packed_opcodes, &mut compiler.cache)?,
+        new_locals,
+    ))
 }
 
 struct Compiler<'gc, 'a> {
     mutation_context: MutationContext<'gc, 'a>,
     current_function: CompilerFunction<'gc>,
     upper_functions: Vec<CompilerFunction<'gc>>,
+    // Only present if warnings are being collected
+    warnings: Option<Vec<CompilerWarning<'gc>>>,
+    // An upper bound on the depth of the expression descriptors built so far in the innermost
+    // expression being compiled.
+    expression_depth: usize,
+    max_expression_depth: usize,
+    global_opcodes: bool,
+    limits: FunctionLimits,
+    // Only present if warnings are being collected
+    known_globals: Option<HashSet<Vec<u8>>>,
+    // For REPL input, the block depth of the top-level statements, whose locals are REPL locals kept
+    // in upvalues of the top-level function.
+    repl_scope: Option<usize>,
+    debug_info: bool,
+    packed_opcodes: bool,
+    cache: CompilerCache<'gc>,
+}
+
+// Per-function limits from `CompilerOptions`.
+#[derive(Copy, Clone, Default)]
+struct FunctionLimits {
+    registers: u16,
+    constants: usize,
+    upvalues: usize,
+    opcodes: usize,
 }
 
 #[derive(Default)]
 struct CompilerFunction<'gc> {
     constants: Vec<Constant<'gc>>,
     constant_table: HashMap<Constant<'gc>, ConstantIndex16>,
+    // Constants that are only ever loaded with `LoadConstant`, which can address the full 16 bit
+    // range.  These are kept separate so that they do not use up the 8 bit constant indexes
+    // available to operands, and are placed after the operand constants when the function is
+    // finished.  `LoadConstant` opcodes are emitted with indexes into this list and patched then.
+    load_constants: Vec<Constant<'gc>>,
+    load_constant_table: HashMap<Constant<'gc>, ConstantIndex16>,
 
     upvalues: Vec<(String<'gc>, UpValueDescriptor)>,
     prototypes: Vec<FunctionProto<'gc>>,
@@ -92,6 +411,14 @@ struct CompilerFunction<'gc> {
     has_varargs: bool,
     fixed_params: u8,
     locals: Vec<(String<'gc>, RegisterIndex)>,
+    // The registers of locals which have been referenced, only tracked when collecting warnings.
+    referenced_locals: HashSet<u8>,
+    // Locals holding a `local function` without varargs, by register, only tracked when collecting
+    // warnings.
+    local_functions: HashMap<u8, LocalFunction>,
+    // Every local declared so far with the range of instructions it is in scope for, only tracked
+    // when keeping debug info.  The end of a local still in scope is not yet known.
+    local_vars: Vec<LocalVarInfo<String<'gc>>>,
 
     blocks: Vec<BlockDescriptor>,
     unique_jump_id: u64,
@@ -99,6 +426,17 @@ struct CompilerFunction<'gc> {
     pending_jumps: Vec<PendingJump<'gc>>,
 
     opcodes: Vec<OpCode>,
+    limits: FunctionLimits,
+}
+
+// The calls made directly to a local function, checked against its parameter count when the local
+// goes out of scope.
+#[derive(Default)]
+struct LocalFunction {
+    parameters: u8,
+    // Set if the local is assigned to or captured, after which its value is unknown.
+    dynamic: bool,
+    call_arguments: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -125,7 +463,11 @@ enum ExprDescriptor<'gc> {
         op: ShortCircuitBinOp,
         right: Box<ExprDescriptor<'gc>>,
     },
-    TableConstructor(Vec<(ExprDescriptor<'gc>, ExprDescriptor<'gc>)>),
+    TableConstructor {
+        // The number of positional fields in `fields`, the rest are keyed fields.
+        array_size: usize,
+        fields: Vec<(ExprDescriptor<'gc>, ExprDescriptor<'gc>)>,
+    },
     TableField {
         table: Box<ExprDescriptor<'gc>>,
         key: Box<ExprDescriptor<'gc>>,
@@ -179,6 +521,16 @@ struct BlockDescriptor {
     owns_upvalues: bool,
 }
 
+// The lengths of everything in a `CompilerFunction` that dead code may add to.
+struct DeadCode {
+    opcodes: usize,
+    constants: usize,
+    load_constants: usize,
+    upvalues: usize,
+    prototypes: usize,
+    local_vars: usize,
+}
+
 #[derive(Debug, Copy, Clone)]
 struct JumpTarget<'gc> {
     label: JumpLabel<'gc>,
@@ -193,8 +545,9 @@ struct JumpTarget<'gc> {
 #[derive(Debug, Copy, Clone)]
 struct PendingJump<'gc> {
     target: JumpLabel<'gc>,
-    // The index of the placeholder jump instruction
-    instruction: usize,
+    // The index of the placeholder jump instruction, or `None` for a jump in dead code that has been
+    // discarded.  Such a jump is still resolved, to check that its target exists, but not patched.
+    instruction: Option<usize>,
     // These are the expected block index and stack top *after* the jump takes place.  These start
     // as the current block index and stack top at the time of the jump, but will be lowered as
     // blocks are exited.
@@ -205,12 +558,86 @@ struct PendingJump<'gc> {
 }
 
 impl<'gc, 'a> Compiler<'gc, 'a> {
+    fn new(
+        mc: MutationContext<'gc, 'a>,
+        chunk: &Chunk<String<'gc>>,
+        options: &CompilerOptions,
+        mut cache: CompilerCache<'gc>,
+    ) -> Result<Compiler<'gc, 'a>, CompilerError> {
+        let limits = FunctionLimits {
+            registers: options.max_registers.min(256),
+            constants: options.max_constants,
+            upvalues: options.max_upvalues,
+            opcodes: options.max_opcodes.min(MAX_OPCODES),
+        };
+        Ok(Compiler {
+            mutation_context: mc,
+            current_function: CompilerFunction::start(&[], true, &chunk.block, limits, &mut cache)?,
+            upper_functions: Vec::new(),
+            warnings: if options.collect_warnings {
+                Some(Vec::new())
+            } else {
+                None
+            },
+            expression_depth: 0,
+            max_expression_depth: options.max_expression_depth,
+            global_opcodes: options.global_opcodes,
+            limits,
+            known_globals: if options.collect_warnings {
+                options.known_globals.cloned()
+            } else {
+                None
+            },
+            repl_scope: None,
+            debug_info: options.debug_info,
+            packed_opcodes: options.packed_opcodes,
+            cache,
+        })
+    }
+
     fn block(&mut self, block: &Block<String<'gc>>) -> Result<(), CompilerError> {
         self.enter_block();
         self.block_statements(block)?;
         self.exit_block()
     }
 
+    // Marks the start of code that can never run, which is still compiled so that it is checked for
+    // errors like any other code, and then discarded by `discard_dead_code`.
+    fn dead_code(&self) -> DeadCode {
+        let function = &self.current_function;
+        DeadCode {
+            opcodes: function.opcodes.len(),
+            constants: function.constants.len(),
+            load_constants: function.load_constants.len(),
+            upvalues: function.upvalues.len(),
+            prototypes: function.prototypes.len(),
+            local_vars: function.local_vars.len(),
+        }
+    }
+
+    // Discards everything emitted since the given `dead_code` mark.  Pending jumps from the dead code
+    // are kept, so that jumps to missing labels are still reported, but are never patched.
+    fn discard_dead_code(&mut self, dead_code: DeadCode) {
+        let function = &mut self.current_function;
+        function.opcodes.truncate(dead_code.opcodes);
+        function.constants.truncate(dead_code.constants);
+        function
+            .constant_table
+            .retain(|_, index| (index.0 as usize) < dead_code.constants);
+        function.load_constants.truncate(dead_code.load_constants);
+        function
+            .load_constant_table
+            .retain(|_, index| (index.0 as usize) < dead_code.load_constants);
+        function.upvalues.truncate(dead_code.upvalues);
+        function.prototypes.truncate(dead_code.prototypes);
+        function.local_vars.truncate(dead_code.local_vars);
+        for pending_jump in &mut function.pending_jumps {
+            if pending_jump.instruction >= Some(dead_code.opcodes) {
+                pending_jump.instruction = None;
+            }
+        }
+    }
+
     fn enter_block(&mut self) {
         self.current_function.blocks.push(BlockDescriptor {
             stack_bottom: self.current_function.register_allocator.stack_top(),
@@ -219,12 +646,62 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         });
     }
 
+    // Brings a local into scope in the given register, from the next instruction onwards.
+    fn push_local(&mut self, name: String<'gc>, reg: RegisterIndex) {
+        if self.debug_info {
+            let start_pc = self.current_function.opcodes.len();
+            self.current_function.local_vars.push(LocalVarInfo {
+                name,
+                reg,
+                start_pc,
+                end_pc: start_pc,
+            });
+        }
+        self.current_function.locals.push((name, reg));
+    }
+
     fn exit_block(&mut self) -> Result<(), CompilerError> {
-        let last_block = self.current_function.blocks.pop().unwrap();
+        let last_block = self
+            .current_function
+            .blocks
+            .pop()
+            .ok_or_else(|| internal_error("no block to exit"))?;
 
-        while let Some((_, last)) = self.current_function.locals.last() {
+        while let Some(&(name, last)) = self.current_function.locals.last() {
             if last.0 as u16 >= last_block.stack_bottom {
-                self.current_function.register_allocator.free(*last);
+                if let Some(warnings) = &mut self.warnings {
+                    if !self.current_function.referenced_locals.remove(&last.0)
+                        && name.as_bytes().first() != Some(&b'_')
+                    {
+                        warnings.push(CompilerWarning::UnusedLocal(name));
+                    }
+                    if let Some(function) = self.current_function.local_functions.remove(&last.0) {
+                        if !function.dynamic {
+                            for arguments in function.call_arguments {
+                                if arguments != function.parameters {
+                                    warnings.push(CompilerWarning::ArgumentCount {
+                                        name,
+                                        parameters: function.parameters,
+                                        arguments,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+                if self.debug_info {
+                    let end_pc = self.current_function.opcodes.len();
+                    if let Some(local) = self
+                        .current_function
+                        .local_vars
+                        .iter_mut()
+                        .rev()
+                        .find(|local| local.reg == last)
+                    {
+                        local.end_pc = end_pc;
+                    }
+                }
+                self.current_function.register_allocator.free(last)?;
                 self.current_function.locals.pop();
             } else {
                 break;
@@ -235,12 +712,12 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             .drain(last_block.bottom_jump_target..);
 
         if last_block.owns_upvalues && !self.current_function.blocks.is_empty() {
-            self.current_function.opcodes.push(OpCode::Jump {
+            self.push_opcode(OpCode::Jump {
                 offset: 0,
                 close_upvalues: cast(last_block.stack_bottom)
                     .and_then(Opt254::try_some)
                     .ok_or(CompilerError::Registers)?,
-            });
+            })?;
         }
 
         // Bring all the pending jumps outward one level, and mark them to close upvalues if this
@@ -251,9 +728,10 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     break;
                 }
                 pending_jump.block_index = self.current_function.blocks.len() - 1;
-                assert!(
-                    pending_jump.stack_top >= self.current_function.register_allocator.stack_top()
-                );
+                check(
+                    pending_jump.stack_top >= self.current_function.register_allocator.stack_top(),
+                    "pending jump is below the stack top of its block",
+                )?;
                 pending_jump.stack_top = self.current_function.register_allocator.stack_top();
                 pending_jump.close_upvalues |= last_block.owns_upvalues;
             }
@@ -329,17 +807,29 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             .map(|arg| self.expression(arg))
             .collect::<Result<Vec<_>, CompilerError>>()?;
 
-        // A return of a single function call is a tail call, and this is the only thing
+        // A return of a single function or method call is a tail call, and this is the only thing
         // in Lua that is considered a tail call
         if returns.len() == 1 {
             match returns.pop().unwrap() {
                 ExprDescriptor::FunctionCall { func, args } => {
+                    self.record_local_function_call(&func, &args);
                     let func = self.expr_discharge(*func, ExprDestination::PushNew)?;
-                    let args = self.push_arguments(args)?;
+                    let args = self.push_arguments(args, CompilerError::Arguments)?;
+                    self.push_opcode(OpCode::TailCall { func, args })?;
+                    self.current_function.register_allocator.free(func)?;
+
+                    return Ok(());
+                }
+                ExprDescriptor::MethodCall {
+                    table,
+                    method,
+                    args,
+                } => {
+                    let (base, args) = self.push_method_call(*table, *method, args)?;
+                    self.push_opcode(OpCode::TailCall { func: base, args })?;
                     self.current_function
-                        .opcodes
-                        .push(OpCode::TailCall { func, args });
-                    self.current_function.register_allocator.free(func);
+                        .register_allocator
+                        .pop_to(base.0 as u16);
 
                     return Ok(());
                 }
@@ -349,13 +839,14 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             }
         }
 
-        let count = self.push_arguments(returns)?;
-        self.current_function.opcodes.push(OpCode::Return {
+        let count = self.push_arguments(returns, CompilerError::Returns)?;
+        self.push_opcode(OpCode::Return {
             start: RegisterIndex(
-                cast(self.current_function.register_allocator.stack_top()).unwrap(),
+                cast(self.current_function.register_allocator.stack_top())
+                    .ok_or(CompilerError::Registers)?,
             ),
             count,
-        });
+        })?;
 
         Ok(())
     }
@@ -364,34 +855,92 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         &mut self,
         if_statement: &IfStatement<String<'gc>>,
     ) -> Result<(), CompilerError> {
-        let end_label = self.unique_jump_label();
-        let mut next_label = self.unique_jump_label();
+        // The jumps here are all forward jumps to targets that nothing else can refer to, so rather
+        // than going through jump labels they are emitted as placeholders and patched directly.
+        // This keeps long elseif chains linear, as no jump targets or pending jumps accumulate.
+        let mut end_jumps = Vec::new();
+        let mut next_jumps = Vec::new();
 
         for (i, (if_expr, block)) in iter::once(&if_statement.if_part)
             .chain(&if_statement.else_if_parts)
             .enumerate()
         {
-            self.jump_target(next_label)?;
-            next_label = self.unique_jump_label();
-
-            let if_expr = self.expression(if_expr)?;
-            self.expr_test(if_expr, true)?;
-            self.jump(next_label)?;
+            for next_jump in next_jumps.drain(..) {
+                self.patch_jump_here(next_jump)?;
+            }
+
+            // A branch with a constant false condition is never taken and its code is discarded, and
+            // a branch with a constant true condition is always taken, so the code for the branches
+            // after it is discarded as well.
+            let always_taken = match self.expression(if_expr)? {
+                ExprDescriptor::Constant(constant) => {
+                    if !constant.to_value().to_bool() {
+                        let dead_code = self.dead_code();
+                        self.block(block)?;
+                        self.discard_dead_code(dead_code);
+                        continue;
+                    }
+                    true
+                }
+                if_expr => {
+                    next_jumps = self.expr_test_jumps(if_expr, false)?;
+                    false
+                }
+            };
 
             self.enter_block();
             self.block_statements(block)?;
-            if i != if_statement.else_if_parts.len() || if_statement.else_part.is_some() {
-                self.jump(end_label)?;
+            // A block ending in a return statement can never fall through, so there is no need to
+            // jump past the remaining branches.
+            if block.return_statement.is_none()
+                && !always_taken
+                && (i != if_statement.else_if_parts.len() || if_statement.else_part.is_some())
+            {
+                let block = self.current_function.blocks.last().unwrap();
+                let close_upvalues = if block.owns_upvalues {
+                    cast(block.stack_bottom)
+                        .and_then(Opt254::try_some)
+                        .ok_or(CompilerError::Registers)?
+                } else {
+                    Opt254::none()
+                };
+                end_jumps.push(self.current_function.opcodes.len());
+                self.push_opcode(OpCode::Jump {
+                    offset: 0,
+                    close_upvalues,
+                })?;
             }
             self.exit_block()?;
+
+            if always_taken {
+                for end_jump in end_jumps {
+                    self.patch_jump_here(end_jump)?;
+                }
+
+                let dead_code = self.dead_code();
+                for (if_expr, block) in &if_statement.else_if_parts[i..] {
+                    let if_expr = self.expression(if_expr)?;
+                    self.expr_test_jumps(if_expr, false)?;
+                    self.block(block)?;
+                }
+                if let Some(else_block) = &if_statement.else_part {
+                    self.block(else_block)?;
+                }
+                self.discard_dead_code(dead_code);
+                return Ok(());
+            }
         }
 
-        self.jump_target(next_label)?;
+        for next_jump in next_jumps {
+            self.patch_jump_here(next_jump)?;
+        }
         if let Some(else_block) = &if_statement.else_part {
             self.block(else_block)?;
         }
 
-        self.jump_target(end_label)?;
+        for end_jump in end_jumps {
+            self.patch_jump_here(end_jump)?;
+        }
 
         Ok(())
     }
@@ -409,22 +958,33 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                 body,
             } => {
                 let initial = self.expression(initial)?;
-                let base = self.expr_discharge(initial, ExprDestination::PushNew)?;
-
                 let limit = self.expression(limit)?;
-                self.expr_discharge(limit, ExprDestination::PushNew)?;
-
                 let step = if let Some(step) = step {
                     self.expression(step)?
                 } else {
                     ExprDescriptor::Constant(Constant::Integer(1))
                 };
+
+                // A loop over an empty constant integer range never runs its body, and constant
+                // control expressions have no side effects, so the whole loop can be skipped.
+                if let (
+                    ExprDescriptor::Constant(Constant::Integer(initial)),
+                    ExprDescriptor::Constant(Constant::Integer(limit)),
+                    ExprDescriptor::Constant(Constant::Integer(step)),
+                ) = (&initial, &limit, &step)
+                {
+                    if (*step > 0 && initial > limit) || (*step < 0 && initial < limit) {
+                        return Ok(());
+                    }
+                }
+
+                let step_one = matches!(step, ExprDescriptor::Constant(Constant::Integer(1)));
+                let base = self.expr_discharge(initial, ExprDestination::PushNew)?;
+                self.expr_discharge(limit, ExprDestination::PushNew)?;
                 self.expr_discharge(step, ExprDestination::PushNew)?;
 
                 let for_prep_index = self.current_function.opcodes.len();
-                self.current_function
-                    .opcodes
-                    .push(OpCode::NumericForPrep { base, jump: 0 });
+                self.push_opcode(OpCode::NumericForPrep { base, jump: 0 })?;
 
                 self.enter_block();
                 self.enter_block();
@@ -434,30 +994,37 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     .register_allocator
                     .push(1)
                     .ok_or(CompilerError::Registers)?;
-                self.current_function.locals.push((*name, loop_var));
+                self.push_local(*name, loop_var);
 
                 self.block_statements(body)?;
                 self.exit_block()?;
 
                 let for_loop_index = self.current_function.opcodes.len();
-                self.current_function.opcodes.push(OpCode::NumericForLoop {
-                    base: RegisterIndex(base.0),
-                    jump: jump_offset(for_loop_index, for_prep_index + 1)
-                        .ok_or(CompilerError::JumpOverflow)?,
-                });
+                let jump = jump_offset(for_loop_index, for_prep_index + 1)
+                    .ok_or(CompilerError::JumpOverflow)?;
+                self.push_opcode(if step_one {
+                    OpCode::NumericForIncLoop { base, jump }
+                } else {
+                    OpCode::NumericForLoop { base, jump }
+                })?;
                 match &mut self.current_function.opcodes[for_prep_index] {
                     OpCode::NumericForPrep {
                         base: prep_base,
                         jump,
                     } => {
-                        assert!(
+                        check(
                             *prep_base == base && *jump == 0,
-                            "instruction is not placeholder NumericForPrep"
-                        );
-                        *jump = jump_offset(for_prep_index, for_loop_index)
+                            "instruction is not placeholder NumericForPrep",
+                        )?;
+                        *jump = jump_offset(for_prep_index, for_loop_index + 1)
                             .ok_or(CompilerError::JumpOverflow)?;
                     }
-                    _ => panic!("instruction is not placeholder NumericForPrep"),
+                    _ => {
+                        return Err(CompilerError::Internal(format!(
+                            "instruction {} is not placeholder NumericForPrep",
+                            for_prep_index
+                        )))
+                    }
                 }
 
                 self.jump_target(JumpLabel::Break)?;
@@ -475,30 +1042,8 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             } => {
                 let loop_label = self.unique_jump_label();
 
-                assert!(arguments.len() >= 1);
-                let base = if arguments.len() == 1 {
-                    let args = self.expression(&arguments[0])?;
-                    self.expr_push_count(args, 3)?
-                } else {
-                    let iterator = self.expression(&arguments[0])?;
-                    let top = self.expr_discharge(iterator, ExprDestination::PushNew)?;
-
-                    let state = if let Some(state) = arguments.get(1) {
-                        self.expression(state)?
-                    } else {
-                        ExprDescriptor::Constant(Constant::Nil)
-                    };
-                    self.expr_discharge(state, ExprDestination::PushNew)?;
-
-                    let control = if let Some(control) = arguments.get(2) {
-                        self.expression(control)?
-                    } else {
-                        ExprDescriptor::Constant(Constant::Nil)
-                    };
-                    self.expr_discharge(control, ExprDestination::PushNew)?;
-
-                    top
-                };
+                check(!arguments.is_empty(), "generic for loop has no arguments")?;
+                let base = self.push_generic_for_arguments(arguments, 3)?;
 
                 self.enter_block();
                 self.enter_block();
@@ -510,9 +1055,7 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     .push(name_count)
                     .ok_or(CompilerError::Registers)?;
                 for i in 0..name_count {
-                    self.current_function
-                        .locals
-                        .push((names[i as usize], RegisterIndex(names_reg.0 + i)));
+                    self.push_local(names[i as usize], RegisterIndex(names_reg.0 + i));
                 }
 
                 self.jump(loop_label)?;
@@ -522,15 +1065,15 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                 self.exit_block()?;
 
                 self.jump_target(loop_label)?;
-                self.current_function.opcodes.push(OpCode::GenericForCall {
+                self.push_opcode(OpCode::GenericForCall {
                     base,
                     var_count: cast(names.len()).ok_or(CompilerError::Registers)?,
-                });
+                })?;
                 let loop_inst = self.current_function.opcodes.len();
-                self.current_function.opcodes.push(OpCode::GenericForLoop {
+                self.push_opcode(OpCode::GenericForLoop {
                     base: RegisterIndex(base.0 + 2),
                     jump: jump_offset(loop_inst, start_inst).ok_or(CompilerError::JumpOverflow)?,
-                });
+                })?;
 
                 self.jump_target(JumpLabel::Break)?;
                 self.exit_block()?;
@@ -552,8 +1095,22 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
 
         self.jump_target(start_label)?;
         let condition = self.expression(&while_statement.condition)?;
-        self.expr_test(condition, true)?;
-        self.jump(end_label)?;
+        // A constant condition needs no test.  The code for a loop that never runs is discarded, and
+        // a loop that always runs only ends through a `break`, `goto` or `return`.
+        let dead_code = match condition {
+            ExprDescriptor::Constant(constant) => {
+                if constant.to_value().to_bool() {
+                    None
+                } else {
+                    Some(self.dead_code())
+                }
+            }
+            condition => {
+                self.expr_test(condition, true)?;
+                self.jump(end_label)?;
+                None
+            }
+        };
 
         self.enter_block();
 
@@ -563,6 +1120,9 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         self.jump_target(JumpLabel::Break)?;
         self.exit_block()?;
 
+        if let Some(dead_code) = dead_code {
+            self.discard_dead_code(dead_code);
+        }
         self.jump_target(end_label)?;
         Ok(())
     }
@@ -616,17 +1176,11 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     key: Box::new(ExprDescriptor::Constant(Constant::String(name))),
                 }
             } else {
-                ExprDescriptor::Variable(self.find_variable(name)?)
+                ExprDescriptor::Variable(self.read_variable(name)?)
             });
             name = *field;
         }
 
-        let table = if let Some(table) = table {
-            table
-        } else {
-            self.get_environment()?
-        };
-
         let proto = if function_statement.method.is_some() {
             let mut parameters = vec![String::new_static(b"self")];
             parameters.extend(&function_statement.definition.parameters);
@@ -644,62 +1198,98 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             )?
         };
 
-        self.set_table(
-            table,
-            ExprDescriptor::Constant(Constant::String(name)),
-            ExprDescriptor::Closure(proto),
-        )?;
+        if let Some(table) = table {
+            self.set_table(
+                table,
+                ExprDescriptor::Constant(Constant::String(name)),
+                ExprDescriptor::Closure(proto),
+            )?;
+        } else {
+            // A plain `function name()` assigns to the variable `name`, which may be a local or an
+            // upvalue rather than a global.
+            self.set_variable(name, ExprDescriptor::Closure(proto))?;
+        }
 
         Ok(())
     }
 
+    // Pushes the generic for arguments adjusted to exactly `count` values, expanding a trailing
+    // multi-value expression the same way as a local statement.  Returns the first register.
+    fn push_generic_for_arguments(
+        &mut self,
+        arguments: &[Expression<String<'gc>>],
+        count: u8,
+    ) -> Result<RegisterIndex, CompilerError> {
+        let count = count as usize;
+        let mut base = None;
+        for (i, argument) in arguments.iter().enumerate() {
+            let expr = self.expression(argument)?;
+            if i >= count {
+                let reg = self.expr_discharge(expr, ExprDestination::AllocateNew)?;
+                self.current_function.register_allocator.free(reg)?;
+                continue;
+            }
+
+            let reg = if i == arguments.len() - 1 {
+                let left = cast(count - i).ok_or(CompilerError::Registers)?;
+                self.expr_push_count(expr, left)?
+            } else {
+                self.expr_discharge(expr, ExprDestination::PushNew)?
+            };
+            base.get_or_insert(reg);
+        }
+        base.ok_or_else(|| internal_error("generic for loop has no arguments"))
+    }
+
     fn local_statement(
         &mut self,
         local_statement: &LocalStatement<String<'gc>>,
     ) -> Result<(), CompilerError> {
         let name_len = local_statement.names.len();
         let val_len = local_statement.values.len();
+        let top = self.current_function.register_allocator.stack_top();
 
-        if local_statement.values.is_empty() {
+        let registers = if local_statement.values.is_empty() {
             let count = cast(name_len).ok_or(CompilerError::Registers)?;
             let dest = self
                 .current_function
                 .register_allocator
                 .push(count)
                 .ok_or(CompilerError::Registers)?;
-            self.current_function
-                .opcodes
-                .push(OpCode::LoadNil { dest, count });
-            for i in 0..name_len {
-                self.current_function
-                    .locals
-                    .push((local_statement.names[i], RegisterIndex(dest.0 + i as u8)));
-            }
+            self.push_opcode(OpCode::LoadNil { dest, count })?;
+            (0..count).map(|i| RegisterIndex(dest.0 + i)).collect()
         } else {
+            // The new locals only come into scope after the statement, so they are not added
+            // until every value has been evaluated.
+            let mut registers = Vec::with_capacity(name_len);
             for i in 0..val_len {
                 let expr = self.expression(&local_statement.values[i])?;
 
                 if i >= name_len {
                     let reg = self.expr_discharge(expr, ExprDestination::AllocateNew)?;
-                    self.current_function.register_allocator.free(reg);
+                    self.current_function.register_allocator.free(reg)?;
                 } else if i == val_len - 1 {
                     let names_left =
                         cast(1 + name_len - val_len).ok_or(CompilerError::Registers)?;
                     let dest = self.expr_push_count(expr, names_left)?;
-
-                    for j in 0..names_left {
-                        self.current_function.locals.push((
-                            local_statement.names[val_len - 1 + j as usize],
-                            RegisterIndex(dest.0 + j),
-                        ));
-                    }
+                    registers.extend((0..names_left).map(|j| RegisterIndex(dest.0 + j)));
                 } else {
-                    let reg = self.expr_discharge(expr, ExprDestination::PushNew)?;
-                    self.current_function
-                        .locals
-                        .push((local_statement.names[i], reg));
+                    registers.push(self.expr_discharge(expr, ExprDestination::PushNew)?);
                 }
             }
+            registers
+        };
+
+        if self.in_repl_scope() {
+            for (&name, source) in local_statement.names.iter().zip(registers) {
+                let dest = self.push_repl_local(name)?;
+                self.push_opcode(OpCode::SetUpValue { dest, source })?;
+            }
+            self.current_function.register_allocator.pop_to(top);
+        } else {
+            for (&name, reg) in local_statement.names.iter().zip(registers) {
+                self.push_local(name, reg);
+            }
         }
 
         Ok(())
@@ -740,7 +1330,12 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     ) -> Result<(), CompilerError> {
         let target_len = assignment.targets.len();
         let val_len = assignment.values.len();
-        assert!(val_len != 0);
+        check(val_len != 0, "assignment has no values")?;
+
+        enum EvaluatedTarget<'gc> {
+            Name(String<'gc>),
+            Field(RegisterIndex, ExprDescriptor<'gc>),
+        }
 
         fn assign<'gc, 'a, 's>(
             this: &'s mut Compiler<'gc, 'a>,
@@ -748,25 +1343,7 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             expr: ExprDescriptor<'gc>,
         ) -> Result<(), CompilerError> {
             match target {
-                AssignmentTarget::Name(name) => match this.find_variable(*name)? {
-                    VariableDescriptor::Local(dest) => {
-                        this.expr_discharge(expr, ExprDestination::Register(dest))?;
-                    }
-                    VariableDescriptor::UpValue(dest) => {
-                        let (source, source_is_temp) = this.expr_any_register(expr)?;
-                        this.current_function
-                            .opcodes
-                            .push(OpCode::SetUpValue { source, dest });
-                        if source_is_temp {
-                            this.current_function.register_allocator.free(source);
-                        }
-                    }
-                    VariableDescriptor::Global(name) => {
-                        let env = this.get_environment()?;
-                        let key = ExprDescriptor::Constant(Constant::String(name));
-                        this.set_table(env, key, expr)?;
-                    }
-                },
+                AssignmentTarget::Name(name) => this.set_variable(*name, expr)?,
 
                 AssignmentTarget::Field(table, field) => {
                     let table = this.suffixed_expression(table)?;
@@ -782,69 +1359,216 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             Ok(())
         }
 
-        for i in 0..val_len {
-            let expr = self.expression(&assignment.values[i])?;
+        if target_len > 1 {
+            // With multiple targets, every value must be evaluated before any target is assigned,
+            // so that `a, b = b, a` sees the old values.  Tables and keys of field targets are
+            // evaluated first into fresh registers as well, so that assigning an earlier target
+            // cannot change which field a later target refers to.
+            let top = self.current_function.register_allocator.stack_top();
+
+            let mut targets = Vec::with_capacity(target_len);
+            for target in &assignment.targets {
+                targets.push(match target {
+                    AssignmentTarget::Name(name) => EvaluatedTarget::Name(*name),
+                    AssignmentTarget::Field(table, field) => {
+                        let table = self.suffixed_expression(table)?;
+                        let table = self.expr_discharge(table, ExprDestination::PushNew)?;
+                        let key = match field {
+                            FieldSuffix::Named(name) => {
+                                ExprDescriptor::Constant(Constant::String(*name))
+                            }
+                            FieldSuffix::Indexed(idx) => match self.expression(idx)? {
+                                ExprDescriptor::Constant(key) => ExprDescriptor::Constant(key),
+                                key => ExprDescriptor::Variable(VariableDescriptor::Local(
+                                    self.expr_discharge(key, ExprDestination::PushNew)?,
+                                )),
+                            },
+                        };
+                        EvaluatedTarget::Field(table, key)
+                    }
+                });
+            }
 
-            if i >= target_len {
-                let reg = self.expr_discharge(expr, ExprDestination::AllocateNew)?;
-                self.current_function.register_allocator.free(reg);
-            } else if i == val_len - 1 {
-                let top = self.current_function.register_allocator.stack_top();
-
-                let targets_left =
-                    cast(1 + target_len - val_len).ok_or(CompilerError::Registers)?;
-                let results = self.expr_push_count(expr, targets_left)?;
-
-                for j in 0..targets_left {
-                    let expr = ExprDescriptor::Variable(VariableDescriptor::Local(RegisterIndex(
-                        results.0 + j,
-                    )));
-                    assign(self, &assignment.targets[val_len - 1 + j as usize], expr)?;
+            let mut values = Vec::with_capacity(target_len);
+            for i in 0..val_len {
+                let expr = self.expression(&assignment.values[i])?;
+                if i >= target_len {
+                    let reg = self.expr_discharge(expr, ExprDestination::AllocateNew)?;
+                    self.current_function.register_allocator.free(reg)?;
+                } else if i == val_len - 1 {
+                    let targets_left =
+                        cast(1 + target_len - val_len).ok_or(CompilerError::Registers)?;
+                    let results = self.expr_push_count(expr, targets_left)?;
+                    for j in 0..targets_left {
+                        values.push(RegisterIndex(results.0 + j));
+                    }
+                } else {
+                    values.push(self.expr_discharge(expr, ExprDestination::PushNew)?);
                 }
+            }
 
-                self.current_function.register_allocator.pop_to(top);
-            } else {
-                assign(self, &assignment.targets[i], expr)?;
+            for (target, value) in targets.into_iter().zip(values) {
+                let value = ExprDescriptor::Variable(VariableDescriptor::Local(value));
+                match target {
+                    EvaluatedTarget::Name(name) => {
+                        assign(self, &AssignmentTarget::Name(name), value)?;
+                    }
+                    EvaluatedTarget::Field(table, key) => {
+                        let table = ExprDescriptor::Variable(VariableDescriptor::Local(table));
+                        self.set_table(table, key, value)?;
+                    }
+                }
             }
+
+            self.current_function.register_allocator.pop_to(top);
+            return Ok(());
         }
 
-        Ok(())
-    }
+        // With a single target, the value can be assigned directly unless there are extra values,
+        // which must still be evaluated before the assignment takes place.
+        let target = &assignment.targets[0];
+        let value = self.expression(&assignment.values[0])?;
+        if val_len == 1 {
+            return assign(self, target, value);
+        }
 
-    fn local_function_statement(
-        &mut self,
-        local_function: &LocalFunctionStatement<String<'gc>>,
-    ) -> Result<(), CompilerError> {
-        let proto = self.new_prototype(
-            &local_function.definition.parameters,
-            local_function.definition.has_varargs,
-            &local_function.definition.body,
+        let top = self.current_function.register_allocator.stack_top();
+        let value = self.expr_discharge(value, ExprDestination::PushNew)?;
+        for extra in &assignment.values[1..] {
+            let expr = self.expression(extra)?;
+            let reg = self.expr_discharge(expr, ExprDestination::AllocateNew)?;
+            self.current_function.register_allocator.free(reg)?;
+        }
+        assign(
+            self,
+            target,
+            ExprDescriptor::Variable(VariableDescriptor::Local(value)),
         )?;
-
-        let dest = self
-            .current_function
-            .register_allocator
-            .push(1)
-            .ok_or(CompilerError::Registers)?;
-        self.current_function
-            .opcodes
-            .push(OpCode::Closure { proto, dest });
-        self.current_function
-            .locals
-            .push((local_function.name, dest));
+        self.current_function.register_allocator.pop_to(top);
 
         Ok(())
     }
 
-    fn expression(
+    // Assign the given expression to the variable with the given name, whether it is a local, an
+    // upvalue, or a global.
+    fn set_variable(
         &mut self,
-        expression: &Expression<String<'gc>>,
-    ) -> Result<ExprDescriptor<'gc>, CompilerError> {
+        name: String<'gc>,
+        expr: ExprDescriptor<'gc>,
+    ) -> Result<(), CompilerError> {
+        match self.find_variable(name, false)? {
+            VariableDescriptor::Local(dest) => {
+                if let Some(function) = self.current_function.local_functions.get_mut(&dest.0) {
+                    function.dynamic = true;
+                }
+                // Table constructors and `and` / `or` write to their destination before they are
+                // fully evaluated, so they cannot be built in place if they read the local itself.
+                let in_place = match &expr {
+                    ExprDescriptor::TableConstructor { .. }
+                    | ExprDescriptor::ShortCircuitBinOp { .. } => !expr_reads_register(&expr, dest),
+                    _ => true,
+                };
+                if in_place {
+                    self.expr_discharge(expr, ExprDestination::Register(dest))?;
+                } else {
+                    let source = self.expr_discharge(expr, ExprDestination::AllocateNew)?;
+                    self.push_opcode(OpCode::Move { dest, source })?;
+                    self.current_function.register_allocator.free(source)?;
+                }
+            }
+            VariableDescriptor::UpValue(dest) => {
+                let (source, source_is_temp) = self.expr_any_register(expr)?;
+                self.push_opcode(OpCode::SetUpValue { source, dest })?;
+                if source_is_temp {
+                    self.current_function.register_allocator.free(source)?;
+                }
+            }
+            VariableDescriptor::Global(name) => {
+                let env = self.get_environment()?;
+                if let Some((env, name)) = self.global_operands(&env, name)? {
+                    let (value, value_is_temp) = self.expr_any_register(expr)?;
+                    self.push_opcode(OpCode::SetGlobal { env, name, value })?;
+                    if value_is_temp {
+                        self.current_function.register_allocator.free(value)?;
+                    }
+                } else {
+                    let key = ExprDescriptor::Constant(Constant::String(name));
+                    self.set_table(env, key, expr)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn local_function_statement(
+        &mut self,
+        local_function: &LocalFunctionStatement<String<'gc>>,
+    ) -> Result<(), CompilerError> {
+        if self.in_repl_scope() {
+            let dest = self.push_repl_local(local_function.name)?;
+            let proto = self.new_prototype(
+                &local_function.definition.parameters,
+                local_function.definition.has_varargs,
+                &local_function.definition.body,
+            )?;
+            let source = self
+                .current_function
+                .register_allocator
+                .allocate()
+                .ok_or(CompilerError::Registers)?;
+            self.push_opcode(OpCode::Closure {
+                proto,
+                dest: source,
+            })?;
+            self.push_opcode(OpCode::SetUpValue { dest, source })?;
+            self.current_function.register_allocator.free(source)?;
+            return Ok(());
+        }
+
+        // The local is in scope inside its own body, so that the function can refer to itself
+        let dest = self
+            .current_function
+            .register_allocator
+            .push(1)
+            .ok_or(CompilerError::Registers)?;
+        self.push_local(local_function.name, dest);
+        if self.warnings.is_some() && !local_function.definition.has_varargs {
+            if let Some(parameters) = cast(local_function.definition.parameters.len()) {
+                self.current_function.local_functions.insert(
+                    dest.0,
+                    LocalFunction {
+                        parameters,
+                        ..LocalFunction::default()
+                    },
+                );
+            }
+        }
+
+        let proto = self.new_prototype(
+            &local_function.definition.parameters,
+            local_function.definition.has_varargs,
+            &local_function.definition.body,
+        )?;
+        self.push_opcode(OpCode::Closure { proto, dest })?;
+
+        Ok(())
+    }
+
+    fn expression(
+        &mut self,
+        expression: &Expression<String<'gc>>,
+    ) -> Result<ExprDescriptor<'gc>, CompilerError> {
+        let outer_depth = mem::replace(&mut self.expression_depth, 0);
         let mut expr = self.head_expression(&expression.head)?;
         for (binop, right) in &expression.tail {
             let right = self.expression(&right)?;
             expr = self.binary_operator_expression(expr, *binop, right)?;
+            if !matches!(expr, ExprDescriptor::Constant(_)) {
+                self.nest_expression()?;
+            }
         }
+        self.nest_expression()?;
+        self.expression_depth = self.expression_depth.max(outer_depth);
         Ok(expr)
     }
 
@@ -905,15 +1629,21 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                 ),
             });
         }
-        Ok(ExprDescriptor::TableConstructor(fields))
+        Ok(ExprDescriptor::TableConstructor {
+            array_size: array_index as usize,
+            fields,
+        })
     }
 
     fn function_expression(
         &mut self,
         function: &FunctionDefinition<String<'gc>>,
     ) -> Result<ExprDescriptor<'gc>, CompilerError> {
+        // Expressions inside the function body are unrelated to the one containing the function.
+        let depth = mem::replace(&mut self.expression_depth, 0);
         let proto =
             self.new_prototype(&function.parameters, function.has_varargs, &function.body)?;
+        self.expression_depth = depth;
         Ok(ExprDescriptor::Closure(proto))
     }
 
@@ -923,6 +1653,7 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     ) -> Result<ExprDescriptor<'gc>, CompilerError> {
         let mut expr = self.primary_expression(&suffixed_expression.primary)?;
         for suffix in &suffixed_expression.suffixes {
+            self.nest_expression()?;
             match suffix {
                 SuffixPart::Field(field) => {
                     let key = match field {
@@ -970,7 +1701,7 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     ) -> Result<ExprDescriptor<'gc>, CompilerError> {
         match primary_expression {
             PrimaryExpression::Name(name) => {
-                Ok(ExprDescriptor::Variable(self.find_variable(*name)?))
+                Ok(ExprDescriptor::Variable(self.read_variable(*name)?))
             }
             PrimaryExpression::GroupedExpression(expr) => self.expression(expr),
         }
@@ -987,6 +1718,24 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             }
         }
 
+        // The length of a constructor made only of positional non-nil constants is known statically,
+        // and building the table has no side effects.
+        if let (UnaryOperator::Len, ExprDescriptor::TableConstructor { array_size, fields }) =
+            (unop, &expr)
+        {
+            if *array_size == fields.len()
+                && fields.iter().all(|(_, value)| match value {
+                    ExprDescriptor::Constant(Constant::Nil) => false,
+                    ExprDescriptor::Constant(_) => true,
+                    _ => false,
+                })
+            {
+                return Ok(ExprDescriptor::Constant(Constant::Integer(
+                    *array_size as i64,
+                )));
+            }
+        }
+
         Ok(ExprDescriptor::UnaryOperator {
             op: unop,
             expr: Box::new(expr),
@@ -1036,26 +1785,48 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                 right: Box::new(right),
             }),
 
-            BinOpCategory::Concat => Ok(match (left, right) {
-                (ExprDescriptor::Concat(mut left), ExprDescriptor::Concat(right)) => {
-                    left.extend(right);
-                    ExprDescriptor::Concat(left)
-                }
-                (ExprDescriptor::Concat(mut left), right) => {
-                    left.push_back(right);
-                    ExprDescriptor::Concat(left)
-                }
-                (left, ExprDescriptor::Concat(mut right)) => {
-                    right.push_front(left);
-                    ExprDescriptor::Concat(right)
-                }
-                (left, right) => {
-                    let mut exprs = VecDeque::new();
-                    exprs.push_back(left);
-                    exprs.push_back(right);
-                    ExprDescriptor::Concat(exprs)
+            BinOpCategory::Concat => {
+                let exprs = match (left, right) {
+                    (ExprDescriptor::Concat(mut left), ExprDescriptor::Concat(right)) => {
+                        left.extend(right);
+                        left
+                    }
+                    (ExprDescriptor::Concat(mut left), right) => {
+                        left.push_back(right);
+                        left
+                    }
+                    (left, ExprDescriptor::Concat(mut right)) => {
+                        right.push_front(left);
+                        right
+                    }
+                    (left, right) => {
+                        let mut exprs = VecDeque::new();
+                        exprs.push_back(left);
+                        exprs.push_back(right);
+                        exprs
+                    }
+                };
+
+                // Collapse every run of adjacent constant operands into a single constant string
+                let mut folded = VecDeque::with_capacity(exprs.len());
+                for expr in exprs {
+                    if let (Some(ExprDescriptor::Constant(a)), ExprDescriptor::Constant(b)) =
+                        (folded.back_mut(), &expr)
+                    {
+                        if let Some(v) = concat_const_fold(self.mutation_context, *a, *b) {
+                            *a = v;
+                            continue;
+                        }
+                    }
+                    folded.push_back(expr);
                 }
-            }),
+
+                Ok(if folded.len() == 1 {
+                    folded.pop_front().unwrap()
+                } else {
+                    ExprDescriptor::Concat(folded)
+                })
+            }
         }
     }
 
@@ -1067,24 +1838,96 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     ) -> Result<PrototypeIndex, CompilerError> {
         let old_current = mem::replace(
             &mut self.current_function,
-            CompilerFunction::start(parameters, has_varargs)?,
+            CompilerFunction::start(parameters, has_varargs, body, self.limits, &mut self.cache)?,
         );
+        if self.debug_info {
+            for (&name, reg) in parameters.iter().zip(0..) {
+                self.current_function.local_vars.push(LocalVarInfo {
+                    name,
+                    reg: RegisterIndex(reg),
+                    start_pc: 0,
+                    end_pc: 0,
+                });
+            }
+        }
         self.upper_functions.push(old_current);
         self.block(body)?;
         let proto = mem::replace(
             &mut self.current_function,
-            self.upper_functions.pop().unwrap(),
+            self.upper_functions
+                .pop()
+                .ok_or_else(|| internal_error("no upper function"))?,
         )
-        .finish(self.mutation_context)?;
+        .finish(
+            self.mutation_context,
+            self.debug_info,
+            self.packed_opcodes,
+            &mut self.cache,
+        )?;
         self.current_function.prototypes.push(proto);
         Ok(PrototypeIndex(
             cast(self.current_function.prototypes.len() - 1).ok_or(CompilerError::Functions)?,
         ))
     }
 
+    // Compiles the body of the top-level function of a chunk and finishes it.
+    fn main_function(
+        &mut self,
+        block: &Block<String<'gc>>,
+        always_emit_env: bool,
+    ) -> Result<FunctionProto<'gc>, CompilerError> {
+        if always_emit_env {
+            self.current_function.push_upvalue(
+                String::new_static(b"_ENV"),
+                UpValueDescriptor::Environment,
+                0,
+            )?;
+        }
+        self.block(block)?;
+        mem::take(&mut self.current_function).finish(
+            self.mutation_context,
+            self.debug_info,
+            self.packed_opcodes,
+            &mut self.cache,
+        )
+    }
+
+    // Locals declared directly in the top-level block of REPL input are REPL locals.
+    fn in_repl_scope(&self) -> bool {
+        self.upper_functions.is_empty()
+            && self.repl_scope == Some(self.current_function.blocks.len())
+    }
+
+    // Brings a REPL local into scope as the next upvalue of the top-level function.
+    fn push_repl_local(&mut self, name: String<'gc>) -> Result<UpValueIndex, CompilerError> {
+        let index = self.current_function.upvalues.len();
+        let index = UpValueIndex(cast(index).ok_or_else(|| upvalue_overflow(name, 0))?);
+        self.current_function
+            .push_upvalue(name, UpValueDescriptor::Outer(index), 0)
+    }
+
+    // Like `find_variable`, for a variable which is read rather than assigned.
+    fn read_variable(
+        &mut self,
+        name: String<'gc>,
+    ) -> Result<VariableDescriptor<'gc>, CompilerError> {
+        let variable = self.find_variable(name, true)?;
+        if let (VariableDescriptor::Global(name), Some(known_globals), Some(warnings)) =
+            (&variable, &self.known_globals, &mut self.warnings)
+        {
+            if !known_globals.contains(name.as_bytes()) {
+                warnings.push(CompilerWarning::UnknownGlobal(*name));
+            }
+        }
+        Ok(variable)
+    }
+
+    // Only a variable which is `read` counts as a reference to a local, assigning to a local does
+    // not.
     fn find_variable(
         &mut self,
         name: String<'gc>,
+        read: bool,
     ) -> Result<VariableDescriptor<'gc>, CompilerError> {
         // We need to be able to index functions from the top-level chunk function (index 0), up to
         // the current function
@@ -1104,6 +1947,18 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             for j in (0..get_function(self, i).locals.len()).rev() {
                 let (local_name, register) = get_function(self, i).locals[j];
                 if name == local_name {
+                    if self.warnings.is_some() {
+                        if read {
+                            get_function(self, i).referenced_locals.insert(register.0);
+                        }
+                        if i != current_function {
+                            if let Some(function) =
+                                get_function(self, i).local_functions.get_mut(&register.0)
+                            {
+                                function.dynamic = true;
+                            }
+                        }
+                    }
                     if i == current_function {
                         return Ok(VariableDescriptor::Local(register));
                     } else {
@@ -1117,21 +1972,17 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                             }
                         }
 
-                        get_function(self, i + 1)
-                            .upvalues
-                            .push((name, UpValueDescriptor::ParentLocal(register)));
-                        let mut upvalue_index = UpValueIndex(
-                            cast(get_function(self, i + 1).upvalues.len() - 1)
-                                .ok_or(CompilerError::UpValues)?,
-                        );
+                        let mut upvalue_index = get_function(self, i + 1).push_upvalue(
+                            name,
+                            UpValueDescriptor::ParentLocal(register),
+                            i + 1,
+                        )?;
                         for k in i + 2..=current_function {
-                            get_function(self, k)
-                                .upvalues
-                                .push((name, UpValueDescriptor::Outer(upvalue_index)));
-                            upvalue_index = UpValueIndex(
-                                cast(get_function(self, k).upvalues.len() - 1)
-                                    .ok_or(CompilerError::UpValues)?,
-                            );
+                            upvalue_index = get_function(self, k).push_upvalue(
+                                name,
+                                UpValueDescriptor::Outer(upvalue_index),
+                                k,
+                            )?;
                         }
                         return Ok(VariableDescriptor::UpValue(upvalue_index));
                     }
@@ -1141,26 +1992,24 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             // The top-level function has an implicit _ENV upvalue (this is the only upvalue it can
             // have), and we add it if it is ever referenced.
             if i == 0 && name == b"_ENV" && get_function(self, i).upvalues.is_empty() {
-                get_function(self, 0)
-                    .upvalues
-                    .push((name, UpValueDescriptor::Environment));
+                get_function(self, 0).push_upvalue(name, UpValueDescriptor::Environment, 0)?;
             }
 
-            for j in 0..get_function(self, i).upvalues.len() {
+            // Searched in reverse, as a REPL local may shadow an earlier one with the same name.
+            for j in (0..get_function(self, i).upvalues.len()).rev() {
                 if name == get_function(self, i).upvalues[j].0 {
-                    let upvalue_index = UpValueIndex(cast(j).ok_or(CompilerError::UpValues)?);
+                    let upvalue_index =
+                        UpValueIndex(cast(j).ok_or_else(|| upvalue_overflow(name, i))?);
                     if i == current_function {
                         return Ok(VariableDescriptor::UpValue(upvalue_index));
                     } else {
                         let mut upvalue_index = upvalue_index;
                         for k in i + 1..=current_function {
-                            get_function(self, k)
-                                .upvalues
-                                .push((name, UpValueDescriptor::Outer(upvalue_index)));
-                            upvalue_index = UpValueIndex(
-                                cast(get_function(self, k).upvalues.len() - 1)
-                                    .ok_or(CompilerError::UpValues)?,
-                            );
+                            upvalue_index = get_function(self, k).push_upvalue(
+                                name,
+                                UpValueDescriptor::Outer(upvalue_index),
+                                k,
+                            )?;
                         }
                         return Ok(VariableDescriptor::UpValue(upvalue_index));
                     }
@@ -1175,10 +2024,29 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     // _ENV.
     fn get_environment(&mut self) -> Result<ExprDescriptor<'gc>, CompilerError> {
         Ok(ExprDescriptor::Variable(
-            self.find_variable(String::new_static(b"_ENV"))?,
+            self.find_variable(String::new_static(b"_ENV"), true)?,
         ))
     }
 
+    // If global opcodes are enabled and can be used for the given environment and global name,
+    // returns the `_ENV` upvalue and the name constant.  They cannot be used when `_ENV` is a local
+    // variable or when the name does not fit in an 8 bit constant index.
+    fn global_operands(
+        &mut self,
+        env: &ExprDescriptor<'gc>,
+        name: String<'gc>,
+    ) -> Result<Option<(UpValueIndex, ConstantIndex8)>, CompilerError> {
+        if !self.global_opcodes {
+            return Ok(None);
+        }
+        if let ExprDescriptor::Variable(VariableDescriptor::UpValue(env)) = *env {
+            if let Some(name) = cast(self.get_constant(Constant::String(name))?.0) {
+                return Ok(Some((env, ConstantIndex8(name))));
+            }
+        }
+        Ok(None)
+    }
+
     fn unique_jump_label(&mut self) -> JumpLabel<'gc> {
         let jl = JumpLabel::Unique(self.current_function.unique_jump_id);
         self.current_function.unique_jump_id =
@@ -1189,20 +2057,28 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     fn jump(&mut self, target: JumpLabel<'gc>) -> Result<(), CompilerError> {
         let jmp_inst = self.current_function.opcodes.len();
         let current_stack_top = self.current_function.register_allocator.stack_top();
-        let current_block_index = self.current_function.blocks.len().checked_sub(1).unwrap();
+        let current_block_index = self
+            .current_function
+            .blocks
+            .len()
+            .checked_sub(1)
+            .ok_or_else(|| internal_error("no current block"))?;
 
         let mut target_found = false;
         for jump_target in self.current_function.jump_targets.iter().rev() {
             if jump_target.label == target {
                 // We need to close upvalues only if any of the blocks we're jumping over own
                 // upvalues
-                assert!(jump_target.stack_top <= current_stack_top);
-                assert!(jump_target.block_index <= current_block_index);
+                check(
+                    jump_target.stack_top <= current_stack_top
+                        && jump_target.block_index <= current_block_index,
+                    "jump target is not in an enclosing scope",
+                )?;
                 let needs_close_upvalues = jump_target.stack_top < current_stack_top
                     && (jump_target.block_index..=current_block_index)
                         .any(|i| self.current_function.blocks[i].owns_upvalues);
 
-                self.current_function.opcodes.push(OpCode::Jump {
+                self.push_opcode(OpCode::Jump {
                     offset: jump_offset(jmp_inst, jump_target.instruction)
                         .ok_or(CompilerError::JumpOverflow)?,
                     close_upvalues: if needs_close_upvalues {
@@ -1212,21 +2088,21 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     } else {
                         Opt254::none()
                     },
-                });
+                })?;
                 target_found = true;
                 break;
             }
         }
 
         if !target_found {
-            self.current_function.opcodes.push(OpCode::Jump {
+            self.push_opcode(OpCode::Jump {
                 offset: 0,
                 close_upvalues: Opt254::none(),
-            });
+            })?;
 
             self.current_function.pending_jumps.push(PendingJump {
                 target,
-                instruction: jmp_inst,
+                instruction: Some(jmp_inst),
                 block_index: current_block_index,
                 stack_top: current_stack_top,
                 close_upvalues: false,
@@ -1239,7 +2115,12 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
     fn jump_target(&mut self, jump_label: JumpLabel<'gc>) -> Result<(), CompilerError> {
         let target_instruction = self.current_function.opcodes.len();
         let current_stack_top = self.current_function.register_allocator.stack_top();
-        let current_block_index = self.current_function.blocks.len().checked_sub(1).unwrap();
+        let current_block_index = self
+            .current_function
+            .blocks
+            .len()
+            .checked_sub(1)
+            .ok_or_else(|| internal_error("no current block"))?;
 
         for jump_target in self.current_function.jump_targets.iter().rev() {
             if jump_target.block_index < current_block_index {
@@ -1256,9 +2137,15 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             block_index: current_block_index,
         });
 
+        check(
+            self.current_function
+                .pending_jumps
+                .iter()
+                .all(|pending_jump| pending_jump.block_index <= current_block_index),
+            "pending jump is in an inner block",
+        )?;
         let mut resolving_jumps = Vec::new();
         self.current_function.pending_jumps.retain(|pending_jump| {
-            assert!(pending_jump.block_index <= current_block_index);
             // Labels in inner blocks are out of scope for outer blocks, so skip if the pending jump
             // is from an outer block.
             if pending_jump.block_index == current_block_index && pending_jump.target == jump_label
@@ -1271,31 +2158,88 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         });
 
         for pending_jump in resolving_jumps {
-            assert!(pending_jump.stack_top <= current_stack_top);
+            check(
+                pending_jump.stack_top <= current_stack_top,
+                "pending jump is below the stack top of the label",
+            )?;
             if pending_jump.stack_top < current_stack_top {
                 return Err(CompilerError::JumpLocal);
             }
 
-            match &mut self.current_function.opcodes[pending_jump.instruction] {
-                OpCode::Jump {
-                    offset,
-                    close_upvalues,
-                } if *offset == 0 && close_upvalues.is_none() => {
-                    *offset = jump_offset(pending_jump.instruction, target_instruction)
+            let instruction = match pending_jump.instruction {
+                Some(instruction) => instruction,
+                None => continue,
+            };
+            let opcode = &mut self.current_function.opcodes[instruction];
+            match opcode.jump_offset_mut() {
+                Some(offset) if *offset == 0 => {
+                    *offset = jump_offset(instruction, target_instruction)
                         .ok_or(CompilerError::JumpOverflow)?;
-                    if pending_jump.close_upvalues {
+                }
+                _ => {
+                    return Err(CompilerError::Internal(format!(
+                        "jump instruction {} is not a placeholder jump instruction",
+                        instruction
+                    )))
+                }
+            }
+            if pending_jump.close_upvalues {
+                match opcode {
+                    OpCode::Jump { close_upvalues, .. } if close_upvalues.is_none() => {
                         *close_upvalues = cast(current_stack_top)
                             .and_then(Opt254::try_some)
                             .ok_or(CompilerError::Registers)?;
-                    };
+                    }
+                    _ => {
+                        return Err(CompilerError::Internal(format!(
+                            "jump instruction {} cannot close upvalues",
+                            instruction
+                        )))
+                    }
                 }
-                _ => panic!("jump instruction is not a placeholder jump instruction"),
             }
         }
 
         Ok(())
     }
 
+    // Called for every level added to the expression descriptor currently being built, evaluating
+    // descriptors recurses once per level so this must be limited.
+    fn nest_expression(&mut self) -> Result<(), CompilerError> {
+        self.expression_depth += 1;
+        if self.expression_depth > self.max_expression_depth {
+            Err(CompilerError::NestingTooDeep)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Patch a placeholder jump at the given instruction to jump to the next emitted instruction.
+    fn patch_jump_here(&mut self, instruction: usize) -> Result<(), CompilerError> {
+        let target = self.current_function.opcodes.len();
+        match self.current_function.opcodes[instruction].jump_offset_mut() {
+            Some(offset) if *offset == 0 => {
+                *offset = jump_offset(instruction, target).ok_or(CompilerError::JumpOverflow)?;
+            }
+            _ => {
+                return Err(CompilerError::Internal(format!(
+                    "jump instruction {} is not a placeholder jump instruction",
+                    instruction
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    // All opcodes should be emitted through this method, which enforces the maximum function size.
+    fn push_opcode(&mut self, opcode: OpCode) -> Result<(), CompilerError> {
+        if self.current_function.opcodes.len() >= self.current_function.limits.opcodes {
+            return Err(CompilerError::OpCodes);
+        }
+        self.current_function.opcodes.push(opcode);
+        Ok(())
+    }
+
     fn get_constant(&mut self, constant: Constant<'gc>) -> Result<ConstantIndex16, CompilerError> {
         if let Some(constant) = self.current_function.constant_table.get(&constant).cloned() {
             Ok(constant)
@@ -1303,70 +2247,56 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             let c = ConstantIndex16(
                 cast(self.current_function.constants.len()).ok_or(CompilerError::Constants)?,
             );
+            let constant = self.cache.intern(constant);
             self.current_function.constants.push(constant);
             self.current_function.constant_table.insert(constant, c);
             Ok(c)
         }
     }
 
-    fn set_table(
+    // Get a constant for use only with `LoadConstant`.  The returned index is provisional and will
+    // be fixed up in `CompilerFunction::finish`.
+    fn get_load_constant(
         &mut self,
-        table: ExprDescriptor<'gc>,
-        key: ExprDescriptor<'gc>,
-        value: ExprDescriptor<'gc>,
-    ) -> Result<(), CompilerError> {
-        match table {
-            ExprDescriptor::Variable(VariableDescriptor::UpValue(table)) => {
-                self.set_uptable(table, key, value)?;
-            }
-            table => {
-                let (table, table_is_temp) = self.expr_any_register(table)?;
-                self.set_rtable(table, key, value)?;
-                if table_is_temp {
-                    self.current_function.register_allocator.free(table);
-                }
-            }
+        constant: Constant<'gc>,
+    ) -> Result<ConstantIndex16, CompilerError> {
+        if let Some(constant) = self
+            .current_function
+            .load_constant_table
+            .get(&constant)
+            .cloned()
+        {
+            Ok(constant)
+        } else {
+            let c = ConstantIndex16(
+                cast(self.current_function.load_constants.len()).ok_or(CompilerError::Constants)?,
+            );
+            let constant = self.cache.intern(constant);
+            self.current_function.load_constants.push(constant);
+            self.current_function
+                .load_constant_table
+                .insert(constant, c);
+            Ok(c)
         }
-        Ok(())
     }
 
-    fn set_uptable(
+    fn set_table(
         &mut self,
-        table: UpValueIndex,
+        table: ExprDescriptor<'gc>,
         key: ExprDescriptor<'gc>,
         value: ExprDescriptor<'gc>,
     ) -> Result<(), CompilerError> {
-        let (key, key_to_free) = self.expr_any_register_or_constant(key)?;
-        let (value, value_to_free) = self.expr_any_register_or_constant(value)?;
-
-        if let Some(to_free) = key_to_free {
-            self.current_function.register_allocator.free(to_free);
+        let (table, table_to_free) = self.expr_table_operand(table)?;
+        self.set_table_operand(table, key, value)?;
+        if let Some(to_free) = table_to_free {
+            self.current_function.register_allocator.free(to_free)?;
         }
-        if let Some(to_free) = value_to_free {
-            self.current_function.register_allocator.free(to_free);
-        }
-
-        self.current_function.opcodes.push(match (key, value) {
-            (RegisterOrConstant::Register(key), RegisterOrConstant::Register(value)) => {
-                OpCode::SetUpTableRR { table, key, value }
-            }
-            (RegisterOrConstant::Register(key), RegisterOrConstant::Constant(value)) => {
-                OpCode::SetUpTableRC { table, key, value }
-            }
-            (RegisterOrConstant::Constant(key), RegisterOrConstant::Register(value)) => {
-                OpCode::SetUpTableCR { table, key, value }
-            }
-            (RegisterOrConstant::Constant(key), RegisterOrConstant::Constant(value)) => {
-                OpCode::SetUpTableCC { table, key, value }
-            }
-        });
-
         Ok(())
     }
 
-    fn set_rtable(
+    fn set_table_operand(
         &mut self,
-        table: RegisterIndex,
+        table: TableOperand,
         key: ExprDescriptor<'gc>,
         value: ExprDescriptor<'gc>,
     ) -> Result<(), CompilerError> {
@@ -1374,28 +2304,13 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         let (value, value_to_free) = self.expr_any_register_or_constant(value)?;
 
         if let Some(to_free) = key_to_free {
-            self.current_function.register_allocator.free(to_free);
+            self.current_function.register_allocator.free(to_free)?;
         }
         if let Some(to_free) = value_to_free {
-            self.current_function.register_allocator.free(to_free);
+            self.current_function.register_allocator.free(to_free)?;
         }
 
-        self.current_function.opcodes.push(match (key, value) {
-            (RegisterOrConstant::Register(key), RegisterOrConstant::Register(value)) => {
-                OpCode::SetTableRR { table, key, value }
-            }
-            (RegisterOrConstant::Register(key), RegisterOrConstant::Constant(value)) => {
-                OpCode::SetTableRC { table, key, value }
-            }
-            (RegisterOrConstant::Constant(key), RegisterOrConstant::Register(value)) => {
-                OpCode::SetTableCR { table, key, value }
-            }
-            (RegisterOrConstant::Constant(key), RegisterOrConstant::Constant(value)) => {
-                OpCode::SetTableCC { table, key, value }
-            }
-        });
-
-        Ok(())
+        self.push_opcode(set_table_opcode(table, key, value))
     }
 
     // Performs a function call.  At the end of the function call, the return values will be left at
@@ -1408,20 +2323,42 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         args: Vec<ExprDescriptor<'gc>>,
         returns: VarCount,
     ) -> Result<RegisterIndex, CompilerError> {
+        self.record_local_function_call(&func, &args);
         let func = self.expr_discharge(func, ExprDestination::PushNew)?;
-        let args = self.push_arguments(args)?;
+        let args = self.push_arguments(args, CompilerError::Arguments)?;
 
-        self.current_function.opcodes.push(OpCode::Call {
+        self.push_opcode(OpCode::Call {
             func,
             args,
             returns,
-        });
+        })?;
 
         // OpCode::Call places returns at the previous location of the function
-        self.current_function.register_allocator.free(func);
+        self.current_function.register_allocator.free(func)?;
         Ok(func)
     }
 
+    // Records a call to a local function for the `ArgumentCount` warning, if the number of arguments
+    // is known.
+    fn record_local_function_call(
+        &mut self,
+        func: &ExprDescriptor<'gc>,
+        args: &[ExprDescriptor<'gc>],
+    ) {
+        if let ExprDescriptor::Variable(VariableDescriptor::Local(register)) = func {
+            if let Some(function) = self.current_function.local_functions.get_mut(&register.0) {
+                match args.last() {
+                    Some(ExprDescriptor::FunctionCall { .. }) | Some(ExprDescriptor::VarArgs) => {}
+                    _ => {
+                        if let Some(arguments) = cast(args.len()) {
+                            function.call_arguments.push(arguments);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     // Performs a method call similarly to how `call_function` works.  Method calls have a special
     // opcode that make them more efficient than executing them in a naive way.
     fn call_method(
@@ -1431,14 +2368,37 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         args: Vec<ExprDescriptor<'gc>>,
         returns: VarCount,
     ) -> Result<RegisterIndex, CompilerError> {
+        let (base, args) = self.push_method_call(table, method, args)?;
+        self.push_opcode(OpCode::Call {
+            func: base,
+            args,
+            returns,
+        })?;
+
+        self.current_function
+            .register_allocator
+            .pop_to(base.0 as u16);
+
+        Ok(base)
+    }
+
+    // Looks up the method with `SelfR` / `SelfC` and pushes the arguments after the table, ready for
+    // a `Call` or `TailCall`.  Returns the base register holding the method, which is left
+    // allocated along with the table after it, and the argument count including the table.
+    fn push_method_call(
+        &mut self,
+        table: ExprDescriptor<'gc>,
+        method: ExprDescriptor<'gc>,
+        args: Vec<ExprDescriptor<'gc>>,
+    ) -> Result<(RegisterIndex, VarCount), CompilerError> {
         let (table, table_is_temp) = self.expr_any_register(table)?;
         let (method, method_to_free) = self.expr_any_register_or_constant(method)?;
 
         if table_is_temp {
-            self.current_function.register_allocator.free(table);
+            self.current_function.register_allocator.free(table)?;
         }
         if let Some(to_free) = method_to_free {
-            self.current_function.register_allocator.free(to_free);
+            self.current_function.register_allocator.free(to_free)?;
         }
 
         let base = self
@@ -1447,39 +2407,31 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             .push(2)
             .ok_or(CompilerError::Registers)?;
 
-        self.current_function.opcodes.push(match method {
+        self.push_opcode(match method {
             RegisterOrConstant::Register(key) => OpCode::SelfR { base, table, key },
             RegisterOrConstant::Constant(key) => OpCode::SelfC { base, table, key },
-        });
+        })?;
 
-        let args = self.push_arguments(args)?;
+        let args = self.push_arguments(args, CompilerError::Arguments)?;
         let args = match args.to_constant() {
             Some(args) => args
                 .checked_add(1)
                 .and_then(VarCount::try_constant)
-                .ok_or(CompilerError::Registers)?,
+                .ok_or(CompilerError::Arguments)?,
             None => VarCount::variable(),
         };
-        self.current_function.opcodes.push(OpCode::Call {
-            func: base,
-            args,
-            returns,
-        });
 
-        self.current_function
-            .register_allocator
-            .pop_to(base.0 as u16);
-
-        Ok(base)
+        Ok((base, args))
     }
 
     // Pushes the given arguments to the top of the stack in preparation for a function call or
     // return statement.  The arguemnts are *not* marked as allocated in the register allocator, as
     // they are potentially variable.  Returns the register at which the arguments start, as well as
-    // their arity.
+    // their arity.  If there are too many arguments for a `VarCount`, returns `too_many`.
     fn push_arguments(
         &mut self,
         mut args: Vec<ExprDescriptor<'gc>>,
+        too_many: CompilerError,
     ) -> Result<VarCount, CompilerError> {
         let top = self.current_function.register_allocator.stack_top();
         let args_len = args.len();
@@ -1495,19 +2447,19 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     VarCount::variable()
                 }
                 ExprDescriptor::VarArgs => {
-                    self.current_function.opcodes.push(OpCode::VarArgs {
+                    self.push_opcode(OpCode::VarArgs {
                         dest: RegisterIndex(
                             cast(top as usize + args_len - 1).ok_or(CompilerError::Registers)?,
                         ),
                         count: VarCount::variable(),
-                    });
+                    })?;
                     VarCount::variable()
                 }
                 last_arg => {
                     self.expr_discharge(last_arg, ExprDestination::PushNew)?;
                     cast(args_len)
                         .and_then(VarCount::try_constant)
-                        .ok_or(CompilerError::Registers)?
+                        .ok_or(too_many)?
                 }
             };
 
@@ -1558,6 +2510,111 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         ))
     }
 
+    // Tables in upvalues can be accessed directly, any other table expression is evaluated into a
+    // register.  If there is a register that must be freed, returns that register as the second
+    // return value.
+    fn expr_table_operand(
+        &mut self,
+        table: ExprDescriptor<'gc>,
+    ) -> Result<(TableOperand, Option<RegisterIndex>), CompilerError> {
+        Ok(match table {
+            ExprDescriptor::Variable(VariableDescriptor::UpValue(table)) => {
+                (TableOperand::UpValue(table), None)
+            }
+            table => {
+                let (table, table_is_temp) = self.expr_any_register(table)?;
+                (
+                    TableOperand::Register(table),
+                    if table_is_temp { Some(table) } else { None },
+                )
+            }
+        })
+    }
+
+    // Returns the register named by the given destination, allocating it if necessary.
+    fn new_destination(&mut self, dest: ExprDestination) -> Result<RegisterIndex, CompilerError> {
+        Ok(match dest {
+            ExprDestination::Register(dest) => dest,
+            ExprDestination::AllocateNew => self
+                .current_function
+                .register_allocator
+                .allocate()
+                .ok_or(CompilerError::Registers)?,
+            ExprDestination::PushNew => self
+                .current_function
+                .register_allocator
+                .push(1)
+                .ok_or(CompilerError::Registers)?,
+        })
+    }
+
+    // Compiles the `cond and value or alternative` idiom as a single conditional, so that a false
+    // `cond` jumps straight to `alternative` without being tested twice.
+    fn and_or_expression(
+        &mut self,
+        cond_and_value: ExprDescriptor<'gc>,
+        alternative_expr: ExprDescriptor<'gc>,
+        dest: ExprDestination,
+    ) -> Result<RegisterIndex, CompilerError> {
+        let (cond, value) = match cond_and_value {
+            ExprDescriptor::ShortCircuitBinOp { left, right, .. } => (*left, *right),
+            _ => return Err(internal_error("and / or idiom mismatch")),
+        };
+
+        let (cond_register, cond_is_temp) = self.expr_any_register(cond)?;
+        if cond_is_temp {
+            self.current_function
+                .register_allocator
+                .free(cond_register)?;
+        }
+
+        let dest_is_new = !matches!(dest, ExprDestination::Register(_));
+        let dest = self.new_destination(dest)?;
+
+        self.push_opcode(OpCode::Test {
+            value: cond_register,
+            is_true: true,
+        })?;
+        let alternative = self.unique_jump_label();
+        self.jump(alternative)?;
+
+        // An existing destination must not be overwritten until the result is known.
+        let (value_register, value_to_free) = match value {
+            ExprDescriptor::Variable(VariableDescriptor::Local(register)) => (register, None),
+            value if dest_is_new => (
+                self.expr_discharge(value, ExprDestination::Register(dest))?,
+                None,
+            ),
+            value => {
+                let register = self.expr_discharge(value, ExprDestination::AllocateNew)?;
+                (register, Some(register))
+            }
+        };
+        self.push_opcode(if value_register == dest {
+            OpCode::Test {
+                value: dest,
+                is_true: false,
+            }
+        } else {
+            OpCode::TestSet {
+                dest,
+                value: value_register,
+                is_true: false,
+            }
+        })?;
+        if let Some(to_free) = value_to_free {
+            self.current_function.register_allocator.free(to_free)?;
+        }
+        let end = self.unique_jump_label();
+        self.jump(end)?;
+
+        self.jump_target(alternative)?;
+        self.expr_discharge(alternative_expr, ExprDestination::Register(dest))?;
+        self.jump_target(end)?;
+
+        Ok(dest)
+    }
+
     // Consume an expression, placing it in the given destination and returning the resulting
     // register.
     fn expr_discharge(
@@ -1565,135 +2622,91 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         expr: ExprDescriptor<'gc>,
         dest: ExprDestination,
     ) -> Result<RegisterIndex, CompilerError> {
-        fn new_destination<'gc, 'a>(
-            this: &mut Compiler<'gc, 'a>,
-            dest: ExprDestination,
-        ) -> Result<RegisterIndex, CompilerError> {
-            Ok(match dest {
-                ExprDestination::Register(dest) => dest,
-                ExprDestination::AllocateNew => this
-                    .current_function
-                    .register_allocator
-                    .allocate()
-                    .ok_or(CompilerError::Registers)?,
-                ExprDestination::PushNew => this
-                    .current_function
-                    .register_allocator
-                    .push(1)
-                    .ok_or(CompilerError::Registers)?,
-            })
-        }
-
         fn get_table<'gc, 'a>(
             this: &mut Compiler<'gc, 'a>,
             table: ExprDescriptor<'gc>,
             key: ExprDescriptor<'gc>,
             dest: ExprDestination,
         ) -> Result<RegisterIndex, CompilerError> {
-            Ok(match table {
-                ExprDescriptor::Variable(VariableDescriptor::UpValue(table)) => {
-                    let (key_reg_cons, key_to_free) = this.expr_any_register_or_constant(key)?;
-                    if let Some(to_free) = key_to_free {
-                        this.current_function.register_allocator.free(to_free);
-                    }
-                    let dest = new_destination(this, dest)?;
-                    this.current_function.opcodes.push(match key_reg_cons {
-                        RegisterOrConstant::Constant(key) => {
-                            OpCode::GetUpTableC { dest, table, key }
-                        }
-                        RegisterOrConstant::Register(key) => {
-                            OpCode::GetUpTableR { dest, table, key }
-                        }
-                    });
-                    dest
-                }
-                table => {
-                    let (table, table_is_temp) = this.expr_any_register(table)?;
-                    let (key_reg_cons, key_to_free) = this.expr_any_register_or_constant(key)?;
-                    if table_is_temp {
-                        this.current_function.register_allocator.free(table);
-                    }
-                    if let Some(to_free) = key_to_free {
-                        this.current_function.register_allocator.free(to_free);
-                    }
-                    let dest = new_destination(this, dest)?;
-                    this.current_function.opcodes.push(match key_reg_cons {
-                        RegisterOrConstant::Constant(key) => OpCode::GetTableC { dest, table, key },
-                        RegisterOrConstant::Register(key) => OpCode::GetTableR { dest, table, key },
-                    });
-                    dest
-                }
-            })
+            let (table, table_to_free) = this.expr_table_operand(table)?;
+            let (key, key_to_free) = this.expr_any_register_or_constant(key)?;
+            if let Some(to_free) = table_to_free {
+                this.current_function.register_allocator.free(to_free)?;
+            }
+            if let Some(to_free) = key_to_free {
+                this.current_function.register_allocator.free(to_free)?;
+            }
+            let dest = this.new_destination(dest)?;
+            this.push_opcode(get_table_opcode(dest, table, key))?;
+            Ok(dest)
         }
 
         let result = match expr {
             ExprDescriptor::Variable(variable) => match variable {
                 VariableDescriptor::Local(source) => {
-                    let dest = new_destination(self, dest)?;
-                    self.current_function
-                        .opcodes
-                        .push(OpCode::Move { dest, source });
+                    let dest = self.new_destination(dest)?;
+                    self.push_opcode(OpCode::Move { dest, source })?;
                     dest
                 }
 
                 VariableDescriptor::UpValue(source) => {
-                    let dest = new_destination(self, dest)?;
-                    self.current_function
-                        .opcodes
-                        .push(OpCode::GetUpValue { source, dest });
+                    let dest = self.new_destination(dest)?;
+                    self.push_opcode(OpCode::GetUpValue { source, dest })?;
                     dest
                 }
 
                 VariableDescriptor::Global(name) => {
                     let env = self.get_environment()?;
-                    let key = ExprDescriptor::Constant(Constant::String(name));
-                    get_table(self, env, key, dest)?
+                    if let Some((env, name)) = self.global_operands(&env, name)? {
+                        let dest = self.new_destination(dest)?;
+                        self.push_opcode(OpCode::GetGlobal { dest, env, name })?;
+                        dest
+                    } else {
+                        let key = ExprDescriptor::Constant(Constant::String(name));
+                        get_table(self, env, key, dest)?
+                    }
                 }
             },
 
             ExprDescriptor::Constant(value) => {
-                let dest = new_destination(self, dest)?;
+                let dest = self.new_destination(dest)?;
                 match value {
                     Constant::Nil => {
-                        self.current_function
-                            .opcodes
-                            .push(OpCode::LoadNil { dest, count: 1 });
+                        self.push_opcode(OpCode::LoadNil { dest, count: 1 })?;
                     }
                     Constant::Boolean(value) => {
-                        self.current_function.opcodes.push(OpCode::LoadBool {
+                        self.push_opcode(OpCode::LoadBool {
                             dest,
                             value,
                             skip_next: false,
-                        });
+                        })?;
                     }
                     val => {
-                        let constant = self.get_constant(val)?;
-                        self.current_function
-                            .opcodes
-                            .push(OpCode::LoadConstant { dest, constant });
+                        let constant = self.get_load_constant(val)?;
+                        self.push_opcode(OpCode::LoadConstant { dest, constant })?;
                     }
                 }
                 dest
             }
 
             ExprDescriptor::VarArgs => {
-                let dest = new_destination(self, dest)?;
-                self.current_function.opcodes.push(OpCode::VarArgs {
+                let dest = self.new_destination(dest)?;
+                self.push_opcode(OpCode::VarArgs {
                     dest,
                     count: VarCount::constant(1),
-                });
+                })?;
                 dest
             }
 
             ExprDescriptor::UnaryOperator { op, expr } => {
                 let (source, source_is_temp) = self.expr_any_register(*expr)?;
                 if source_is_temp {
-                    self.current_function.register_allocator.free(source);
+                    self.current_function.register_allocator.free(source)?;
                 }
 
-                let dest = new_destination(self, dest)?;
+                let dest = self.new_destination(dest)?;
                 let unop_opcode = unop_opcode(op, dest, source);
-                self.current_function.opcodes.push(unop_opcode);
+                self.push_opcode(unop_opcode)?;
                 dest
             }
 
@@ -1701,94 +2714,54 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                 let (left_reg_cons, left_to_free) = self.expr_any_register_or_constant(*left)?;
                 let (right_reg_cons, right_to_free) = self.expr_any_register_or_constant(*right)?;
                 if let Some(to_free) = left_to_free {
-                    self.current_function.register_allocator.free(to_free);
+                    self.current_function.register_allocator.free(to_free)?;
                 }
                 if let Some(to_free) = right_to_free {
-                    self.current_function.register_allocator.free(to_free);
+                    self.current_function.register_allocator.free(to_free)?;
                 }
 
-                let dest = new_destination(self, dest)?;
+                let dest = self.new_destination(dest)?;
                 let simple_binop_opcode =
                     simple_binop_opcode(op, dest, left_reg_cons, right_reg_cons);
-                self.current_function.opcodes.push(simple_binop_opcode);
+                self.push_opcode(simple_binop_opcode)?;
 
                 dest
             }
 
             ExprDescriptor::Comparison { left, op, right } => {
-                let (left_reg_cons, left_to_free) = self.expr_any_register_or_constant(*left)?;
-                let (right_reg_cons, right_to_free) = self.expr_any_register_or_constant(*right)?;
-                if let Some(to_free) = left_to_free {
-                    self.current_function.register_allocator.free(to_free);
-                }
-                if let Some(to_free) = right_to_free {
-                    self.current_function.register_allocator.free(to_free);
+                self.comparison_expression(*left, op, *right, dest)?
+            }
+
+            ExprDescriptor::ShortCircuitBinOp {
+                left,
+                op: ShortCircuitBinOp::Or,
+                right,
+            } if matches!(
+                *left,
+                ExprDescriptor::ShortCircuitBinOp {
+                    op: ShortCircuitBinOp::And,
+                    ..
                 }
-
-                let dest = new_destination(self, dest)?;
-                let comparison_opcode =
-                    comparison_binop_opcode(op, left_reg_cons, right_reg_cons, false);
-
-                let opcodes = &mut self.current_function.opcodes;
-                opcodes.push(comparison_opcode);
-                opcodes.push(OpCode::Jump {
-                    offset: 1,
-                    close_upvalues: Opt254::none(),
-                });
-                opcodes.push(OpCode::LoadBool {
-                    dest,
-                    value: false,
-                    skip_next: true,
-                });
-                opcodes.push(OpCode::LoadBool {
-                    dest,
-                    value: true,
-                    skip_next: false,
-                });
-
-                dest
+            ) =>
+            {
+                self.and_or_expression(*left, *right, dest)?
             }
 
             ExprDescriptor::ShortCircuitBinOp { left, op, right } => {
-                let (left_register, left_is_temp) = self.expr_any_register(*left)?;
-                if left_is_temp {
-                    self.current_function.register_allocator.free(left_register);
-                }
-
-                let dest = new_destination(self, dest)?;
-
-                let test_op_true = op == ShortCircuitBinOp::And;
-                let test_op = if left_register == dest {
-                    OpCode::Test {
-                        value: left_register,
-                        is_true: test_op_true,
-                    }
-                } else {
-                    OpCode::TestSet {
-                        dest,
-                        value: left_register,
-                        is_true: test_op_true,
-                    }
-                };
-                self.current_function.opcodes.push(test_op);
-
-                let skip = self.unique_jump_label();
-                self.jump(skip)?;
-
-                self.expr_discharge(*right, ExprDestination::Register(dest))?;
-                self.jump_target(skip)?;
-
-                dest
+                self.short_circuit_expression(*left, op, *right, dest)?
             }
 
-            ExprDescriptor::TableConstructor(fields) => {
-                let dest = new_destination(self, dest)?;
-                self.current_function
-                    .opcodes
-                    .push(OpCode::NewTable { dest });
+            ExprDescriptor::TableConstructor { array_size, fields } => {
+                let dest = self.new_destination(dest)?;
+                let map_size = fields.len() - array_size;
+                self.push_opcode(OpCode::NewTable {
+                    dest,
+                    array_size: cast(array_size).unwrap_or(u8::MAX),
+                    map_size: cast(map_size).unwrap_or(u8::MAX),
+                })?;
 
                 for (key, value) in fields {
-                    self.set_rtable(dest, key, value)?;
+                    self.set_table_operand(TableOperand::Register(dest), key, value)?;
                 }
 
                 dest
@@ -1797,34 +2770,14 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             ExprDescriptor::TableField { table, key } => get_table(self, *table, *key, dest)?,
 
             ExprDescriptor::Closure(proto) => {
-                let dest = new_destination(self, dest)?;
-                self.current_function
-                    .opcodes
-                    .push(OpCode::Closure { proto, dest });
+                let dest = self.new_destination(dest)?;
+                self.push_opcode(OpCode::Closure { proto, dest })?;
                 dest
             }
 
             ExprDescriptor::FunctionCall { func, args } => {
                 let source = self.call_function(*func, args, VarCount::constant(1))?;
-                match dest {
-                    ExprDestination::Register(dest) => {
-                        assert_ne!(dest, source);
-                        self.current_function
-                            .opcodes
-                            .push(OpCode::Move { dest, source });
-                        dest
-                    }
-                    ExprDestination::AllocateNew | ExprDestination::PushNew => {
-                        assert_eq!(
-                            self.current_function
-                                .register_allocator
-                                .push(1)
-                                .ok_or(CompilerError::Registers)?,
-                            source
-                        );
-                        source
-                    }
-                }
+                self.call_result(source, dest)?
             }
 
             ExprDescriptor::MethodCall {
@@ -1833,63 +2786,157 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                 args,
             } => {
                 let source = self.call_method(*table, *method, args, VarCount::constant(1))?;
-                match dest {
-                    ExprDestination::Register(dest) => {
-                        assert_ne!(dest, source);
-                        self.current_function
-                            .opcodes
-                            .push(OpCode::Move { dest, source });
-                        dest
-                    }
-                    ExprDestination::AllocateNew | ExprDestination::PushNew => {
-                        assert_eq!(
-                            self.current_function
-                                .register_allocator
-                                .push(1)
-                                .ok_or(CompilerError::Registers)?,
-                            source
-                        );
-                        source
-                    }
-                }
+                self.call_result(source, dest)?
             }
 
-            ExprDescriptor::Concat(mut exprs) => {
-                assert!(!exprs.is_empty());
-                let dest = new_destination(self, dest)?;
-                let source =
-                    self.expr_discharge(exprs.pop_front().unwrap(), ExprDestination::PushNew)?;
-                let mut count = 1;
-                while !exprs.is_empty() {
-                    if let Some(new) = self.current_function.register_allocator.push(1) {
-                        let next = exprs.pop_front().unwrap();
-                        self.expr_discharge(next, ExprDestination::Register(new))?;
-                        count += 1;
-                    } else {
-                        self.current_function.opcodes.push(OpCode::Concat {
-                            dest: source,
-                            source,
-                            count,
-                        });
-                        self.current_function
-                            .register_allocator
-                            .pop_to(source.0 as u16 + 1);
-                        count = 1;
-                    }
-                }
-                self.current_function.opcodes.push(OpCode::Concat {
-                    dest,
+            ExprDescriptor::Concat(exprs) => self.concat_expression(exprs, dest)?,
+        };
+
+        Ok(result)
+    }
+
+    fn comparison_expression(
+        &mut self,
+        left: ExprDescriptor<'gc>,
+        op: ComparisonBinOp,
+        right: ExprDescriptor<'gc>,
+        dest: ExprDestination,
+    ) -> Result<RegisterIndex, CompilerError> {
+        let (left_reg_cons, left_to_free) = self.expr_any_register_or_constant(left)?;
+        let (right_reg_cons, right_to_free) = self.expr_any_register_or_constant(right)?;
+        if let Some(to_free) = left_to_free {
+            self.current_function.register_allocator.free(to_free)?;
+        }
+        if let Some(to_free) = right_to_free {
+            self.current_function.register_allocator.free(to_free)?;
+        }
+
+        let dest = self.new_destination(dest)?;
+        let comparison_opcode = comparison_binop_opcode(op, left_reg_cons, right_reg_cons, false);
+
+        self.push_opcode(comparison_opcode)?;
+        self.push_opcode(OpCode::Jump {
+            offset: 1,
+            close_upvalues: Opt254::none(),
+        })?;
+        self.push_opcode(OpCode::LoadBool {
+            dest,
+            value: false,
+            skip_next: true,
+        })?;
+        self.push_opcode(OpCode::LoadBool {
+            dest,
+            value: true,
+            skip_next: false,
+        })?;
+
+        Ok(dest)
+    }
+
+    fn short_circuit_expression(
+        &mut self,
+        left: ExprDescriptor<'gc>,
+        op: ShortCircuitBinOp,
+        right: ExprDescriptor<'gc>,
+        dest: ExprDestination,
+    ) -> Result<RegisterIndex, CompilerError> {
+        let (left_register, left_is_temp) = self.expr_any_register(left)?;
+        if left_is_temp {
+            self.current_function
+                .register_allocator
+                .free(left_register)?;
+        }
+
+        let dest = self.new_destination(dest)?;
+
+        let test_op_true = op == ShortCircuitBinOp::And;
+        let test_op = if left_register == dest {
+            OpCode::Test {
+                value: left_register,
+                is_true: test_op_true,
+            }
+        } else {
+            OpCode::TestSet {
+                dest,
+                value: left_register,
+                is_true: test_op_true,
+            }
+        };
+        self.push_opcode(test_op)?;
+
+        let skip = self.unique_jump_label();
+        self.jump(skip)?;
+
+        self.expr_discharge(right, ExprDestination::Register(dest))?;
+        self.jump_target(skip)?;
+
+        Ok(dest)
+    }
+
+    // Places the single result of a call, which is left at the top of the stack, in the requested
+    // destination.
+    fn call_result(
+        &mut self,
+        source: RegisterIndex,
+        dest: ExprDestination,
+    ) -> Result<RegisterIndex, CompilerError> {
+        Ok(match dest {
+            ExprDestination::Register(dest) => {
+                check(dest != source, "call result moved onto itself")?;
+                self.push_opcode(OpCode::Move { dest, source })?;
+                dest
+            }
+            ExprDestination::AllocateNew | ExprDestination::PushNew => {
+                check(
+                    self.current_function
+                        .register_allocator
+                        .push(1)
+                        .ok_or(CompilerError::Registers)?
+                        == source,
+                    "call result is not at the stack top",
+                )?;
+                source
+            }
+        })
+    }
+
+    fn concat_expression(
+        &mut self,
+        mut exprs: VecDeque<ExprDescriptor<'gc>>,
+        dest: ExprDestination,
+    ) -> Result<RegisterIndex, CompilerError> {
+        let first = exprs
+            .pop_front()
+            .ok_or_else(|| internal_error("empty concat expression"))?;
+        let dest = self.new_destination(dest)?;
+        let source = self.expr_discharge(first, ExprDestination::PushNew)?;
+        let mut count = 1;
+        while !exprs.is_empty() {
+            if let Some(new) = self.current_function.register_allocator.push(1) {
+                let next = exprs.pop_front().unwrap();
+                self.expr_discharge(next, ExprDestination::Register(new))?;
+                count += 1;
+            } else {
+                self.push_opcode(OpCode::Concat {
+                    dest: source,
                     source,
                     count,
-                });
+                })?;
                 self.current_function
                     .register_allocator
-                    .pop_to(source.0 as u16);
-                dest
+                    .pop_to(source.0 as u16 + 1);
+                count = 1;
             }
-        };
-
-        Ok(result)
+        }
+        self.push_opcode(OpCode::Concat {
+            dest,
+            source,
+            count,
+        })?;
+        self.current_function
+            .register_allocator
+            .pop_to(source.0 as u16);
+        Ok(dest)
     }
 
     // Evaluates an expression and pushes it to a range of newly allocated registers at the top of
@@ -1899,13 +2946,13 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         expr: ExprDescriptor<'gc>,
         count: u8,
     ) -> Result<RegisterIndex, CompilerError> {
-        assert!(count != 0);
+        check(count != 0, "no values to push")?;
         Ok(match expr {
             ExprDescriptor::FunctionCall { func, args } => {
                 let dest = self.call_function(
                     *func,
                     args,
-                    VarCount::try_constant(count).ok_or(CompilerError::Registers)?,
+                    VarCount::try_constant(count).ok_or(CompilerError::Returns)?,
                 )?;
                 self.current_function
                     .register_allocator
@@ -1919,10 +2966,10 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     .register_allocator
                     .push(count)
                     .ok_or(CompilerError::Registers)?;
-                self.current_function.opcodes.push(OpCode::VarArgs {
+                self.push_opcode(OpCode::VarArgs {
                     dest,
-                    count: VarCount::try_constant(count).ok_or(CompilerError::Registers)?,
-                });
+                    count: VarCount::try_constant(count).ok_or(CompilerError::Returns)?,
+                })?;
                 dest
             }
             ExprDescriptor::Constant(Constant::Nil) => {
@@ -1931,9 +2978,7 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                     .register_allocator
                     .push(count)
                     .ok_or(CompilerError::Registers)?;
-                self.current_function
-                    .opcodes
-                    .push(OpCode::LoadNil { dest, count });
+                self.push_opcode(OpCode::LoadNil { dest, count })?;
                 dest
             }
             expr => {
@@ -1944,10 +2989,10 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
                         .register_allocator
                         .push(count - 1)
                         .ok_or(CompilerError::Registers)?;
-                    self.current_function.opcodes.push(OpCode::LoadNil {
+                    self.push_opcode(OpCode::LoadNil {
                         dest: nils,
                         count: count - 1,
-                    });
+                    })?;
                 }
                 dest
             }
@@ -1967,15 +3012,15 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
             let (left_reg_cons, left_to_free) = this.expr_any_register_or_constant(left)?;
             let (right_reg_cons, right_to_free) = this.expr_any_register_or_constant(right)?;
             if let Some(to_free) = left_to_free {
-                this.current_function.register_allocator.free(to_free);
+                this.current_function.register_allocator.free(to_free)?;
             }
             if let Some(to_free) = right_to_free {
-                this.current_function.register_allocator.free(to_free);
+                this.current_function.register_allocator.free(to_free)?;
             }
 
             let comparison_opcode =
                 comparison_binop_opcode(op, left_reg_cons, right_reg_cons, skip_if);
-            this.current_function.opcodes.push(comparison_opcode);
+            this.push_opcode(comparison_opcode)?;
 
             Ok(())
         }
@@ -1987,12 +3032,12 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         ) -> Result<(), CompilerError> {
             let (test_reg, test_is_temp) = this.expr_any_register(expr)?;
             if test_is_temp {
-                this.current_function.register_allocator.free(test_reg);
+                this.current_function.register_allocator.free(test_reg)?;
             }
-            this.current_function.opcodes.push(OpCode::Test {
+            this.push_opcode(OpCode::Test {
                 value: test_reg,
                 is_true,
-            });
+            })?;
 
             Ok(())
         }
@@ -2000,10 +3045,10 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
         match expr {
             ExprDescriptor::Constant(cons) => {
                 if cons.to_value().to_bool() == skip_if {
-                    self.current_function.opcodes.push(OpCode::Jump {
+                    self.push_opcode(OpCode::Jump {
                         offset: 1,
                         close_upvalues: Opt254::none(),
-                    });
+                    })?;
                 }
             }
             ExprDescriptor::Comparison { left, op, right } => {
@@ -2023,17 +3068,83 @@ impl<'gc, 'a> Compiler<'gc, 'a> {
 
         Ok(())
     }
+
+    // Evaluates the given expression as a condition, emitting placeholder jumps that are taken when
+    // its boolean result is equal to `jump_if` and falling through otherwise.  Short-circuit
+    // operators are tested operand by operand rather than materializing their result in a register.
+    // Returns the placeholder jumps, which must all be patched by the caller.
+    fn expr_test_jumps(
+        &mut self,
+        expr: ExprDescriptor<'gc>,
+        jump_if: bool,
+    ) -> Result<Vec<usize>, CompilerError> {
+        Ok(match expr {
+            ExprDescriptor::ShortCircuitBinOp { left, op, right } => {
+                // `a and b` is false if either operand is false, and `a or b` is true if either
+                // operand is true, otherwise the left operand decides whether to skip the right.
+                if jump_if == (op == ShortCircuitBinOp::Or) {
+                    let mut jumps = self.expr_test_jumps(*left, jump_if)?;
+                    jumps.extend(self.expr_test_jumps(*right, jump_if)?);
+                    jumps
+                } else {
+                    let skip_jumps = self.expr_test_jumps(*left, !jump_if)?;
+                    let jumps = self.expr_test_jumps(*right, jump_if)?;
+                    for skip_jump in skip_jumps {
+                        self.patch_jump_here(skip_jump)?;
+                    }
+                    jumps
+                }
+            }
+            ExprDescriptor::UnaryOperator {
+                op: UnaryOperator::Not,
+                expr,
+            } if matches!(*expr, ExprDescriptor::ShortCircuitBinOp { .. }) => {
+                self.expr_test_jumps(*expr, !jump_if)?
+            }
+            ExprDescriptor::Constant(cons) if cons.to_value().to_bool() != jump_if => Vec::new(),
+            expr => {
+                // A constant reaching here always takes the jump, so it needs no test.
+                if !matches!(expr, ExprDescriptor::Constant(_)) {
+                    self.expr_test(expr, !jump_if)?;
+                }
+                let jump = self.current_function.opcodes.len();
+                self.push_opcode(OpCode::Jump {
+                    offset: 0,
+                    close_upvalues: Opt254::none(),
+                })?;
+                vec![jump]
+            }
+        })
+    }
 }
 
 impl<'gc> CompilerFunction<'gc> {
     fn start(
         parameters: &[String<'gc>],
         has_varargs: bool,
+        body: &Block<String<'gc>>,
+        limits: FunctionLimits,
+        cache: &mut CompilerCache<'gc>,
     ) -> Result<CompilerFunction<'gc>, CompilerError> {
-        let mut function = CompilerFunction::default();
+        let mut function = CompilerFunction {
+            constant_table: cache.take_constant_table(),
+            load_constant_table: cache.take_constant_table(),
+            register_allocator: RegisterAllocator::with_limit(limits.registers),
+            limits,
+            // Nearly every statement produces at least one opcode, so the number of statements in
+            // the body (plus the final return) is a cheap lower bound on the number of opcodes.
+            opcodes: Vec::with_capacity(
+                body.statements.len() + body.return_statement.is_some() as usize + 1,
+            ),
+            locals: Vec::with_capacity(parameters.len()),
+            ..CompilerFunction::default()
+        };
         let fixed_params: u8 = cast(parameters.len()).ok_or(CompilerError::FixedParameters)?;
         if fixed_params != 0 {
-            function.register_allocator.push(fixed_params).unwrap();
+            function
+                .register_allocator
+                .push(fixed_params)
+                .ok_or(CompilerError::FixedParameters)?;
         }
         function.has_varargs = has_varargs;
         function.fixed_params = fixed_params;
@@ -2045,32 +3156,105 @@ impl<'gc> CompilerFunction<'gc> {
         Ok(function)
     }
 
-    fn finish(mut self, mc: MutationContext<'gc, '_>) -> Result<FunctionProto<'gc>, CompilerError> {
+    // Adds an upvalue to this function, which is nested at the given depth.
+    fn push_upvalue(
+        &mut self,
+        name: String<'gc>,
+        descriptor: UpValueDescriptor,
+        depth: usize,
+    ) -> Result<UpValueIndex, CompilerError> {
+        let index = self.upvalues.len();
+        if index >= self.limits.upvalues {
+            return Err(upvalue_overflow(name, depth));
+        }
+        let index = UpValueIndex(cast(index).ok_or_else(|| upvalue_overflow(name, depth))?);
+        self.upvalues.push((name, descriptor));
+        Ok(index)
+    }
+
+    fn finish(
+        mut self,
+        mc: MutationContext<'gc, '_>,
+        debug_info: bool,
+        packed_opcodes: bool,
+        cache: &mut CompilerCache<'gc>,
+    ) -> Result<FunctionProto<'gc>, CompilerError> {
+        if self.opcodes.len() >= self.limits.opcodes {
+            return Err(CompilerError::OpCodes);
+        }
         self.opcodes.push(OpCode::Return {
             start: RegisterIndex(0),
             count: VarCount::constant(0),
         });
-        assert!(self.locals.len() == self.fixed_params as usize);
+        check(
+            self.locals.len() == self.fixed_params as usize,
+            "locals left in scope",
+        )?;
+        // The parameters are the only locals still in scope, and are in scope until the end.
+        if debug_info {
+            for local in &mut self.local_vars[..self.locals.len()] {
+                local.end_pc = self.opcodes.len();
+            }
+        }
         for (_, r) in self.locals.drain(..) {
-            self.register_allocator.free(r);
+            self.register_allocator.free(r)?;
         }
-        assert_eq!(
-            self.register_allocator.stack_top(),
-            0,
-            "register leak detected"
-        );
+        check(
+            self.register_allocator.stack_top() == 0,
+            "register leak detected",
+        )?;
 
         if !self.pending_jumps.is_empty() {
             return Err(CompilerError::GotoInvalid);
         }
 
+        let mut load_indexes = Vec::with_capacity(self.load_constants.len());
+        for constant in self.load_constants {
+            load_indexes.push(if let Some(&index) = self.constant_table.get(&constant) {
+                index
+            } else {
+                let index =
+                    ConstantIndex16(cast(self.constants.len()).ok_or(CompilerError::Constants)?);
+                self.constants.push(constant);
+                self.constant_table.insert(constant, index);
+                index
+            });
+        }
+        for opcode in &mut self.opcodes {
+            if let OpCode::LoadConstant { constant, .. } = opcode {
+                *constant = load_indexes[constant.0 as usize];
+            }
+        }
+
+        if self.constants.len() > self.limits.constants {
+            return Err(CompilerError::Constants);
+        }
+        cache.return_constant_table(mem::take(&mut self.constant_table));
+        cache.return_constant_table(mem::take(&mut self.load_constant_table));
+
+        let packed_opcodes = if packed_opcodes {
+            self.opcodes
+                .iter()
+                .map(|&opcode| PackedOpCode::encode(opcode))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         Ok(FunctionProto {
             fixed_params: self.fixed_params,
             has_varargs: self.has_varargs,
             stack_size: self.register_allocator.stack_size(),
             constants: self.constants,
             opcodes: self.opcodes,
+            packed_opcodes,
             upvalues: self.upvalues.iter().map(|(_, d)| *d).collect(),
+            upvalue_names: if debug_info {
+                self.upvalues.iter().map(|(n, _)| *n).collect()
+            } else {
+                Vec::new()
+            },
+            local_vars: self.local_vars,
             prototypes: self
                 .prototypes
                 .into_iter()
@@ -2080,6 +3264,66 @@ impl<'gc> CompilerFunction<'gc> {
     }
 }
 
+// Whether evaluating the expression reads the given register.
+fn expr_reads_register(expr: &ExprDescriptor, reg: RegisterIndex) -> bool {
+    match expr {
+        ExprDescriptor::Variable(VariableDescriptor::Local(source)) => *source == reg,
+        ExprDescriptor::Variable(_)
+        | ExprDescriptor::Constant(_)
+        | ExprDescriptor::VarArgs
+        | ExprDescriptor::Closure(_) => false,
+        ExprDescriptor::UnaryOperator { expr, .. } => expr_reads_register(expr, reg),
+        ExprDescriptor::SimpleBinaryOperator { left, right, .. }
+        | ExprDescriptor::Comparison { left, right, .. }
+        | ExprDescriptor::ShortCircuitBinOp { left, right, .. } => {
+            expr_reads_register(left, reg) || expr_reads_register(right, reg)
+        }
+        ExprDescriptor::TableConstructor { fields, .. } => fields
+            .iter()
+            .any(|(key, value)| expr_reads_register(key, reg) || expr_reads_register(value, reg)),
+        ExprDescriptor::TableField { table, key } => {
+            expr_reads_register(table, reg) || expr_reads_register(key, reg)
+        }
+        ExprDescriptor::FunctionCall { func, args } => {
+            expr_reads_register(func, reg) || args.iter().any(|arg| expr_reads_register(arg, reg))
+        }
+        ExprDescriptor::MethodCall {
+            table,
+            method,
+            args,
+        } => {
+            expr_reads_register(table, reg)
+                || expr_reads_register(method, reg)
+                || args.iter().any(|arg| expr_reads_register(arg, reg))
+        }
+        ExprDescriptor::Concat(exprs) => exprs.iter().any(|expr| expr_reads_register(expr, reg)),
+    }
+}
+
+fn upvalue_overflow(name: String, depth: usize) -> CompilerError {
+    CompilerError::UpValues {
+        name: name.as_bytes().into(),
+        depth: cast(depth).unwrap_or(u32::MAX),
+    }
+}
+
+// Internal invariants of the compiler are reported as `CompilerError::Internal` rather than
+// panicking, so that no input can crash the compiler.
+fn check(condition: bool, invariant: &str) -> Result<(), CompilerError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(internal_error(invariant))
+    }
+}
+
+fn internal_error(invariant: &str) -> CompilerError {
+    CompilerError::Internal(invariant.to_owned())
+}
+
+// The maximum number of opcodes in a single function.
+const MAX_OPCODES: usize = 1 << 20;
+
 fn jump_offset(source: usize, target: usize) -> Option<i16> {
     if target > source {
         cast(target - (source + 1))
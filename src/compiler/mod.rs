@@ -2,14 +2,25 @@ use std::io::Read;
 
 use gc_arena::MutationContext;
 
-use crate::{parse_chunk, Error, FunctionProto, InternedStringSet};
+use crate::{
+    parse_chunk,
+    parser::{parse_expression, parse_script, Block, Chunk, ReturnStatement},
+    Error, FunctionProto, InternedStringSet,
+};
 
 mod compiler;
 mod operators;
 mod register_allocator;
+mod repl;
 
-pub use self::compiler::{compile_chunk, CompilerError};
+pub use self::compiler::{
+    compile_chunk, compile_chunk_with_cache, compile_chunk_with_options, CompileStats,
+    CompilerCache, CompilerError, CompilerOptions, CompilerOutput, CompilerWarning,
+};
+pub use self::repl::ReplCompiler;
 
+/// Parses and compiles a chunk.  Every string in the source is interned in the given
+/// `InternedStringSet`, so chunks compiled with the same set share their string constants.
 pub fn compile<'gc, R: Read>(
     mc: MutationContext<'gc, '_>,
     interned_strings: InternedStringSet<'gc>,
@@ -20,3 +31,39 @@ pub fn compile<'gc, R: Read>(
         &parse_chunk(source, |s| interned_strings.new_string(mc, s))?,
     )?)
 }
+
+/// Parses and compiles a chunk with the given options, see `compile_chunk_with_options`.
+pub fn compile_with_options<'gc, R: Read>(
+    mc: MutationContext<'gc, '_>,
+    interned_strings: InternedStringSet<'gc>,
+    source: R,
+    options: CompilerOptions,
+) -> Result<CompilerOutput<'gc>, Error<'gc>> {
+    let create_string = |s: &[u8]| interned_strings.new_string(mc, s);
+    let chunk = if options.implicit_return {
+        parse_script(source, create_string)?
+    } else {
+        parse_chunk(source, create_string)?
+    };
+    Ok(compile_chunk_with_options(mc, &chunk, options)?)
+}
+
+/// Parses a single expression and compiles it as a chunk that returns the expression's values,
+/// just as if the source were prefixed with `return `.  Names in the expression are globals, as
+/// in any other chunk.
+pub fn compile_expression<'gc, R: Read>(
+    mc: MutationContext<'gc, '_>,
+    interned_strings: InternedStringSet<'gc>,
+    source: R,
+) -> Result<FunctionProto<'gc>, Error<'gc>> {
+    let expression = parse_expression(source, |s| interned_strings.new_string(mc, s))?;
+    let chunk = Chunk {
+        block: Block {
+            statements: Vec::new(),
+            return_statement: Some(ReturnStatement {
+                returns: vec![expression],
+            }),
+        },
+    };
+    Ok(compile_chunk(mc, &chunk)?)
+}
@@ -1,5 +1,7 @@
+use gc_arena::MutationContext;
+
 use crate::parser::{BinaryOperator, UnaryOperator};
-use crate::{Constant, ConstantIndex8, OpCode, RegisterIndex};
+use crate::{Constant, ConstantIndex8, OpCode, RegisterIndex, String, UpValueIndex};
 
 // Binary operators which map directly to a single opcode
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
@@ -77,6 +79,71 @@ pub enum RegisterOrConstant {
     Constant(ConstantIndex8),
 }
 
+// The table operand of a table access, tables held in upvalues have their own opcodes so that
+// they do not need to be loaded into a register first.
+pub enum TableOperand {
+    Register(RegisterIndex),
+    UpValue(UpValueIndex),
+}
+
+pub fn get_table_opcode(
+    dest: RegisterIndex,
+    table: TableOperand,
+    key: RegisterOrConstant,
+) -> OpCode {
+    match (table, key) {
+        (TableOperand::Register(table), RegisterOrConstant::Register(key)) => {
+            OpCode::GetTableR { dest, table, key }
+        }
+        (TableOperand::Register(table), RegisterOrConstant::Constant(key)) => {
+            OpCode::GetTableC { dest, table, key }
+        }
+        (TableOperand::UpValue(table), RegisterOrConstant::Register(key)) => {
+            OpCode::GetUpTableR { dest, table, key }
+        }
+        (TableOperand::UpValue(table), RegisterOrConstant::Constant(key)) => {
+            OpCode::GetUpTableC { dest, table, key }
+        }
+    }
+}
+
+pub fn set_table_opcode(
+    table: TableOperand,
+    key: RegisterOrConstant,
+    value: RegisterOrConstant,
+) -> OpCode {
+    match table {
+        TableOperand::Register(table) => match (key, value) {
+            (RegisterOrConstant::Register(key), RegisterOrConstant::Register(value)) => {
+                OpCode::SetTableRR { table, key, value }
+            }
+            (RegisterOrConstant::Register(key), RegisterOrConstant::Constant(value)) => {
+                OpCode::SetTableRC { table, key, value }
+            }
+            (RegisterOrConstant::Constant(key), RegisterOrConstant::Register(value)) => {
+                OpCode::SetTableCR { table, key, value }
+            }
+            (RegisterOrConstant::Constant(key), RegisterOrConstant::Constant(value)) => {
+                OpCode::SetTableCC { table, key, value }
+            }
+        },
+        TableOperand::UpValue(table) => match (key, value) {
+            (RegisterOrConstant::Register(key), RegisterOrConstant::Register(value)) => {
+                OpCode::SetUpTableRR { table, key, value }
+            }
+            (RegisterOrConstant::Register(key), RegisterOrConstant::Constant(value)) => {
+                OpCode::SetUpTableRC { table, key, value }
+            }
+            (RegisterOrConstant::Constant(key), RegisterOrConstant::Register(value)) => {
+                OpCode::SetUpTableCR { table, key, value }
+            }
+            (RegisterOrConstant::Constant(key), RegisterOrConstant::Constant(value)) => {
+                OpCode::SetUpTableCC { table, key, value }
+            }
+        },
+    }
+}
+
 pub fn simple_binop_opcode(
     simple_binop: SimpleBinOp,
     dest: RegisterIndex,
@@ -465,22 +532,41 @@ pub fn comparison_binop_opcode(
     }
 }
 
+// Comparisons involving NaN are always false (and `~=` is always true), so `a > b` must be folded
+// as `b < a` and never as `not (a <= b)`. The bitwise equality used to deduplicate constants does
+// not apply here.
 pub fn comparison_binop_const_fold<'gc>(
     comparison_binop: ComparisonBinOp,
     left: Constant<'gc>,
     right: Constant<'gc>,
 ) -> Option<Constant<'gc>> {
-    match comparison_binop {
-        ComparisonBinOp::Equal => Some(Constant::Boolean(left.to_value() == right.to_value())),
-        ComparisonBinOp::LessThan => match left.to_value().less_than(right.to_value()) {
-            Some(a) => Some(Constant::Boolean(a)),
-            _ => None,
-        },
-        ComparisonBinOp::LessEqual => match left.to_value().less_equal(right.to_value()) {
-            Some(a) => Some(Constant::Boolean(a)),
-            _ => None,
-        },
-        _ => None,
+    let (left, right) = (left.to_value(), right.to_value());
+    let res = match comparison_binop {
+        ComparisonBinOp::Equal => left == right,
+        ComparisonBinOp::NotEqual => left != right,
+        ComparisonBinOp::LessThan => left.less_than(right)?,
+        ComparisonBinOp::LessEqual => left.less_equal(right)?,
+        ComparisonBinOp::GreaterThan => right.less_than(left)?,
+        ComparisonBinOp::GreaterEqual => right.less_equal(left)?,
+    };
+    Some(Constant::Boolean(res))
+}
+
+// Only strings and numbers are folded, concatenating any other constant is an error that must
+// happen at runtime.
+pub fn concat_const_fold<'gc>(
+    mc: MutationContext<'gc, '_>,
+    left: Constant<'gc>,
+    right: Constant<'gc>,
+) -> Option<Constant<'gc>> {
+    match (left, right) {
+        (Constant::Nil, _)
+        | (Constant::Boolean(_), _)
+        | (_, Constant::Nil)
+        | (_, Constant::Boolean(_)) => None,
+        (left, right) => String::concat_all(mc, &[left.to_value(), right.to_value()])
+            .ok()
+            .map(Constant::String),
     }
 }
 
@@ -504,6 +590,11 @@ pub fn unop_const_fold<'gc>(unop: UnaryOperator, cons: Constant<'gc>) -> Option<
             Some(a) => Constant::from_value(a),
             _ => None,
         },
-        _ => None,
+        // Only string lengths are folded, the length of anything else is an error or may call a
+        // metamethod.
+        UnaryOperator::Len => match cons {
+            Constant::String(s) => Some(Constant::Integer(s.len() as i64)),
+            _ => None,
+        },
     }
 }
@@ -1,9 +1,25 @@
 use crate::RegisterIndex;
 
-/// Allocates registers in the range [0-255].
+use super::compiler::CompilerError;
+
+/// An attempt to free a register that is not allocated, which is always a compiler bug.  This is
+/// kept small so that the many fallible `free` calls do not bloat the frames of the recursive
+/// compiler functions, and becomes a `CompilerError::Internal` when propagated.
+#[derive(Debug)]
+pub struct InvalidFree(RegisterIndex);
+
+impl From<InvalidFree> for CompilerError {
+    fn from(InvalidFree(register): InvalidFree) -> CompilerError {
+        CompilerError::Internal(format!("freed unallocated register {}", register.0))
+    }
+}
+
+/// Allocates registers in the range [0-255], or a smaller range if given a lower limit.
 pub struct RegisterAllocator {
     // The total array of registers, marking whether they are allocated
     registers: [bool; 256],
+    // The number of registers available for allocation, at most 256
+    limit: u16,
     // The first free register
     first_free: u16,
     // The free register after the last used register
@@ -16,6 +32,7 @@ impl Default for RegisterAllocator {
     fn default() -> RegisterAllocator {
         RegisterAllocator {
             registers: [false; 256],
+            limit: 256,
             first_free: 0,
             stack_top: 0,
             stack_size: 0,
@@ -24,6 +41,14 @@ impl Default for RegisterAllocator {
 }
 
 impl RegisterAllocator {
+    /// Creates an allocator that only allocates registers below `limit`, which is capped to 256.
+    pub fn with_limit(limit: u16) -> RegisterAllocator {
+        RegisterAllocator {
+            limit: limit.min(256),
+            ..RegisterAllocator::default()
+        }
+    }
+
     /// Returns the index immediately after the largest used register index
     pub fn stack_top(&self) -> u16 {
         self.stack_top
@@ -38,7 +63,7 @@ impl RegisterAllocator {
     /// Allocates any single available register, returns it if one is available.
     #[must_use = "unused register allocation"]
     pub fn allocate(&mut self) -> Option<RegisterIndex> {
-        if self.first_free < 256 {
+        if self.first_free < self.limit {
             let register = self.first_free as u8;
             self.registers[register as usize] = true;
 
@@ -61,18 +86,19 @@ impl RegisterAllocator {
         }
     }
 
-    /// Free a single register.
-    pub fn free(&mut self, register: RegisterIndex) {
-        assert!(
-            self.registers[register.0 as usize],
-            "cannot free unallocated register",
-        );
+    /// Free a single register.  Freeing an unallocated register is always a compiler bug, and is
+    /// reported without changing any allocation.
+    pub fn free(&mut self, register: RegisterIndex) -> Result<(), InvalidFree> {
+        if !self.registers[register.0 as usize] {
+            return Err(InvalidFree(register));
+        }
         if register.0 as u16 + 1 == self.stack_top {
             self.pop_to(register.0 as u16);
         } else {
             self.registers[register.0 as usize] = false;
             self.first_free = self.first_free.min(register.0 as u16);
         }
+        Ok(())
     }
 
     /// Allocates a block of registers of the given size (which must be > 0) always at the end of
@@ -81,9 +107,9 @@ impl RegisterAllocator {
     pub fn push(&mut self, size: u8) -> Option<RegisterIndex> {
         if size == 0 {
             None
-        } else if size as u16 <= 256 - self.stack_top {
+        } else if size as u16 <= self.limit - self.stack_top {
             let rbegin = self.stack_top as u8;
-            for i in rbegin..rbegin + size {
+            for i in self.stack_top..self.stack_top + size as u16 {
                 self.registers[i as usize] = true;
             }
             if self.first_free == self.stack_top {
@@ -115,3 +141,233 @@ impl RegisterAllocator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_in_order() {
+        let mut allocator = RegisterAllocator::default();
+        for i in 0..10 {
+            assert_eq!(allocator.allocate(), Some(RegisterIndex(i)));
+        }
+        assert_eq!(allocator.stack_top(), 10);
+        assert_eq!(allocator.stack_size(), 10);
+        assert_eq!(allocator.first_free, 10);
+    }
+
+    #[test]
+    fn allocate_fills_holes() {
+        let mut allocator = RegisterAllocator::default();
+        for _ in 0..5 {
+            allocator.allocate().unwrap();
+        }
+        allocator.free(RegisterIndex(3)).unwrap();
+        allocator.free(RegisterIndex(1)).unwrap();
+        assert_eq!(allocator.first_free, 1);
+        assert_eq!(allocator.stack_top(), 5);
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(1)));
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(3)));
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(5)));
+    }
+
+    #[test]
+    fn free_top_lowers_stack_top() {
+        let mut allocator = RegisterAllocator::default();
+        for _ in 0..4 {
+            allocator.allocate().unwrap();
+        }
+        allocator.free(RegisterIndex(1)).unwrap();
+        allocator.free(RegisterIndex(2)).unwrap();
+        assert_eq!(allocator.stack_top(), 4);
+        // Freeing the top register also drops the free registers below it from the stack.
+        allocator.free(RegisterIndex(3)).unwrap();
+        assert_eq!(allocator.stack_top(), 1);
+        assert_eq!(allocator.first_free, 1);
+        assert_eq!(allocator.stack_size(), 4);
+    }
+
+    #[test]
+    fn invalid_free() {
+        let mut allocator = RegisterAllocator::default();
+        let r = allocator.allocate().unwrap();
+        allocator.free(r).unwrap();
+        match allocator.free(r) {
+            Err(InvalidFree(invalid)) => assert_eq!(invalid, r),
+            res => panic!("expected an invalid free, got {:?}", res),
+        }
+        assert_eq!(allocator.stack_top(), 0);
+        assert!(!allocator.registers[r.0 as usize]);
+    }
+
+    #[test]
+    fn push_at_stack_top() {
+        let mut allocator = RegisterAllocator::default();
+        for _ in 0..3 {
+            allocator.allocate().unwrap();
+        }
+        allocator.free(RegisterIndex(0)).unwrap();
+        // A push always goes above the stack top, leaving the hole at 0 free.
+        assert_eq!(allocator.push(4), Some(RegisterIndex(3)));
+        assert_eq!(allocator.stack_top(), 7);
+        assert_eq!(allocator.first_free, 0);
+        for i in 3..7 {
+            assert!(allocator.registers[i as usize]);
+        }
+    }
+
+    #[test]
+    fn push_zero() {
+        let mut allocator = RegisterAllocator::default();
+        assert_eq!(allocator.push(0), None);
+        assert_eq!(allocator.stack_top(), 0);
+        assert_eq!(allocator.stack_size(), 0);
+    }
+
+    #[test]
+    fn push_advances_first_free() {
+        let mut allocator = RegisterAllocator::default();
+        assert_eq!(allocator.push(3), Some(RegisterIndex(0)));
+        assert_eq!(allocator.first_free, 3);
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(3)));
+    }
+
+    #[test]
+    fn pop_to() {
+        let mut allocator = RegisterAllocator::default();
+        allocator.push(8).unwrap();
+        allocator.free(RegisterIndex(2)).unwrap();
+        allocator.free(RegisterIndex(3)).unwrap();
+        allocator.pop_to(5);
+        assert_eq!(allocator.stack_top(), 5);
+        assert_eq!(allocator.first_free, 2);
+        assert_eq!(allocator.stack_size(), 8);
+        for i in 5..8 {
+            assert!(!allocator.registers[i as usize]);
+        }
+
+        // The free registers just below the new top are dropped from the stack as well.
+        allocator.pop_to(4);
+        assert_eq!(allocator.stack_top(), 2);
+        assert_eq!(allocator.first_free, 2);
+
+        // Popping to at or above the stack top does nothing.
+        allocator.pop_to(2);
+        allocator.pop_to(100);
+        assert_eq!(allocator.stack_top(), 2);
+        assert!(allocator.registers[1]);
+    }
+
+    #[test]
+    fn full_range() {
+        let mut allocator = RegisterAllocator::default();
+        assert_eq!(allocator.push(255), Some(RegisterIndex(0)));
+        assert_eq!(allocator.push(2), None);
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(255)));
+        assert_eq!(allocator.allocate(), None);
+        assert_eq!(allocator.push(1), None);
+        assert_eq!(allocator.stack_top(), 256);
+        assert_eq!(allocator.stack_size(), 256);
+
+        allocator.pop_to(0);
+        assert_eq!(allocator.stack_top(), 0);
+        assert_eq!(allocator.first_free, 0);
+        assert_eq!(allocator.stack_size(), 256);
+    }
+
+    #[test]
+    fn limit() {
+        let mut allocator = RegisterAllocator::with_limit(4);
+        assert_eq!(allocator.push(3), Some(RegisterIndex(0)));
+        assert_eq!(allocator.push(2), None);
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(3)));
+        assert_eq!(allocator.allocate(), None);
+        allocator.free(RegisterIndex(1)).unwrap();
+        assert_eq!(allocator.allocate(), Some(RegisterIndex(1)));
+
+        let allocator = RegisterAllocator::with_limit(1000);
+        assert_eq!(allocator.stack_size(), 0);
+        let mut allocator = allocator;
+        assert_eq!(allocator.push(255), Some(RegisterIndex(0)));
+        assert_eq!(allocator.push(1), Some(RegisterIndex(255)));
+        assert_eq!(allocator.push(1), None);
+    }
+
+    #[test]
+    fn random_operations() {
+        // A simple linear congruential generator, so that failures are reproducible.
+        let mut state = 0x2545_f491u32;
+        let mut next = |bound: u32| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) % bound
+        };
+
+        for &limit in &[256, 40, 7] {
+            let mut allocator = RegisterAllocator::with_limit(limit);
+            let mut model = vec![false; limit as usize];
+            let mut stack_size = 0;
+
+            for _ in 0..10_000 {
+                match next(4) {
+                    0 => {
+                        let expected = model.iter().position(|&a| !a);
+                        let register = allocator.allocate();
+                        assert_eq!(register.map(|r| r.0 as usize), expected);
+                        if let Some(r) = register {
+                            model[r.0 as usize] = true;
+                        }
+                    }
+                    1 => {
+                        let allocated = model
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, &a)| a)
+                            .map(|(i, _)| i)
+                            .collect::<Vec<_>>();
+                        if !allocated.is_empty() {
+                            let r = allocated[next(allocated.len() as u32) as usize];
+                            allocator.free(RegisterIndex(r as u8)).unwrap();
+                            model[r] = false;
+                        }
+                    }
+                    2 => {
+                        let size = next(6) as u8;
+                        let top = model.iter().rposition(|&a| a).map_or(0, |r| r + 1);
+                        let expected = if size != 0 && top + size as usize <= model.len() {
+                            Some(top)
+                        } else {
+                            None
+                        };
+                        let start = allocator.push(size);
+                        assert_eq!(start.map(|r| r.0 as usize), expected);
+                        if let Some(start) = start {
+                            for r in &mut model[start.0 as usize..start.0 as usize + size as usize]
+                            {
+                                *r = true;
+                            }
+                        }
+                    }
+                    _ => {
+                        let new_top = next(limit as u32 + 1) as u16;
+                        allocator.pop_to(new_top);
+                        for r in &mut model[new_top as usize..] {
+                            *r = false;
+                        }
+                    }
+                }
+
+                let top = model.iter().rposition(|&a| a).map_or(0, |r| r + 1);
+                stack_size = stack_size.max(top);
+                assert_eq!(allocator.stack_top() as usize, top);
+                assert_eq!(allocator.stack_size() as usize, stack_size);
+                let first_free = allocator.first_free as usize;
+                assert!(first_free >= model.len() || !model[first_free]);
+                assert!(model[..first_free.min(model.len())].iter().all(|&a| a));
+                for (r, &allocated) in model.iter().enumerate() {
+                    assert_eq!(allocator.registers[r], allocated);
+                }
+            }
+        }
+    }
+}
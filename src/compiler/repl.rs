@@ -0,0 +1,119 @@
+use std::mem;
+
+use gc_arena::{Gc, GcCell, MutationContext};
+
+use crate::{
+    parse_chunk, Closure, ClosureState, Error, FunctionProto, InternedStringSet, Table, UpValue,
+    UpValueState, Value,
+};
+
+use super::compiler::compile_repl_chunk;
+
+/// Compiles REPL input one piece at a time, keeping the top-level locals declared by each input in
+/// scope for all of the inputs that follow.
+///
+/// These REPL locals are kept in upvalues of the top-level function rather than in registers, so
+/// they are shared with every closure that captures them.  An input is run by calling the closure
+/// returned from `ReplCompiler::closure`.
+#[derive(Debug, Default)]
+pub struct ReplCompiler {
+    // The REPL locals in scope, each name listed once.
+    locals: Vec<Box<[u8]>>,
+    // For each of `locals`, the index of the upvalue holding it in the closure of the most recently
+    // compiled input.
+    slots: Vec<usize>,
+    // For the most recently compiled input, the index of the upvalue holding each of the REPL locals
+    // it starts with in the closure of the input before it.
+    previous_slots: Vec<usize>,
+}
+
+impl ReplCompiler {
+    pub fn new() -> ReplCompiler {
+        ReplCompiler::default()
+    }
+
+    /// The names of the REPL locals in scope, in the order they were first declared.  A name
+    /// declared more than once is listed only once.
+    pub fn locals(&self) -> impl Iterator<Item = &[u8]> {
+        self.locals.iter().map(|name| &name[..])
+    }
+
+    /// Parses and compiles an input.  An input that is an expression list, such as `x + 1` or
+    /// `f(), g()`, is compiled as `return <input>` so that its values are returned, any other input
+    /// is compiled as a chunk.  Incomplete input fails with `ParserError::EndOfStream`.
+    pub fn compile<'gc>(
+        &mut self,
+        mc: MutationContext<'gc, '_>,
+        interned_strings: InternedStringSet<'gc>,
+        source: &[u8],
+    ) -> Result<FunctionProto<'gc>, Error<'gc>> {
+        let mut return_source = b"return ".to_vec();
+        return_source.extend_from_slice(source);
+        let chunk = match parse_chunk(&return_source[..], |s| interned_strings.new_string(mc, s)) {
+            Ok(chunk) => chunk,
+            Err(_) => parse_chunk(source, |s| interned_strings.new_string(mc, s))?,
+        };
+
+        let locals = self
+            .locals
+            .iter()
+            .map(|name| interned_strings.new_string(mc, name))
+            .collect::<Vec<_>>();
+        let (proto, new_locals) = compile_repl_chunk(mc, &chunk, &locals)?;
+
+        // A redeclared local takes over the slot of the local it shadows, so that the number of
+        // upvalues only grows with the number of distinct names.  The closure for this input still
+        // gives the new declaration its own upvalue, so closures that captured the old one keep it.
+        self.previous_slots = mem::replace(&mut self.slots, (1..=locals.len()).collect());
+        for (i, name) in new_locals.iter().enumerate() {
+            let slot = 1 + locals.len() + i;
+            match self
+                .locals
+                .iter()
+                .position(|local| &local[..] == name.as_bytes())
+            {
+                Some(j) => self.slots[j] = slot,
+                None => {
+                    self.locals.push(Box::from(name.as_bytes()));
+                    self.slots.push(slot);
+                }
+            }
+        }
+        Ok(proto)
+    }
+
+    /// Creates the closure that runs a prototype returned by `compile`.  `previous` must be the
+    /// closure created for the previously compiled input, if there was one, as it holds the REPL
+    /// locals declared before this input.
+    pub fn closure<'gc>(
+        &self,
+        mc: MutationContext<'gc, '_>,
+        proto: FunctionProto<'gc>,
+        environment: Table<'gc>,
+        previous: Option<Closure<'gc>>,
+    ) -> Closure<'gc> {
+        debug_assert!(proto.upvalues.len() > self.previous_slots.len());
+
+        let mut upvalues = vec![UpValue(GcCell::allocate(
+            mc,
+            UpValueState::Closed(Value::Table(environment)),
+        ))];
+        if let Some(previous) = previous {
+            upvalues.extend(self.previous_slots.iter().map(|&i| previous.0.upvalues[i]));
+        }
+        while upvalues.len() < proto.upvalues.len() {
+            upvalues.push(UpValue(GcCell::allocate(
+                mc,
+                UpValueState::Closed(Value::Nil),
+            )));
+        }
+
+        Closure(Gc::allocate(
+            mc,
+            ClosureState {
+                proto: Gc::allocate(mc, proto),
+                upvalues,
+            },
+        ))
+    }
+}
@@ -1,7 +1,6 @@
 use std::hash::{Hash, Hasher};
-use std::mem;
 
-use gc_arena::Collect;
+use gc_arena::{Collect, MutationContext};
 
 use crate::{String, Value};
 
@@ -38,6 +37,39 @@ impl<'gc> Constant<'gc> {
             Constant::String(s) => Value::String(s),
         }
     }
+
+    pub fn to_owned_constant(self) -> OwnedConstant {
+        match self {
+            Constant::Nil => OwnedConstant::Nil,
+            Constant::Boolean(b) => OwnedConstant::Boolean(b),
+            Constant::Integer(i) => OwnedConstant::Integer(i),
+            Constant::Number(n) => OwnedConstant::Number(n),
+            Constant::String(s) => OwnedConstant::String(s.as_bytes().to_vec()),
+        }
+    }
+
+    pub fn from_owned_constant(
+        mc: MutationContext<'gc, '_>,
+        constant: &OwnedConstant,
+    ) -> Constant<'gc> {
+        match constant {
+            OwnedConstant::Nil => Constant::Nil,
+            OwnedConstant::Boolean(b) => Constant::Boolean(*b),
+            OwnedConstant::Integer(i) => Constant::Integer(*i),
+            OwnedConstant::Number(n) => Constant::Number(*n),
+            OwnedConstant::String(s) => Constant::String(String::new(mc, s)),
+        }
+    }
+}
+
+/// A `Constant` which does not live in a GC arena, strings are held as owned bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedConstant {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(Vec<u8>),
 }
 
 impl<'gc> PartialEq for Constant<'gc> {
@@ -52,7 +84,7 @@ impl<'gc> PartialEq for Constant<'gc> {
             (Constant::Integer(a), Constant::Integer(b)) => a == b,
             (Constant::Integer(_), _) => false,
 
-            (Constant::Number(a), Constant::Number(b)) => float_bytes(*a) == float_bytes(*b),
+            (Constant::Number(a), Constant::Number(b)) => a.to_bits() == b.to_bits(),
             (Constant::Number(_), _) => false,
 
             (Constant::String(a), Constant::String(b)) => a == b,
@@ -79,7 +111,7 @@ impl<'gc> Hash for Constant<'gc> {
             }
             Constant::Number(n) => {
                 Hash::hash(&3, state);
-                float_bytes(*n).hash(state);
+                n.to_bits().hash(state);
             }
             Constant::String(s) => {
                 Hash::hash(&4, state);
@@ -88,7 +120,3 @@ impl<'gc> Hash for Constant<'gc> {
         }
     }
 }
-
-fn float_bytes(f: f64) -> u64 {
-    unsafe { mem::transmute(f) }
-}
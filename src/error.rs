@@ -6,7 +6,7 @@ use gc_arena::{Collect, MutationContext, StaticCollect};
 
 use crate::{
     BadThreadMode, BinaryOperatorError, ClosureError, CompilerError, InternedStringSet,
-    InvalidTableKey, ParserError, StringError, ThreadError, Value,
+    InvalidTableKey, LexerError, MetaError, ParserError, StringError, ThreadError, Value,
 };
 
 #[derive(Debug, Clone, Copy, Collect)]
@@ -43,15 +43,23 @@ impl<'gc> fmt::Display for RuntimeError<'gc> {
     }
 }
 
+/// Any error produced while loading or running Lua code.
+///
+/// Compilation failures are tagged by the phase that produced them: `LexerError`, `ParserError`
+/// or `CompilerError`. Errors carry no source location.
 // Safe, does not implement drop
 #[derive(Debug, Collect)]
 #[collect(unsafe_drop)]
 pub enum Error<'gc> {
     IoError(StaticCollect<io::Error>),
+    /// Also holds lexer errors found by the parser, so `ParserError` never wraps a
+    /// `ParserError::LexerError` when converted through `From`.
+    LexerError(LexerError),
     ParserError(ParserError),
     CompilerError(CompilerError),
     ClosureError(ClosureError),
     InvalidTableKey(InvalidTableKey),
+    MetaError(MetaError),
     StringError(StringError),
     ThreadError(ThreadError),
     BadThreadMode(BadThreadMode),
@@ -66,10 +74,12 @@ impl<'gc> fmt::Display for Error<'gc> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::IoError(error) => write!(fmt, "i/o error: {}", error.0),
+            Error::LexerError(error) => write!(fmt, "lexer error: {}", error),
             Error::ParserError(error) => write!(fmt, "parser error: {}", error),
             Error::CompilerError(error) => write!(fmt, "compiler error: {}", error),
             Error::ClosureError(error) => write!(fmt, "closure error: {}", error),
             Error::InvalidTableKey(error) => write!(fmt, "invalid table key: {}", error),
+            Error::MetaError(error) => write!(fmt, "metamethod error: {}", error),
             Error::StringError(error) => write!(fmt, "string error: {}", error),
             Error::ThreadError(error) => write!(fmt, "thread error: {}", error),
             Error::BadThreadMode(error) => write!(fmt, "bad thread mode: {}", error),
@@ -86,9 +96,21 @@ impl<'gc> From<io::Error> for Error<'gc> {
     }
 }
 
+impl<'gc> From<LexerError> for Error<'gc> {
+    fn from(error: LexerError) -> Error<'gc> {
+        Error::LexerError(error)
+    }
+}
+
+// Lexer errors surfaced through the parser are reported as `Error::LexerError` rather than
+// `Error::ParserError(ParserError::LexerError(_))`, so callers can tell which phase failed without
+// unwrapping the parser error.
 impl<'gc> From<ParserError> for Error<'gc> {
     fn from(error: ParserError) -> Error<'gc> {
-        Error::ParserError(error)
+        match error {
+            ParserError::LexerError(error) => Error::LexerError(error),
+            error => Error::ParserError(error),
+        }
     }
 }
 
@@ -110,6 +132,12 @@ impl<'gc> From<InvalidTableKey> for Error<'gc> {
     }
 }
 
+impl<'gc> From<MetaError> for Error<'gc> {
+    fn from(error: MetaError) -> Error<'gc> {
+        Error::MetaError(error)
+    }
+}
+
 impl<'gc> From<StringError> for Error<'gc> {
     fn from(error: StringError) -> Error<'gc> {
         Error::StringError(error)
@@ -150,10 +178,12 @@ impl<'gc> Error<'gc> {
     pub fn to_static(self) -> StaticError {
         match self {
             Error::IoError(error) => StaticError::IoError(error.0),
+            Error::LexerError(error) => StaticError::LexerError(error),
             Error::ParserError(error) => StaticError::ParserError(error),
             Error::CompilerError(error) => StaticError::CompilerError(error),
             Error::ClosureError(error) => StaticError::ClosureError(error),
             Error::InvalidTableKey(error) => StaticError::InvalidTableKey(error),
+            Error::MetaError(error) => StaticError::MetaError(error),
             Error::StringError(error) => StaticError::StringError(error),
             Error::ThreadError(error) => StaticError::ThreadError(error),
             Error::BadThreadMode(error) => StaticError::BadThreadMode(error),
@@ -182,14 +212,17 @@ impl<'gc> Error<'gc> {
     }
 }
 
+/// A version of `Error` that does not borrow from the arena, with the same phase variants.
 #[derive(Debug, Collect)]
 #[collect(require_static)]
 pub enum StaticError {
     IoError(io::Error),
+    LexerError(LexerError),
     ParserError(ParserError),
     CompilerError(CompilerError),
     ClosureError(ClosureError),
     InvalidTableKey(InvalidTableKey),
+    MetaError(MetaError),
     StringError(StringError),
     ThreadError(ThreadError),
     BadThreadMode(BadThreadMode),
@@ -204,10 +237,12 @@ impl fmt::Display for StaticError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             StaticError::IoError(error) => write!(fmt, "i/o error: {}", error),
+            StaticError::LexerError(error) => write!(fmt, "lexer error: {}", error),
             StaticError::ParserError(error) => write!(fmt, "parser error: {}", error),
             StaticError::CompilerError(error) => write!(fmt, "compiler error: {}", error),
             StaticError::ClosureError(error) => write!(fmt, "closure error: {}", error),
             StaticError::InvalidTableKey(error) => write!(fmt, "invalid table key: {}", error),
+            StaticError::MetaError(error) => write!(fmt, "metamethod error: {}", error),
             StaticError::StringError(error) => write!(fmt, "string error: {}", error),
             StaticError::ThreadError(error) => write!(fmt, "thread error: {}", error),
             StaticError::BadThreadMode(error) => write!(fmt, "bad thread mode: {}", error),
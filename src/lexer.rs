@@ -68,6 +68,76 @@ pub enum Token<S> {
     String(S),
 }
 
+/// Describes the token for error messages, as the quoted keyword or symbol, `identifier 'name'`,
+/// `number` or `string`.
+impl<S: AsRef<[u8]>> fmt::Display for Token<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Token::Break => "break",
+            Token::Do => "do",
+            Token::Else => "else",
+            Token::ElseIf => "elseif",
+            Token::End => "end",
+            Token::Function => "function",
+            Token::Goto => "goto",
+            Token::If => "if",
+            Token::In => "in",
+            Token::Local => "local",
+            Token::Nil => "nil",
+            Token::For => "for",
+            Token::While => "while",
+            Token::Repeat => "repeat",
+            Token::Until => "until",
+            Token::Return => "return",
+            Token::Then => "then",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Not => "not",
+            Token::And => "and",
+            Token::Or => "or",
+            Token::Minus => "-",
+            Token::Add => "+",
+            Token::Mul => "*",
+            Token::Div => "/",
+            Token::IDiv => "//",
+            Token::Pow => "^",
+            Token::Mod => "%",
+            Token::Len => "#",
+            Token::BitNotXor => "~",
+            Token::BitAnd => "&",
+            Token::BitOr => "|",
+            Token::ShiftRight => ">>",
+            Token::ShiftLeft => "<<",
+            Token::Concat => "..",
+            Token::Dots => "...",
+            Token::Assign => "=",
+            Token::LessThan => "<",
+            Token::LessEqual => "<=",
+            Token::GreaterThan => ">",
+            Token::GreaterEqual => ">=",
+            Token::Equal => "==",
+            Token::NotEqual => "~=",
+            Token::Dot => ".",
+            Token::SemiColon => ";",
+            Token::Colon => ":",
+            Token::DoubleColon => "::",
+            Token::Comma => ",",
+            Token::LeftParen => "(",
+            Token::RightParen => ")",
+            Token::LeftBracket => "[",
+            Token::RightBracket => "]",
+            Token::LeftBrace => "{",
+            Token::RightBrace => "}",
+            Token::Integer(_) | Token::Float(_) => return write!(f, "number"),
+            Token::String(_) => return write!(f, "string"),
+            Token::Name(name) => {
+                return write!(f, "identifier '{}'", String::from_utf8_lossy(name.as_ref()))
+            }
+        };
+        write!(f, "'{}'", symbol)
+    }
+}
+
 #[derive(Debug, Collect)]
 #[collect(require_static)]
 pub enum LexerError {
@@ -83,6 +153,12 @@ pub enum LexerError {
     UnfinishedLongString,
     BadNumber,
     IOError(io::Error),
+    /// An internal invariant of the lexer was violated on the given 0-indexed line, which is always
+    /// a bug in the lexer rather than in the source.
+    Internal {
+        invariant: &'static str,
+        line: u64,
+    },
 }
 
 impl fmt::Display for LexerError {
@@ -112,13 +188,25 @@ impl fmt::Display for LexerError {
             LexerError::UnfinishedLongString => write!(f, "unfinished long string"),
             LexerError::BadNumber => write!(f, "malformed number"),
             LexerError::IOError(err) => write!(f, "IO Error: {}", err),
+            LexerError::Internal { invariant, line } => {
+                write!(f, "internal lexer error on line {}: {}", line, invariant)
+            }
         }
     }
 }
 
+/// Extensions to the standard Lua syntax, all disabled by default.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct LexerOptions {
+    /// Accept binary integer literals such as `0b1010`, which wrap around on overflow.  When this
+    /// is false, `0b1010` is the integer `0` followed by the name `b1010`.
+    pub binary_literals: bool,
+}
+
 pub struct Lexer<R, CS> {
     source: Option<R>,
     create_string: CS,
+    options: LexerOptions,
     peek_buffer: Vec<u8>,
     string_buffer: Vec<u8>,
     line_number: u64,
@@ -130,9 +218,14 @@ where
     CS: FnMut(&[u8]) -> S,
 {
     pub fn new(source: R, create_string: CS) -> Lexer<R, CS> {
+        Lexer::with_options(source, create_string, LexerOptions::default())
+    }
+
+    pub fn with_options(source: R, create_string: CS, options: LexerOptions) -> Lexer<R, CS> {
         Lexer {
             source: Some(source),
             create_string,
+            options,
             peek_buffer: Vec::new(),
             string_buffer: Vec::new(),
             line_number: 0,
@@ -162,8 +255,15 @@ where
                         } else {
                             self.advance(2);
 
-                            match (self.peek(0)?, self.peek(1)?) {
-                                (Some(b'['), Some(b'=')) | (Some(b'['), Some(b'[')) => {
+                            // A comment is only long if it starts with a whole opening long
+                            // bracket, otherwise something like `--[=` is a short comment.
+                            let mut sep_end = 1;
+                            while self.peek(sep_end)? == Some(b'=') {
+                                sep_end += 1;
+                            }
+
+                            match (self.peek(0)?, self.peek(sep_end)?) {
+                                (Some(b'['), Some(b'[')) => {
                                     // long comment
                                     self.read_long_string(false)?;
                                 }
@@ -205,7 +305,7 @@ where
             if let Some(c) = self.peek(0)? {
                 Ok(Some(match c {
                     b' ' | b'\t' | VERTICAL_TAB | FORM_FEED | b'\n' | b'\r' => {
-                        unreachable!("whitespace should have been skipped");
+                        return Err(self.internal("whitespace should have been skipped"));
                     }
 
                     b'-' => {
@@ -213,7 +313,7 @@ where
                             self.advance(1);
                             Token::Minus
                         } else {
-                            unreachable!("whitespace should have been skipped");
+                            return Err(self.internal("comments should have been skipped"));
                         }
                     }
 
@@ -373,8 +473,10 @@ where
     // Read any of "\n", "\r", "\n\r", or "\r\n" as a single newline, and increment the current line
     // number.  If `append_buffer` is true, then appends the read newline to the string buffer.
     fn read_line_end(&mut self, append_string: bool) -> Result<(), LexerError> {
-        let newline = self.peek(0).unwrap().unwrap();
-        assert!(is_newline(newline));
+        let newline = match self.peek(0)? {
+            Some(c) if is_newline(c) => c,
+            _ => return Err(self.internal("line end does not start with a newline")),
+        };
         self.advance(1);
         if append_string {
             self.string_buffer.push(newline);
@@ -396,8 +498,10 @@ where
     // Read a string on a single line delimited by ' or " that allows for \ escaping of certain
     // characters.  Always reads the contained string into the string buffer.
     fn read_short_string(&mut self) -> Result<(), LexerError> {
-        let start_quote = self.peek(0).unwrap().unwrap();
-        assert!(start_quote == b'\'' || start_quote == b'"');
+        let start_quote = match self.peek(0)? {
+            Some(c) if c == b'\'' || c == b'"' => c,
+            _ => return Err(self.internal("short string does not start with a quote")),
+        };
         self.advance(1);
 
         self.string_buffer.clear();
@@ -469,8 +573,10 @@ where
                         self.string_buffer.push(b'"');
                     }
 
+                    // An escaped line end of any kind is a single '\n'
                     b'\n' | b'\r' => {
-                        self.read_line_end(true)?;
+                        self.read_line_end(false)?;
+                        self.string_buffer.push(b'\n');
                     }
 
                     b'x' => {
@@ -500,7 +606,10 @@ where
                                     self.advance(1);
                                     break;
                                 } else if let Some(h) = from_hex_digit(c) {
-                                    u = (u << 4) | h as u32;
+                                    u = u
+                                        .checked_mul(16)
+                                        .ok_or(LexerError::EscapeUnicodeInvalid)?
+                                        | h as u32;
                                     self.advance(1);
                                 } else {
                                     return Err(LexerError::EscapeUnicodeEnd);
@@ -564,7 +673,9 @@ where
     // Read a [=*[...]=*] sequence with matching numbers of '='.  If `into_string` is true, writes
     // the contained string into the string buffer.
     fn read_long_string(&mut self, into_string: bool) -> Result<(), LexerError> {
-        assert_eq!(self.peek(0).unwrap().unwrap(), b'[');
+        if self.peek(0)? != Some(b'[') {
+            return Err(self.internal("long string does not start with '['"));
+        }
         self.advance(1);
 
         if into_string {
@@ -633,16 +744,22 @@ where
     // hex integers (0xdeadbeef), decimal floating point with optional exponent and exponent sign
     // (3.21e+1), and hex floats with optional exponent and exponent sign (0xe.2fp-1c).
     fn read_numeral(&mut self) -> Result<Token<S>, LexerError> {
-        let p1 = self.peek(0).unwrap().unwrap();
-        assert!(p1 == b'.' || is_digit(p1));
+        let p1 = match self.peek(0)? {
+            Some(c) if c == b'.' || is_digit(c) => c,
+            _ => return Err(self.internal("numeral does not start with a digit or '.'")),
+        };
 
         self.string_buffer.clear();
 
         let p2 = self.peek(1)?;
+        if self.options.binary_literals && p1 == b'0' && (p2 == Some(b'b') || p2 == Some(b'B')) {
+            return self.read_binary_integer();
+        }
+
         let is_hex = p1 == b'0' && (p2 == Some(b'x') || p2 == Some(b'X'));
-        if is_hex {
+        if let (true, Some(p2)) = (is_hex, p2) {
             self.string_buffer.push(p1);
-            self.string_buffer.push(p2.unwrap());
+            self.string_buffer.push(p2);
             self.advance(2);
         }
 
@@ -708,6 +825,31 @@ where
         ))
     }
 
+    // Reads a binary integer literal (0b1010), the leading "0b" must not have been consumed yet.
+    fn read_binary_integer(&mut self) -> Result<Token<S>, LexerError> {
+        self.advance(2);
+
+        let mut i: i64 = 0;
+        let mut has_digits = false;
+        while let Some(c) = self.peek(0)? {
+            if !is_digit(c) {
+                break;
+            }
+            let d = from_digit(c)
+                .filter(|&d| d < 2)
+                .ok_or(LexerError::BadNumber)?;
+            i = i.wrapping_mul(2).wrapping_add(d as i64);
+            has_digits = true;
+            self.advance(1);
+        }
+
+        if has_digits {
+            Ok(Token::Integer(i))
+        } else {
+            Err(LexerError::BadNumber)
+        }
+    }
+
     fn peek(&mut self, n: usize) -> Result<Option<u8>, LexerError> {
         if let Some(source) = self.source.as_mut() {
             while self.peek_buffer.len() <= n {
@@ -733,6 +875,13 @@ where
         Ok(self.peek_buffer.get(n).cloned())
     }
 
+    fn internal(&self, invariant: &'static str) -> LexerError {
+        LexerError::Internal {
+            invariant,
+            line: self.line_number,
+        }
+    }
+
     fn advance(&mut self, n: usize) {
         assert!(
             n <= self.peek_buffer.len(),
@@ -748,6 +897,20 @@ where
     }
 }
 
+/// Lexers are also iterators over their tokens.  Tokens are read lazily from the source as the
+/// iterator is advanced, and iteration stops after the end of the source or the first error.
+impl<R, S, CS> Iterator for Lexer<R, CS>
+where
+    R: Read,
+    CS: FnMut(&[u8]) -> S,
+{
+    type Item = Result<Token<S>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_token().transpose()
+    }
+}
+
 pub fn read_integer(s: &[u8]) -> Option<i64> {
     let (is_neg, s) = read_neg(s);
 
@@ -771,7 +934,7 @@ pub fn read_integer(s: &[u8]) -> Option<i64> {
 pub fn read_hex_integer(s: &[u8]) -> Option<i64> {
     let (is_neg, s) = read_neg(s);
 
-    if s[0] != b'0' || (s[1] != b'x' && s[1] != b'X') {
+    if s.len() < 2 || s[0] != b'0' || (s[1] != b'x' && s[1] != b'X') {
         return None;
     }
 
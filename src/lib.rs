@@ -15,22 +15,28 @@ mod table;
 mod thread;
 mod types;
 mod value;
+mod verify;
 
 mod stdlib;
 
 pub use callback::{Callback, CallbackResult, CallbackReturn, Continuation};
 pub use closure::{
-    Closure, ClosureError, ClosureState, FunctionProto, UpValue, UpValueDescriptor, UpValueState,
+    Closure, ClosureError, ClosureState, FunctionProto, ListedOpCode, Listing, LocalVarInfo,
+    OwnedProto, UpValue, UpValueDescriptor, UpValueState,
 };
-pub use compiler::{compile, compile_chunk, CompilerError};
-pub use constant::Constant;
+pub use compiler::{
+    compile, compile_chunk, compile_chunk_with_cache, compile_chunk_with_options,
+    compile_expression, compile_with_options, CompileStats, CompilerCache, CompilerError,
+    CompilerOptions, CompilerOutput, CompilerWarning, ReplCompiler,
+};
+pub use constant::{Constant, OwnedConstant};
 pub use error::{Error, RuntimeError, StaticError, TypeError};
-pub use lexer::{Lexer, LexerError, Token};
+pub use lexer::{Lexer, LexerError, LexerOptions, Token};
 pub use lua::{Lua, Root};
-pub use opcode::OpCode;
-pub use parser::{parse_chunk, ParserError};
+pub use opcode::{OpCode, PackedOpCode};
+pub use parser::{parse_chunk, parse_script, ParserError};
 pub use string::{InternedStringSet, String, StringError};
-pub use table::{InvalidTableKey, Table, TableState};
+pub use table::{InvalidTableKey, MetaError, MetaResult, PositionOutOfBounds, Table, TableState};
 pub use thread::{
     BadThreadMode, BinaryOperatorError, Thread, ThreadError, ThreadMode, ThreadSequence,
 };
@@ -38,3 +44,4 @@ pub use types::{
     ConstantIndex16, ConstantIndex8, Opt254, PrototypeIndex, RegisterIndex, UpValueIndex, VarCount,
 };
 pub use value::{Function, Value};
+pub use verify::{verify, VerifyError};
@@ -12,6 +12,9 @@ pub struct Root<'gc> {
     pub main_thread: Thread<'gc>,
     pub globals: Table<'gc>,
     pub interned_strings: InternedStringSet<'gc>,
+    /// A table for the embedder to keep values alive across calls to `Lua::mutate` and
+    /// `Lua::sequence`, which is not reachable from Lua code.
+    pub registry: Table<'gc>,
 }
 
 impl<'gc> Root<'gc> {
@@ -20,6 +23,7 @@ impl<'gc> Root<'gc> {
             main_thread: Thread::new(mc, false),
             globals: Table::new(mc),
             interned_strings: InternedStringSet::new(mc),
+            registry: Table::new(mc),
         };
 
         load_base(mc, root, root.globals);
@@ -4,7 +4,7 @@ use crate::{
     ConstantIndex16, ConstantIndex8, Opt254, PrototypeIndex, RegisterIndex, UpValueIndex, VarCount,
 };
 
-#[derive(Debug, Copy, Clone, Collect)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Collect)]
 #[collect(require_static)]
 pub enum OpCode {
     Move {
@@ -26,8 +26,12 @@ pub enum OpCode {
         dest: RegisterIndex,
         count: u8,
     },
+    // The sizes are only hints for how many array and map entries to preallocate, and saturate at
+    // 255.
     NewTable {
         dest: RegisterIndex,
+        array_size: u8,
+        map_size: u8,
     },
     GetTableR {
         dest: RegisterIndex,
@@ -89,6 +93,18 @@ pub enum OpCode {
         key: ConstantIndex8,
         value: ConstantIndex8,
     },
+    // Global access through the `_ENV` upvalue, equivalent to `GetUpTableC` / `SetUpTableCR` but
+    // only emitted for global variables when `CompilerOptions::global_opcodes` is set.
+    GetGlobal {
+        dest: RegisterIndex,
+        env: UpValueIndex,
+        name: ConstantIndex8,
+    },
+    SetGlobal {
+        env: UpValueIndex,
+        name: ConstantIndex8,
+        value: RegisterIndex,
+    },
     Call {
         func: RegisterIndex,
         args: VarCount,
@@ -131,13 +147,30 @@ pub enum OpCode {
     },
     // Used to set up for a numeric for loop:
     //
-    // R(base) -= R(base + 2)
-    // pc += jump
+    // if the loop runs at all then
+    //     R(base + 3) = R(base)
+    // else
+    //     pc += jump
+    // end
+    //
+    // If R(base) and R(base + 2) are integers, this is an integer loop, and R(base + 1) is replaced
+    // with the number of iterations remaining after the first.  Otherwise all three control
+    // registers are converted to floats.
     NumericForPrep {
         base: RegisterIndex,
         jump: i16,
     },
-    // Used to iterate a numeric for loop:
+    // Used to iterate a numeric for loop.  For an integer loop:
+    //
+    // if R(base + 1) != 0 then
+    //     R(base + 1) -= 1
+    //     R(base) += R(base + 2)
+    //     pc += jump
+    //     R(base + 3) = R(base)
+    // end
+    //
+    // Counting the remaining iterations means the index never steps past the limit, so it cannot
+    // overflow.  For a float loop:
     //
     // R(base) += R(base + 2)
     // if R(base) <?= R(base + 1) then
@@ -151,6 +184,21 @@ pub enum OpCode {
         base: RegisterIndex,
         jump: i16,
     },
+    // Used instead of `NumericForLoop` when the step is the constant integer 1, which lets integer
+    // loops skip the general add:
+    //
+    // if R(base + 1) != 0 then
+    //     R(base + 1) -= 1
+    //     R(base) += 1
+    //     pc += jump
+    //     R(base + 3) = R(base)
+    // end
+    //
+    // Any other loop runs exactly as with `NumericForLoop`.
+    NumericForIncLoop {
+        base: RegisterIndex,
+        jump: i16,
+    },
     // Used to set up for a generic for loop:
     //
     // R(base + 3), ..., R(base + 2 + var_count) = R(base)(R(base + 1), R(base + 2))
@@ -515,3 +563,394 @@ pub enum OpCode {
         source: RegisterIndex,
     },
 }
+
+impl OpCode {
+    /// Returns a mutable reference to the jump offset of any opcode that performs a relative jump,
+    /// or None if this opcode does not jump.
+    pub fn jump_offset_mut(&mut self) -> Option<&mut i16> {
+        match self {
+            OpCode::Jump { offset, .. } => Some(offset),
+            OpCode::NumericForPrep { jump, .. }
+            | OpCode::NumericForLoop { jump, .. }
+            | OpCode::NumericForIncLoop { jump, .. }
+            | OpCode::GenericForLoop { jump, .. } => Some(jump),
+            _ => None,
+        }
+    }
+
+    /// Returns the constant indexes this opcode reads, in operand order.
+    pub fn constant_operands(&self) -> [Option<ConstantIndex16>; 2] {
+        fn c8(c: ConstantIndex8) -> Option<ConstantIndex16> {
+            Some(ConstantIndex16(c.0 as u16))
+        }
+
+        match *self {
+            OpCode::LoadConstant { constant, .. } => [Some(constant), None],
+            OpCode::GetTableC { key, .. }
+            | OpCode::SetTableCR { key, .. }
+            | OpCode::GetUpTableC { key, .. }
+            | OpCode::SetUpTableCR { key, .. }
+            | OpCode::SelfC { key, .. } => [c8(key), None],
+            OpCode::SetTableRC { value, .. } | OpCode::SetUpTableRC { value, .. } => {
+                [c8(value), None]
+            }
+            OpCode::SetTableCC { key, value, .. } | OpCode::SetUpTableCC { key, value, .. } => {
+                [c8(key), c8(value)]
+            }
+            OpCode::GetGlobal { name, .. } | OpCode::SetGlobal { name, .. } => [c8(name), None],
+            OpCode::EqRC { right, .. }
+            | OpCode::LessRC { right, .. }
+            | OpCode::LessEqRC { right, .. }
+            | OpCode::AddRC { right, .. }
+            | OpCode::SubRC { right, .. }
+            | OpCode::MulRC { right, .. }
+            | OpCode::DivRC { right, .. }
+            | OpCode::IDivRC { right, .. }
+            | OpCode::ModRC { right, .. }
+            | OpCode::PowRC { right, .. }
+            | OpCode::BitAndRC { right, .. }
+            | OpCode::BitOrRC { right, .. }
+            | OpCode::BitXorRC { right, .. }
+            | OpCode::ShiftLeftRC { right, .. }
+            | OpCode::ShiftRightRC { right, .. } => [c8(right), None],
+            OpCode::EqCR { left, .. }
+            | OpCode::LessCR { left, .. }
+            | OpCode::LessEqCR { left, .. }
+            | OpCode::AddCR { left, .. }
+            | OpCode::SubCR { left, .. }
+            | OpCode::MulCR { left, .. }
+            | OpCode::DivCR { left, .. }
+            | OpCode::IDivCR { left, .. }
+            | OpCode::ModCR { left, .. }
+            | OpCode::PowCR { left, .. }
+            | OpCode::BitAndCR { left, .. }
+            | OpCode::BitOrCR { left, .. }
+            | OpCode::BitXorCR { left, .. }
+            | OpCode::ShiftLeftCR { left, .. }
+            | OpCode::ShiftRightCR { left, .. } => [c8(left), None],
+            OpCode::EqCC { left, right, .. }
+            | OpCode::LessCC { left, right, .. }
+            | OpCode::LessEqCC { left, right, .. }
+            | OpCode::AddCC { left, right, .. }
+            | OpCode::SubCC { left, right, .. }
+            | OpCode::MulCC { left, right, .. }
+            | OpCode::DivCC { left, right, .. }
+            | OpCode::IDivCC { left, right, .. }
+            | OpCode::ModCC { left, right, .. }
+            | OpCode::PowCC { left, right, .. }
+            | OpCode::BitAndCC { left, right, .. }
+            | OpCode::BitOrCC { left, right, .. }
+            | OpCode::BitXorCC { left, right, .. }
+            | OpCode::ShiftLeftCC { left, right, .. }
+            | OpCode::ShiftRightCC { left, right, .. } => [c8(left), c8(right)],
+            _ => [None, None],
+        }
+    }
+}
+
+/// A 32 bit encoding of an `OpCode`, with the opcode variant in the low 8 bits and its operands
+/// packed into the bits above.  Every operand of every variant fits, so decoding always gives back
+/// the encoded `OpCode`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Collect)]
+#[collect(require_static)]
+pub struct PackedOpCode(u32);
+
+impl PackedOpCode {
+    pub fn to_u32(self) -> u32 {
+        self.0
+    }
+}
+
+// An opcode operand with a fixed width encoding.
+trait Operand: Sized {
+    const BITS: u32;
+
+    fn pack(self) -> u32;
+    // Decodes the operand from the low `BITS` bits, ignoring any bits above.
+    fn unpack(bits: u32) -> Self;
+}
+
+impl Operand for bool {
+    const BITS: u32 = 1;
+
+    fn pack(self) -> u32 {
+        self as u32
+    }
+
+    fn unpack(bits: u32) -> bool {
+        bits & 1 != 0
+    }
+}
+
+impl Operand for u8 {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self as u32
+    }
+
+    fn unpack(bits: u32) -> u8 {
+        bits as u8
+    }
+}
+
+impl Operand for i16 {
+    const BITS: u32 = 16;
+
+    fn pack(self) -> u32 {
+        self as u16 as u32
+    }
+
+    fn unpack(bits: u32) -> i16 {
+        bits as u16 as i16
+    }
+}
+
+impl Operand for RegisterIndex {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self.0.pack()
+    }
+
+    fn unpack(bits: u32) -> RegisterIndex {
+        RegisterIndex(u8::unpack(bits))
+    }
+}
+
+impl Operand for ConstantIndex8 {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self.0.pack()
+    }
+
+    fn unpack(bits: u32) -> ConstantIndex8 {
+        ConstantIndex8(u8::unpack(bits))
+    }
+}
+
+impl Operand for ConstantIndex16 {
+    const BITS: u32 = 16;
+
+    fn pack(self) -> u32 {
+        self.0 as u32
+    }
+
+    fn unpack(bits: u32) -> ConstantIndex16 {
+        ConstantIndex16(bits as u16)
+    }
+}
+
+impl Operand for UpValueIndex {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self.0.pack()
+    }
+
+    fn unpack(bits: u32) -> UpValueIndex {
+        UpValueIndex(u8::unpack(bits))
+    }
+}
+
+impl Operand for PrototypeIndex {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self.0.pack()
+    }
+
+    fn unpack(bits: u32) -> PrototypeIndex {
+        PrototypeIndex(u8::unpack(bits))
+    }
+}
+
+impl Operand for Opt254 {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self.to_u8().unwrap_or(255).pack()
+    }
+
+    fn unpack(bits: u32) -> Opt254 {
+        match u8::unpack(bits) {
+            255 => Opt254::none(),
+            v => Opt254::some(v),
+        }
+    }
+}
+
+impl Operand for VarCount {
+    const BITS: u32 = 8;
+
+    fn pack(self) -> u32 {
+        self.to_constant().unwrap_or(255).pack()
+    }
+
+    fn unpack(bits: u32) -> VarCount {
+        match u8::unpack(bits) {
+            255 => VarCount::variable(),
+            v => VarCount::constant(v),
+        }
+    }
+}
+
+fn operand_bits<T: Operand>(_: &T) -> u32 {
+    T::BITS
+}
+
+// Decodes the operand in the low bits and shifts it out.
+fn unpack_operand<T: Operand>(bits: &mut u32) -> T {
+    let operand = T::unpack(*bits);
+    *bits >>= T::BITS;
+    operand
+}
+
+// Implements `PackedOpCode::encode` and `PackedOpCode::decode` given every `OpCode` variant with
+// its fields.  The variants are tagged in the order listed, and the operands are packed in the
+// order listed starting from the lowest bits.
+macro_rules! packed_opcodes {
+    ($($variant:ident { $($field:ident),* },)*) => {
+        #[allow(non_upper_case_globals)]
+        mod tag {
+            #[repr(u8)]
+            enum Tag {
+                $($variant,)*
+            }
+
+            $(pub const $variant: u32 = Tag::$variant as u32;)*
+        }
+
+        impl PackedOpCode {
+            pub fn encode(opcode: OpCode) -> PackedOpCode {
+                match opcode {
+                    $(OpCode::$variant { $($field),* } => {
+                        let mut bits = tag::$variant;
+                        let mut shift = 8;
+                        $(
+                            bits |= $field.pack() << shift;
+                            shift += operand_bits(&$field);
+                        )*
+                        debug_assert!(shift <= 32, "opcode operands do not fit in 32 bits");
+                        PackedOpCode(bits)
+                    })*
+                }
+            }
+
+            pub fn decode(self) -> OpCode {
+                let mut bits = self.0 >> 8;
+                match self.0 & 0xff {
+                    $(tag::$variant => OpCode::$variant {
+                        $($field: unpack_operand(&mut bits)),*
+                    },)*
+                    _ => unreachable!("packed opcodes are only built by `PackedOpCode::encode`"),
+                }
+            }
+        }
+    };
+}
+
+packed_opcodes! {
+    Move { dest, source },
+    LoadConstant { dest, constant },
+    LoadBool { dest, value, skip_next },
+    LoadNil { dest, count },
+    NewTable { dest, array_size, map_size },
+    GetTableR { dest, table, key },
+    GetTableC { dest, table, key },
+    SetTableRR { table, key, value },
+    SetTableRC { table, key, value },
+    SetTableCR { table, key, value },
+    SetTableCC { table, key, value },
+    GetUpTableR { dest, table, key },
+    GetUpTableC { dest, table, key },
+    SetUpTableRR { table, key, value },
+    SetUpTableRC { table, key, value },
+    SetUpTableCR { table, key, value },
+    SetUpTableCC { table, key, value },
+    GetGlobal { dest, env, name },
+    SetGlobal { env, name, value },
+    Call { func, args, returns },
+    TailCall { func, args },
+    Return { start, count },
+    VarArgs { dest, count },
+    Jump { offset, close_upvalues },
+    Test { value, is_true },
+    TestSet { dest, value, is_true },
+    Closure { dest, proto },
+    NumericForPrep { base, jump },
+    NumericForLoop { base, jump },
+    NumericForIncLoop { base, jump },
+    GenericForCall { base, var_count },
+    GenericForLoop { base, jump },
+    SelfR { base, table, key },
+    SelfC { base, table, key },
+    Concat { dest, source, count },
+    GetUpValue { dest, source },
+    SetUpValue { dest, source },
+    Length { dest, source },
+    EqRR { skip_if, left, right },
+    EqRC { skip_if, left, right },
+    EqCR { skip_if, left, right },
+    EqCC { skip_if, left, right },
+    LessRR { skip_if, left, right },
+    LessRC { skip_if, left, right },
+    LessCR { skip_if, left, right },
+    LessCC { skip_if, left, right },
+    LessEqRR { skip_if, left, right },
+    LessEqRC { skip_if, left, right },
+    LessEqCR { skip_if, left, right },
+    LessEqCC { skip_if, left, right },
+    Not { dest, source },
+    Minus { dest, source },
+    AddRR { dest, left, right },
+    AddRC { dest, left, right },
+    AddCR { dest, left, right },
+    AddCC { dest, left, right },
+    SubRR { dest, left, right },
+    SubRC { dest, left, right },
+    SubCR { dest, left, right },
+    SubCC { dest, left, right },
+    MulRR { dest, left, right },
+    MulRC { dest, left, right },
+    MulCR { dest, left, right },
+    MulCC { dest, left, right },
+    DivRR { dest, left, right },
+    DivRC { dest, left, right },
+    DivCR { dest, left, right },
+    DivCC { dest, left, right },
+    IDivRR { dest, left, right },
+    IDivRC { dest, left, right },
+    IDivCR { dest, left, right },
+    IDivCC { dest, left, right },
+    ModRR { dest, left, right },
+    ModRC { dest, left, right },
+    ModCR { dest, left, right },
+    ModCC { dest, left, right },
+    PowRR { dest, left, right },
+    PowRC { dest, left, right },
+    PowCR { dest, left, right },
+    PowCC { dest, left, right },
+    BitAndRR { dest, left, right },
+    BitAndRC { dest, left, right },
+    BitAndCR { dest, left, right },
+    BitAndCC { dest, left, right },
+    BitOrRR { dest, left, right },
+    BitOrRC { dest, left, right },
+    BitOrCR { dest, left, right },
+    BitOrCC { dest, left, right },
+    BitXorRR { dest, left, right },
+    BitXorRC { dest, left, right },
+    BitXorCR { dest, left, right },
+    BitXorCC { dest, left, right },
+    ShiftLeftRR { dest, left, right },
+    ShiftLeftRC { dest, left, right },
+    ShiftLeftCR { dest, left, right },
+    ShiftLeftCC { dest, left, right },
+    ShiftRightRR { dest, left, right },
+    ShiftRightRC { dest, left, right },
+    ShiftRightCR { dest, left, right },
+    ShiftRightCC { dest, left, right },
+    BitNot { dest, source },
+}
@@ -7,6 +7,12 @@ use gc_arena::Collect;
 
 use crate::{Lexer, LexerError, Token};
 
+mod desugar;
+pub mod visit;
+
+pub use self::desugar::desugar_chunk;
+pub use self::visit::Visit;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Chunk<S> {
     pub block: Block<S>,
@@ -257,11 +263,8 @@ impl StdError for ParserError {}
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let write_expected = |f: &mut fmt::Formatter, expected: &Option<String>| {
-            match expected {
-                Some(expected) => {
-                    write!(f, ", expected {}", expected)?;
-                }
-                None => {}
+            if let Some(expected) = expected {
+                write!(f, ", expected {}", expected)?;
             }
             Ok(())
         };
@@ -271,7 +274,7 @@ impl fmt::Display for ParserError {
                 unexpected,
                 expected,
             } => {
-                write!(f, "found {:?}", unexpected)?;
+                write!(f, "unexpected {}", unexpected)?;
                 write_expected(f, expected)
             }
             ParserError::EndOfStream { expected } => {
@@ -286,42 +289,110 @@ impl fmt::Display for ParserError {
     }
 }
 
+/// Lex and parse a chunk from the given source.
+///
+/// Errors from the lexer are returned as `ParserError::LexerError`, every other variant comes from
+/// the parser itself.
 pub fn parse_chunk<R, S, CS>(source: R, create_string: CS) -> Result<Chunk<S>, ParserError>
 where
     R: Read,
-    S: fmt::Debug + PartialEq,
+    S: AsRef<[u8]> + fmt::Debug + PartialEq,
     CS: FnMut(&[u8]) -> S,
 {
-    Parser {
-        lexer: Lexer::new(source, create_string),
-        read_buffer: Vec::new(),
-        recursion_guard: Rc::new(()),
-    }
-    .parse_chunk()
+    Parser::new(Lexer::new(source, create_string)).parse_chunk()
 }
 
-struct Parser<R, S, CS> {
-    lexer: Lexer<R, CS>,
-    read_buffer: Vec<Token<S>>,
-    recursion_guard: Rc<()>,
+/// Lex and parse a chunk from the given source, like `parse_chunk`, except that the chunk may end
+/// with a bare expression which is parsed as if it were returned.  A bare expression anywhere else
+/// is still `ParserError::ExpressionNotStatement`.
+pub fn parse_script<R, S, CS>(source: R, create_string: CS) -> Result<Chunk<S>, ParserError>
+where
+    R: Read,
+    S: AsRef<[u8]> + fmt::Debug + PartialEq,
+    CS: FnMut(&[u8]) -> S,
+{
+    let mut parser = Parser::new(Lexer::new(source, create_string));
+    let block = parser.parse_block_with(true)?;
+    parser.expect_end()?;
+    Ok(Chunk { block })
 }
 
-impl<R, S, CS> Parser<R, S, CS>
+/// Lex and parse a single expression from the given source, which must contain nothing else.
+///
+/// Errors from the lexer are returned as `ParserError::LexerError`, every other variant comes from
+/// the parser itself.
+pub fn parse_expression<R, S, CS>(
+    source: R,
+    create_string: CS,
+) -> Result<Expression<S>, ParserError>
 where
     R: Read,
-    S: fmt::Debug + PartialEq,
+    S: AsRef<[u8]> + fmt::Debug + PartialEq,
     CS: FnMut(&[u8]) -> S,
 {
+    let mut parser = Parser::new(Lexer::new(source, create_string));
+    let expression = parser.parse_expression()?;
+    parser.expect_end()?;
+    Ok(expression)
+}
+
+/// Lex and parse a chunk from the given source bytes, keeping every string as a boxed byte slice.
+pub fn parse_bytes(source: &[u8]) -> Result<Chunk<Box<[u8]>>, ParserError> {
+    parse_chunk(source, |s| s.to_vec().into_boxed_slice())
+}
+
+/// Parse a chunk from an already lexed sequence of tokens.
+///
+/// Since no lexing takes place, this never returns `ParserError::LexerError`.
+pub fn parse<S>(tokens: &[Token<S>]) -> Result<Chunk<S>, ParserError>
+where
+    S: AsRef<[u8]> + fmt::Debug + PartialEq + Clone,
+{
+    Parser::new(tokens.iter().cloned().map(Ok)).parse_chunk()
+}
+
+struct Parser<I, S> {
+    tokens: I,
+    read_buffer: Vec<Token<S>>,
+    recursion_guard: Rc<()>,
+}
+
+impl<I, S> Parser<I, S>
+where
+    I: Iterator<Item = Result<Token<S>, LexerError>>,
+    S: AsRef<[u8]> + fmt::Debug + PartialEq,
+{
+    fn new(tokens: I) -> Parser<I, S> {
+        Parser {
+            tokens,
+            read_buffer: Vec::new(),
+            recursion_guard: Rc::new(()),
+        }
+    }
+
     fn parse_chunk(&mut self) -> Result<Chunk<S>, ParserError> {
         let block = self.parse_block()?;
-        if self.look_ahead(0)? != None {
-            Err(ParserError::EndOfStream { expected: None })
+        self.expect_end()?;
+        Ok(Chunk { block })
+    }
+
+    fn expect_end(&mut self) -> Result<(), ParserError> {
+        if let Some(token) = self.look_ahead(0)? {
+            Err(ParserError::Unexpected {
+                unexpected: token.to_string(),
+                expected: Some("end of stream".to_owned()),
+            })
         } else {
-            Ok(Chunk { block })
+            Ok(())
         }
     }
 
     fn parse_block(&mut self) -> Result<Block<S>, ParserError> {
+        self.parse_block_with(false)
+    }
+
+    // With `implicit_return`, a bare expression ending the block becomes its return statement.
+    fn parse_block_with(&mut self, implicit_return: bool) -> Result<Block<S>, ParserError> {
         let mut statements = Vec::new();
         let mut return_statement = None;
 
@@ -337,6 +408,28 @@ where
                     break;
                 }
                 None => break,
+                Some(token) if implicit_return && !starts_statement(token) => {
+                    let expression = self.parse_expression()?;
+                    while self.check_ahead(0, Token::SemiColon)? {
+                        self.take_next()?;
+                    }
+                    if self.look_ahead(0)?.is_none() {
+                        return_statement = Some(ReturnStatement {
+                            returns: vec![expression],
+                        });
+                        break;
+                    }
+
+                    let Expression { head, tail } = expression;
+                    match *head {
+                        HeadExpression::Simple(SimpleExpression::Suffixed(suffixed))
+                            if tail.is_empty() =>
+                        {
+                            statements.push(self.parse_expression_statement_from(suffixed)?);
+                        }
+                        _ => return Err(ParserError::ExpressionNotStatement),
+                    }
+                }
                 _ => {
                     statements.push(self.parse_statement()?);
                 }
@@ -492,7 +585,7 @@ where
             }
 
             token => Err(ParserError::Unexpected {
-                unexpected: format!("{:?}", token),
+                unexpected: token.to_string(),
                 expected: Some("'=' or 'in'".to_owned()),
             }),
         }
@@ -579,7 +672,16 @@ where
     }
 
     fn parse_expression_statement(&mut self) -> Result<Statement<S>, ParserError> {
-        let mut suffixed_expression = self.parse_suffixed_expression()?;
+        let suffixed_expression = self.parse_suffixed_expression()?;
+        self.parse_expression_statement_from(suffixed_expression)
+    }
+
+    // Parses the rest of an assignment or function call statement starting with the given
+    // expression.
+    fn parse_expression_statement_from(
+        &mut self,
+        mut suffixed_expression: SuffixedExpression<S>,
+    ) -> Result<Statement<S>, ParserError> {
         if self.check_ahead(0, Token::Assign)? || self.check_ahead(0, Token::Comma)? {
             let mut targets = Vec::new();
             loop {
@@ -717,7 +819,7 @@ where
             }
             Token::Name(n) => Ok(PrimaryExpression::Name(n)),
             token => Err(ParserError::Unexpected {
-                unexpected: format!("{:?}", token),
+                unexpected: token.to_string(),
                 expected: Some("grouped expression or name".to_owned()),
             }),
         }
@@ -736,7 +838,7 @@ where
                 Ok(FieldSuffix::Indexed(expr))
             }
             token => Err(ParserError::Unexpected {
-                unexpected: format!("{:?}", token),
+                unexpected: token.to_string(),
                 expected: Some("field or suffix".to_owned()),
             }),
         }
@@ -776,7 +878,7 @@ where
             }],
             token => {
                 return Err(ParserError::Unexpected {
-                    unexpected: format!("{:?}", token),
+                    unexpected: token.to_string(),
                     expected: Some("function arguments".to_owned()),
                 });
             }
@@ -796,7 +898,7 @@ where
                 Ok(SuffixPart::Call(self.parse_call_suffix()?))
             }
             token => Err(ParserError::Unexpected {
-                unexpected: format!("{:?}", token),
+                unexpected: token.to_string(),
                 expected: Some("expression suffix".to_owned()),
             }),
         }
@@ -837,7 +939,7 @@ where
                     }
                     token => {
                         return Err(ParserError::Unexpected {
-                            unexpected: format!("{:?}", token),
+                            unexpected: token.to_string(),
                             expected: Some("parameter name or '...'".to_owned()),
                         });
                     }
@@ -929,7 +1031,7 @@ where
         self.read_ahead(1)?;
         if self.read_buffer.is_empty() {
             Err(ParserError::EndOfStream {
-                expected: Some(format!("{:?}", token)),
+                expected: Some(token.to_string()),
             })
         } else {
             let next_token = self.read_buffer.remove(0);
@@ -937,8 +1039,8 @@ where
                 Ok(())
             } else {
                 Err(ParserError::Unexpected {
-                    unexpected: format!("{:?}", next_token),
-                    expected: Some(format!("{:?}", token)),
+                    unexpected: next_token.to_string(),
+                    expected: Some(token.to_string()),
                 })
             }
         }
@@ -955,7 +1057,7 @@ where
             match self.read_buffer.remove(0) {
                 Token::Name(name) => Ok(name),
                 token => Err(ParserError::Unexpected {
-                    unexpected: format!("{:?}", token),
+                    unexpected: token.to_string(),
                     expected: Some("name".to_owned()),
                 }),
             }
@@ -973,7 +1075,7 @@ where
             match self.read_buffer.remove(0) {
                 Token::String(string) => Ok(string),
                 token => Err(ParserError::Unexpected {
-                    unexpected: format!("{:?}", token),
+                    unexpected: token.to_string(),
                     expected: Some("string".to_owned()),
                 }),
             }
@@ -1011,7 +1113,12 @@ where
     // possible).
     fn read_ahead(&mut self, n: usize) -> Result<(), ParserError> {
         while self.read_buffer.len() <= n {
-            if let Some(token) = self.lexer.read_token().map_err(ParserError::LexerError)? {
+            if let Some(token) = self
+                .tokens
+                .next()
+                .transpose()
+                .map_err(ParserError::LexerError)?
+            {
                 self.read_buffer.push(token);
             } else {
                 break;
@@ -1045,7 +1152,7 @@ fn binary_priority(operator: BinaryOperator) -> (u8, u8) {
         BinaryOperator::ShiftLeft => (7, 7),
         BinaryOperator::ShiftRight => (7, 7),
         BinaryOperator::Concat => (9, 8),
-        BinaryOperator::NotEqual => (10, 10),
+        BinaryOperator::NotEqual => (3, 3),
         BinaryOperator::Equal => (3, 3),
         BinaryOperator::LessThan => (3, 3),
         BinaryOperator::LessEqual => (3, 3),
@@ -1056,6 +1163,23 @@ fn binary_priority(operator: BinaryOperator) -> (u8, u8) {
     }
 }
 
+// Whether the token starts a statement which is not an assignment or function call.
+fn starts_statement<S>(token: &Token<S>) -> bool {
+    matches!(
+        token,
+        Token::If
+            | Token::While
+            | Token::Do
+            | Token::For
+            | Token::Repeat
+            | Token::Function
+            | Token::Local
+            | Token::DoubleColon
+            | Token::Break
+            | Token::Goto
+    )
+}
+
 // Get the unary operator associated with the given token, if it exists.
 fn get_unary_operator<S>(token: &Token<S>) -> Option<UnaryOperator> {
     match *token {
@@ -0,0 +1,269 @@
+use super::{
+    AssignmentStatement, AssignmentTarget, Block, CallSuffix, Chunk, ConstructorField, Expression,
+    FieldSuffix, ForStatement, FunctionCallStatement, FunctionDefinition, FunctionStatement,
+    HeadExpression, IfStatement, LocalFunctionStatement, LocalStatement, PrimaryExpression,
+    RecordKey, RepeatStatement, ReturnStatement, SimpleExpression, Statement, SuffixPart,
+    SuffixedExpression, TableConstructor, WhileStatement,
+};
+
+/// Rewrites every function statement in the chunk, including those nested in blocks and function
+/// bodies, into the plain assignment it is sugar for:
+///
+/// - `function a.b.c() end` becomes `a.b.c = function() end`
+/// - `function a.b:m(x) end` becomes `a.b.m = function(self, x) end`
+///
+/// The `self_name` string is used as the name of the implicit first parameter of methods.  Every
+/// other statement and expression is left as it is.
+pub fn desugar_chunk<S: Clone>(chunk: Chunk<S>, self_name: S) -> Chunk<S> {
+    Chunk {
+        block: Desugarer { self_name }.block(chunk.block),
+    }
+}
+
+struct Desugarer<S> {
+    self_name: S,
+}
+
+impl<S: Clone> Desugarer<S> {
+    fn block(&self, block: Block<S>) -> Block<S> {
+        Block {
+            statements: block
+                .statements
+                .into_iter()
+                .map(|s| self.statement(s))
+                .collect(),
+            return_statement: block.return_statement.map(|r| ReturnStatement {
+                returns: self.expressions(r.returns),
+            }),
+        }
+    }
+
+    fn statement(&self, statement: Statement<S>) -> Statement<S> {
+        match statement {
+            Statement::If(if_statement) => Statement::If(IfStatement {
+                if_part: self.conditional(if_statement.if_part),
+                else_if_parts: if_statement
+                    .else_if_parts
+                    .into_iter()
+                    .map(|p| self.conditional(p))
+                    .collect(),
+                else_part: if_statement.else_part.map(|b| self.block(b)),
+            }),
+            Statement::While(while_statement) => Statement::While(WhileStatement {
+                condition: self.expression(while_statement.condition),
+                block: self.block(while_statement.block),
+            }),
+            Statement::Do(block) => Statement::Do(self.block(block)),
+            Statement::For(ForStatement::Numeric {
+                name,
+                initial,
+                limit,
+                step,
+                body,
+            }) => Statement::For(ForStatement::Numeric {
+                name,
+                initial: self.expression(initial),
+                limit: self.expression(limit),
+                step: step.map(|e| self.expression(e)),
+                body: self.block(body),
+            }),
+            Statement::For(ForStatement::Generic {
+                names,
+                arguments,
+                body,
+            }) => Statement::For(ForStatement::Generic {
+                names,
+                arguments: self.expressions(arguments),
+                body: self.block(body),
+            }),
+            Statement::Repeat(repeat_statement) => Statement::Repeat(RepeatStatement {
+                body: self.block(repeat_statement.body),
+                until: self.expression(repeat_statement.until),
+            }),
+            Statement::Function(function_statement) => self.function_statement(function_statement),
+            Statement::LocalFunction(local_function) => {
+                Statement::LocalFunction(LocalFunctionStatement {
+                    name: local_function.name,
+                    definition: self.definition(local_function.definition),
+                })
+            }
+            Statement::LocalStatement(local_statement) => {
+                Statement::LocalStatement(LocalStatement {
+                    names: local_statement.names,
+                    values: self.expressions(local_statement.values),
+                })
+            }
+            Statement::FunctionCall(function_call) => {
+                Statement::FunctionCall(FunctionCallStatement {
+                    head: self.suffixed_expression(function_call.head),
+                    call: self.call_suffix(function_call.call),
+                })
+            }
+            Statement::Assignment(assignment) => Statement::Assignment(AssignmentStatement {
+                targets: assignment
+                    .targets
+                    .into_iter()
+                    .map(|target| match target {
+                        AssignmentTarget::Name(name) => AssignmentTarget::Name(name),
+                        AssignmentTarget::Field(table, field) => AssignmentTarget::Field(
+                            self.suffixed_expression(table),
+                            self.field_suffix(field),
+                        ),
+                    })
+                    .collect(),
+                values: self.expressions(assignment.values),
+            }),
+            statement @ Statement::Label(_)
+            | statement @ Statement::Break
+            | statement @ Statement::Goto(_) => statement,
+        }
+    }
+
+    fn function_statement(&self, function_statement: FunctionStatement<S>) -> Statement<S> {
+        let FunctionStatement {
+            name,
+            mut fields,
+            method,
+            definition,
+        } = function_statement;
+
+        let mut definition = self.definition(definition);
+        if let Some(method) = method {
+            definition.parameters.insert(0, self.self_name.clone());
+            fields.push(method);
+        }
+
+        let target = if let Some(last) = fields.pop() {
+            AssignmentTarget::Field(
+                SuffixedExpression {
+                    primary: PrimaryExpression::Name(name),
+                    suffixes: fields
+                        .into_iter()
+                        .map(|field| SuffixPart::Field(FieldSuffix::Named(field)))
+                        .collect(),
+                },
+                FieldSuffix::Named(last),
+            )
+        } else {
+            AssignmentTarget::Name(name)
+        };
+
+        Statement::Assignment(AssignmentStatement {
+            targets: vec![target],
+            values: vec![Expression {
+                head: Box::new(HeadExpression::Simple(SimpleExpression::Function(
+                    definition,
+                ))),
+                tail: Vec::new(),
+            }],
+        })
+    }
+
+    fn conditional(
+        &self,
+        (condition, block): (Expression<S>, Block<S>),
+    ) -> (Expression<S>, Block<S>) {
+        (self.expression(condition), self.block(block))
+    }
+
+    fn definition(&self, definition: FunctionDefinition<S>) -> FunctionDefinition<S> {
+        FunctionDefinition {
+            parameters: definition.parameters,
+            has_varargs: definition.has_varargs,
+            body: self.block(definition.body),
+        }
+    }
+
+    fn expressions(&self, expressions: Vec<Expression<S>>) -> Vec<Expression<S>> {
+        expressions
+            .into_iter()
+            .map(|e| self.expression(e))
+            .collect()
+    }
+
+    fn expression(&self, expression: Expression<S>) -> Expression<S> {
+        let head = match *expression.head {
+            HeadExpression::Simple(simple) => {
+                HeadExpression::Simple(self.simple_expression(simple))
+            }
+            HeadExpression::UnaryOperator(op, operand) => {
+                HeadExpression::UnaryOperator(op, self.expression(operand))
+            }
+        };
+        Expression {
+            head: Box::new(head),
+            tail: expression
+                .tail
+                .into_iter()
+                .map(|(op, right)| (op, self.expression(right)))
+                .collect(),
+        }
+    }
+
+    fn simple_expression(&self, simple: SimpleExpression<S>) -> SimpleExpression<S> {
+        match simple {
+            SimpleExpression::TableConstructor(constructor) => {
+                SimpleExpression::TableConstructor(TableConstructor {
+                    fields: constructor
+                        .fields
+                        .into_iter()
+                        .map(|field| match field {
+                            ConstructorField::Array(value) => {
+                                ConstructorField::Array(self.expression(value))
+                            }
+                            ConstructorField::Record(key, value) => ConstructorField::Record(
+                                match key {
+                                    RecordKey::Named(name) => RecordKey::Named(name),
+                                    RecordKey::Indexed(key) => {
+                                        RecordKey::Indexed(self.expression(key))
+                                    }
+                                },
+                                self.expression(value),
+                            ),
+                        })
+                        .collect(),
+                })
+            }
+            SimpleExpression::Function(definition) => {
+                SimpleExpression::Function(self.definition(definition))
+            }
+            SimpleExpression::Suffixed(suffixed) => {
+                SimpleExpression::Suffixed(self.suffixed_expression(suffixed))
+            }
+            simple => simple,
+        }
+    }
+
+    fn suffixed_expression(&self, suffixed: SuffixedExpression<S>) -> SuffixedExpression<S> {
+        SuffixedExpression {
+            primary: match suffixed.primary {
+                PrimaryExpression::Name(name) => PrimaryExpression::Name(name),
+                PrimaryExpression::GroupedExpression(expr) => {
+                    PrimaryExpression::GroupedExpression(self.expression(expr))
+                }
+            },
+            suffixes: suffixed
+                .suffixes
+                .into_iter()
+                .map(|suffix| match suffix {
+                    SuffixPart::Field(field) => SuffixPart::Field(self.field_suffix(field)),
+                    SuffixPart::Call(call) => SuffixPart::Call(self.call_suffix(call)),
+                })
+                .collect(),
+        }
+    }
+
+    fn field_suffix(&self, field: FieldSuffix<S>) -> FieldSuffix<S> {
+        match field {
+            FieldSuffix::Named(name) => FieldSuffix::Named(name),
+            FieldSuffix::Indexed(key) => FieldSuffix::Indexed(self.expression(key)),
+        }
+    }
+
+    fn call_suffix(&self, call: CallSuffix<S>) -> CallSuffix<S> {
+        match call {
+            CallSuffix::Method(name, args) => CallSuffix::Method(name, self.expressions(args)),
+            CallSuffix::Function(args) => CallSuffix::Function(self.expressions(args)),
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! A read-only visitor over the parser's AST.
+//!
+//! Every `visit_*` method of `Visit` defaults to calling the matching `walk_*` function, which
+//! visits each child node in source order.  Implementors override only the methods for the nodes
+//! they are interested in, and call the `walk_*` function from an override to keep descending.
+
+use super::{
+    AssignmentStatement, AssignmentTarget, Block, CallSuffix, Chunk, ConstructorField, Expression,
+    FieldSuffix, ForStatement, FunctionCallStatement, FunctionDefinition, FunctionStatement,
+    HeadExpression, IfStatement, LocalFunctionStatement, LocalStatement, PrimaryExpression,
+    RecordKey, RepeatStatement, ReturnStatement, SimpleExpression, Statement, SuffixPart,
+    SuffixedExpression, TableConstructor, WhileStatement,
+};
+
+pub trait Visit<S> {
+    fn visit_chunk(&mut self, chunk: &Chunk<S>) {
+        walk_chunk(self, chunk)
+    }
+
+    fn visit_block(&mut self, block: &Block<S>) {
+        walk_block(self, block)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement<S>) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_return_statement(&mut self, return_statement: &ReturnStatement<S>) {
+        walk_return_statement(self, return_statement)
+    }
+
+    fn visit_if_statement(&mut self, if_statement: &IfStatement<S>) {
+        walk_if_statement(self, if_statement)
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &WhileStatement<S>) {
+        walk_while_statement(self, while_statement)
+    }
+
+    fn visit_for_statement(&mut self, for_statement: &ForStatement<S>) {
+        walk_for_statement(self, for_statement)
+    }
+
+    fn visit_repeat_statement(&mut self, repeat_statement: &RepeatStatement<S>) {
+        walk_repeat_statement(self, repeat_statement)
+    }
+
+    fn visit_function_statement(&mut self, function_statement: &FunctionStatement<S>) {
+        walk_function_statement(self, function_statement)
+    }
+
+    fn visit_local_function_statement(
+        &mut self,
+        local_function_statement: &LocalFunctionStatement<S>,
+    ) {
+        walk_local_function_statement(self, local_function_statement)
+    }
+
+    fn visit_local_statement(&mut self, local_statement: &LocalStatement<S>) {
+        walk_local_statement(self, local_statement)
+    }
+
+    fn visit_label(&mut self, _name: &S) {}
+
+    fn visit_break(&mut self) {}
+
+    fn visit_goto(&mut self, _name: &S) {}
+
+    fn visit_function_call_statement(&mut self, function_call: &FunctionCallStatement<S>) {
+        walk_function_call_statement(self, function_call)
+    }
+
+    fn visit_assignment_statement(&mut self, assignment_statement: &AssignmentStatement<S>) {
+        walk_assignment_statement(self, assignment_statement)
+    }
+
+    fn visit_assignment_target(&mut self, target: &AssignmentTarget<S>) {
+        walk_assignment_target(self, target)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression<S>) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_simple_expression(&mut self, simple_expression: &SimpleExpression<S>) {
+        walk_simple_expression(self, simple_expression)
+    }
+
+    fn visit_table_constructor(&mut self, table_constructor: &TableConstructor<S>) {
+        walk_table_constructor(self, table_constructor)
+    }
+
+    fn visit_function_definition(&mut self, function_definition: &FunctionDefinition<S>) {
+        walk_function_definition(self, function_definition)
+    }
+
+    fn visit_suffixed_expression(&mut self, suffixed_expression: &SuffixedExpression<S>) {
+        walk_suffixed_expression(self, suffixed_expression)
+    }
+
+    /// Called for every variable reference, including assignment targets.
+    fn visit_name(&mut self, _name: &S) {}
+
+    fn visit_field_suffix(&mut self, field_suffix: &FieldSuffix<S>) {
+        walk_field_suffix(self, field_suffix)
+    }
+
+    /// Called for every function or method call, whether it appears as a statement or inside an
+    /// expression.
+    fn visit_function_call(&mut self, call: &CallSuffix<S>) {
+        walk_function_call(self, call)
+    }
+}
+
+pub fn walk_chunk<S, V: Visit<S> + ?Sized>(visitor: &mut V, chunk: &Chunk<S>) {
+    visitor.visit_block(&chunk.block);
+}
+
+pub fn walk_block<S, V: Visit<S> + ?Sized>(visitor: &mut V, block: &Block<S>) {
+    for statement in &block.statements {
+        visitor.visit_statement(statement);
+    }
+    if let Some(return_statement) = &block.return_statement {
+        visitor.visit_return_statement(return_statement);
+    }
+}
+
+pub fn walk_statement<S, V: Visit<S> + ?Sized>(visitor: &mut V, statement: &Statement<S>) {
+    match statement {
+        Statement::If(if_statement) => visitor.visit_if_statement(if_statement),
+        Statement::While(while_statement) => visitor.visit_while_statement(while_statement),
+        Statement::Do(block) => visitor.visit_block(block),
+        Statement::For(for_statement) => visitor.visit_for_statement(for_statement),
+        Statement::Repeat(repeat_statement) => visitor.visit_repeat_statement(repeat_statement),
+        Statement::Function(function_statement) => {
+            visitor.visit_function_statement(function_statement)
+        }
+        Statement::LocalFunction(local_function_statement) => {
+            visitor.visit_local_function_statement(local_function_statement)
+        }
+        Statement::LocalStatement(local_statement) => {
+            visitor.visit_local_statement(local_statement)
+        }
+        Statement::Label(label_statement) => visitor.visit_label(&label_statement.name),
+        Statement::Break => visitor.visit_break(),
+        Statement::Goto(goto_statement) => visitor.visit_goto(&goto_statement.name),
+        Statement::FunctionCall(function_call) => {
+            visitor.visit_function_call_statement(function_call)
+        }
+        Statement::Assignment(assignment_statement) => {
+            visitor.visit_assignment_statement(assignment_statement)
+        }
+    }
+}
+
+pub fn walk_return_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    return_statement: &ReturnStatement<S>,
+) {
+    for expression in &return_statement.returns {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_if_statement<S, V: Visit<S> + ?Sized>(visitor: &mut V, if_statement: &IfStatement<S>) {
+    let (condition, block) = &if_statement.if_part;
+    visitor.visit_expression(condition);
+    visitor.visit_block(block);
+    for (condition, block) in &if_statement.else_if_parts {
+        visitor.visit_expression(condition);
+        visitor.visit_block(block);
+    }
+    if let Some(block) = &if_statement.else_part {
+        visitor.visit_block(block);
+    }
+}
+
+pub fn walk_while_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    while_statement: &WhileStatement<S>,
+) {
+    visitor.visit_expression(&while_statement.condition);
+    visitor.visit_block(&while_statement.block);
+}
+
+pub fn walk_for_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    for_statement: &ForStatement<S>,
+) {
+    match for_statement {
+        ForStatement::Numeric {
+            initial,
+            limit,
+            step,
+            body,
+            ..
+        } => {
+            visitor.visit_expression(initial);
+            visitor.visit_expression(limit);
+            if let Some(step) = step {
+                visitor.visit_expression(step);
+            }
+            visitor.visit_block(body);
+        }
+        ForStatement::Generic {
+            arguments, body, ..
+        } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+            visitor.visit_block(body);
+        }
+    }
+}
+
+pub fn walk_repeat_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    repeat_statement: &RepeatStatement<S>,
+) {
+    visitor.visit_block(&repeat_statement.body);
+    visitor.visit_expression(&repeat_statement.until);
+}
+
+pub fn walk_function_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    function_statement: &FunctionStatement<S>,
+) {
+    // The name is assigned to in `function f() end`, and read in `function a.b() end`.
+    visitor.visit_name(&function_statement.name);
+    visitor.visit_function_definition(&function_statement.definition);
+}
+
+pub fn walk_local_function_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    local_function_statement: &LocalFunctionStatement<S>,
+) {
+    visitor.visit_function_definition(&local_function_statement.definition);
+}
+
+pub fn walk_local_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    local_statement: &LocalStatement<S>,
+) {
+    for value in &local_statement.values {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_function_call_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    function_call: &FunctionCallStatement<S>,
+) {
+    visitor.visit_suffixed_expression(&function_call.head);
+    visitor.visit_function_call(&function_call.call);
+}
+
+pub fn walk_assignment_statement<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    assignment_statement: &AssignmentStatement<S>,
+) {
+    for target in &assignment_statement.targets {
+        visitor.visit_assignment_target(target);
+    }
+    for value in &assignment_statement.values {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_assignment_target<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    target: &AssignmentTarget<S>,
+) {
+    match target {
+        AssignmentTarget::Name(name) => visitor.visit_name(name),
+        AssignmentTarget::Field(table, field) => {
+            visitor.visit_suffixed_expression(table);
+            visitor.visit_field_suffix(field);
+        }
+    }
+}
+
+pub fn walk_expression<S, V: Visit<S> + ?Sized>(visitor: &mut V, expression: &Expression<S>) {
+    match &*expression.head {
+        HeadExpression::Simple(simple_expression) => {
+            visitor.visit_simple_expression(simple_expression)
+        }
+        HeadExpression::UnaryOperator(_, operand) => visitor.visit_expression(operand),
+    }
+    for (_, right) in &expression.tail {
+        visitor.visit_expression(right);
+    }
+}
+
+pub fn walk_simple_expression<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    simple_expression: &SimpleExpression<S>,
+) {
+    match simple_expression {
+        SimpleExpression::TableConstructor(table_constructor) => {
+            visitor.visit_table_constructor(table_constructor)
+        }
+        SimpleExpression::Function(function_definition) => {
+            visitor.visit_function_definition(function_definition)
+        }
+        SimpleExpression::Suffixed(suffixed_expression) => {
+            visitor.visit_suffixed_expression(suffixed_expression)
+        }
+        SimpleExpression::Float(_)
+        | SimpleExpression::Integer(_)
+        | SimpleExpression::String(_)
+        | SimpleExpression::Nil
+        | SimpleExpression::True
+        | SimpleExpression::False
+        | SimpleExpression::VarArgs => {}
+    }
+}
+
+pub fn walk_table_constructor<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    table_constructor: &TableConstructor<S>,
+) {
+    for field in &table_constructor.fields {
+        match field {
+            ConstructorField::Array(value) => visitor.visit_expression(value),
+            ConstructorField::Record(key, value) => {
+                if let RecordKey::Indexed(key) = key {
+                    visitor.visit_expression(key);
+                }
+                visitor.visit_expression(value);
+            }
+        }
+    }
+}
+
+pub fn walk_function_definition<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    function_definition: &FunctionDefinition<S>,
+) {
+    visitor.visit_block(&function_definition.body);
+}
+
+pub fn walk_suffixed_expression<S, V: Visit<S> + ?Sized>(
+    visitor: &mut V,
+    suffixed_expression: &SuffixedExpression<S>,
+) {
+    match &suffixed_expression.primary {
+        PrimaryExpression::Name(name) => visitor.visit_name(name),
+        PrimaryExpression::GroupedExpression(expression) => visitor.visit_expression(expression),
+    }
+    for suffix in &suffixed_expression.suffixes {
+        match suffix {
+            SuffixPart::Field(field) => visitor.visit_field_suffix(field),
+            SuffixPart::Call(call) => visitor.visit_function_call(call),
+        }
+    }
+}
+
+pub fn walk_field_suffix<S, V: Visit<S> + ?Sized>(visitor: &mut V, field_suffix: &FieldSuffix<S>) {
+    if let FieldSuffix::Indexed(key) = field_suffix {
+        visitor.visit_expression(key);
+    }
+}
+
+pub fn walk_function_call<S, V: Visit<S> + ?Sized>(visitor: &mut V, call: &CallSuffix<S>) {
+    let arguments = match call {
+        CallSuffix::Method(_, arguments) | CallSuffix::Function(arguments) => arguments,
+    };
+    for argument in arguments {
+        visitor.visit_expression(argument);
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
 };
 
 pub fn load_base<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: Table<'gc>) {
-    env.set(
+    env.raw_set(
         mc,
         String::new_static(b"print"),
         Callback::new_immediate(mc, |args| {
@@ -26,7 +26,7 @@ pub fn load_base<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: Table<
     )
     .unwrap();
 
-    env.set(
+    env.raw_set(
         mc,
         String::new_static(b"error"),
         Callback::new_immediate(mc, |args| {
@@ -36,7 +36,7 @@ pub fn load_base<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: Table<
     )
     .unwrap();
 
-    env.set(
+    env.raw_set(
         mc,
         String::new_static(b"assert"),
         Callback::new_immediate(mc, |args| {
@@ -55,7 +55,7 @@ pub fn load_base<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: Table<
     )
     .unwrap();
 
-    env.set(
+    env.raw_set(
         mc,
         String::new_static(b"pcall"),
         Callback::new_immediate_with(mc, root.interned_strings, |interned_strings, mut args| {
@@ -99,7 +99,7 @@ pub fn load_base<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: Table<
     )
     .unwrap();
 
-    env.set(
+    env.raw_set(
         mc,
         String::new_static(b"type"),
         Callback::new_immediate(mc, |args| {
@@ -116,7 +116,7 @@ pub fn load_base<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: Table<
     )
     .unwrap();
 
-    env.set(
+    env.raw_set(
         mc,
         String::new_static(b"select"),
         Callback::new_immediate(mc, |args| {
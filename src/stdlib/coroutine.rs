@@ -10,7 +10,7 @@ pub fn load_coroutine<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: T
     let coroutine = Table::new(mc);
 
     coroutine
-        .set(
+        .raw_set(
             mc,
             String::new_static(b"create"),
             Callback::new_sequence(mc, |args| {
@@ -35,7 +35,7 @@ pub fn load_coroutine<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: T
         .unwrap();
 
     coroutine
-        .set(
+        .raw_set(
             mc,
             String::new_static(b"resume"),
             Callback::new_sequence_with(mc, root.interned_strings, |interned_strings, mut args| {
@@ -83,7 +83,7 @@ pub fn load_coroutine<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: T
         .unwrap();
 
     coroutine
-        .set(
+        .raw_set(
             mc,
             String::new_static(b"status"),
             Callback::new_immediate(mc, |args| {
@@ -112,13 +112,13 @@ pub fn load_coroutine<'gc>(mc: MutationContext<'gc, '_>, root: Root<'gc>, env: T
         .unwrap();
 
     coroutine
-        .set(
+        .raw_set(
             mc,
             String::new_static(b"yield"),
             Callback::new_immediate(mc, |args| Ok(CallbackResult::Yield(args))),
         )
         .unwrap();
 
-    env.set(mc, String::new_static(b"coroutine"), coroutine)
+    env.raw_set(mc, String::new_static(b"coroutine"), coroutine)
         .unwrap();
 }
@@ -11,7 +11,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     let seeded_rng: Rc<RefCell<Xoshiro256StarStar>> =
         Rc::new(RefCell::new(Xoshiro256StarStar::from_entropy()));
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"abs"),
         Callback::new_immediate(mc, |args| {
@@ -29,7 +29,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"acos"),
         Callback::new_immediate(mc, |args| {
@@ -43,7 +43,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"asin"),
         Callback::new_immediate(mc, |args| {
@@ -57,7 +57,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"atan"),
         Callback::new_immediate(mc, |args| {
@@ -71,7 +71,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"atan2"),
         Callback::new_immediate(mc, |args| {
@@ -89,7 +89,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"ceil"),
         Callback::new_immediate(mc, |args| {
@@ -105,7 +105,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"cos"),
         Callback::new_immediate(mc, |args| {
@@ -119,7 +119,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"cosh"),
         Callback::new_immediate(mc, |args| {
@@ -133,7 +133,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"deg"),
         Callback::new_immediate(mc, |args| {
@@ -147,7 +147,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"exp"),
         Callback::new_immediate(mc, |args| {
@@ -163,7 +163,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"floor"),
         Callback::new_immediate(mc, |args| {
@@ -180,7 +180,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"fmod"),
         Callback::new_immediate(mc, |args| {
@@ -204,7 +204,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"frexp"),
         Callback::new_immediate(mc, |args| {
@@ -236,14 +236,14 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"huge"),
         Value::Number(std::f64::INFINITY),
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"ldexp"),
         Callback::new_immediate(mc, |args| {
@@ -263,7 +263,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"log"),
         Callback::new_immediate(mc, |args| {
@@ -277,7 +277,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"log10"),
         Callback::new_immediate(mc, |args| {
@@ -292,7 +292,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"max"),
         Callback::new_immediate(mc, |args| {
@@ -317,14 +317,14 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"maxinteger"),
         Value::Integer(std::i64::MAX),
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"min"),
         Callback::new_immediate(mc, |args| {
@@ -350,14 +350,14 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"mininteger"),
         Value::Integer(std::i64::MIN),
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"modf"),
         Callback::new_immediate(mc, |args| {
@@ -374,14 +374,14 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"pi"),
         Value::Number(std::f64::consts::PI),
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"rad"),
         Callback::new_immediate(mc, |args| {
@@ -397,7 +397,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
 
     // TODO: Random and Randomseed
     let random_rng = seeded_rng.clone();
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"random"),
         Callback::new_immediate(mc, move |args| {
@@ -431,7 +431,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     .unwrap();
 
     let randomseed_rng = seeded_rng.clone();
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"randomseed"),
         Callback::new_immediate(mc, move |args| {
@@ -450,7 +450,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"sin"),
         Callback::new_immediate(mc, |args| {
@@ -464,7 +464,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"sqrt"),
         Callback::new_immediate(mc, |args| {
@@ -478,7 +478,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"tan"),
         Callback::new_immediate(mc, |args| {
@@ -492,7 +492,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"tointeger"),
         Callback::new_immediate(mc, |args| {
@@ -504,7 +504,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"type"),
         Callback::new_immediate(mc, |args| {
@@ -521,7 +521,7 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    math.set(
+    math.raw_set(
         mc,
         String::new_static(b"ult"),
         Callback::new_immediate(mc, |args| {
@@ -540,5 +540,5 @@ pub fn load_math<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'gc
     )
     .unwrap();
 
-    env.set(mc, String::new_static(b"math"), math).unwrap();
+    env.raw_set(mc, String::new_static(b"math"), math).unwrap();
 }
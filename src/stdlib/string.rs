@@ -7,13 +7,13 @@ pub fn load_string<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'
     let string = Table::new(mc);
 
     string
-        .set(
+        .raw_set(
             mc,
             String::new_static(b"len"),
             Callback::new_sequence(mc, |args| {
                 Ok(sequence::from_fn_with(args, |mc, args| {
                     match args.get(0).cloned().unwrap_or(Value::Nil).to_string(mc) {
-                        Some(s) => Ok(CallbackResult::Return(vec![Value::Integer(s.len())])),
+                        Some(s) => Ok(CallbackResult::Return(vec![Value::Integer(s.len() as i64)])),
                         None => Err(RuntimeError(Value::String(String::new_static(
                             b"Bad argument to len",
                         )))
@@ -24,5 +24,6 @@ pub fn load_string<'gc>(mc: MutationContext<'gc, '_>, _: Root<'gc>, env: Table<'
         )
         .unwrap();
 
-    env.set(mc, String::new_static(b"string"), string).unwrap();
+    env.raw_set(mc, String::new_static(b"string"), string)
+        .unwrap();
 }
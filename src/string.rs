@@ -2,9 +2,10 @@ use std::borrow::Borrow;
 use std::error::Error as StdError;
 use std::fmt::{self, Debug};
 use std::hash::{Hash, Hasher};
-use std::io::Write;
+use std::io::{self, Write};
 use std::ops::Deref;
 use std::str;
+use std::string::String as StdString;
 
 use rustc_hash::FxHashSet;
 
@@ -39,22 +40,26 @@ pub enum String<'gc> {
     Static(&'static [u8]),
 }
 
+// Shows the contents as a quoted string, with printable ASCII written as is and every other byte
+// escaped, so that embedded NULs and non-UTF-8 bytes stay legible.
 impl<'gc> Debug for String<'gc> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            String::Short8(_, _) => fmt.write_str("Short8")?,
-            String::Short32(_, _) => fmt.write_str("Short32")?,
-            String::Long(_) => fmt.write_str("Long")?,
-            String::Static(_) => fmt.write_str("Static")?,
-        }
-        fmt.write_str("(")?;
-        if let Ok(s) = str::from_utf8(self.as_bytes()) {
-            Debug::fmt(s, fmt)?;
-        } else {
-            Debug::fmt(self.as_bytes(), fmt)?;
+        fmt.write_str("\"")?;
+        for &b in self.as_bytes() {
+            match b {
+                b'"' | b'\\' => write!(fmt, "\\{}", b as char)?,
+                0x20..=0x7e => write!(fmt, "{}", b as char)?,
+                _ => write!(fmt, "\\x{:02x}", b)?,
+            }
         }
-        fmt.write_str(")")?;
-        Ok(())
+        fmt.write_str("\"")
+    }
+}
+
+// Writes the contents as text, replacing any invalid UTF-8 with the replacement character.
+impl<'gc> fmt::Display for String<'gc> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&StdString::from_utf8_lossy(self.as_bytes()), fmt)
     }
 }
 
@@ -74,22 +79,70 @@ impl<'gc> String<'gc> {
         }
     }
 
+    // Like `String::new`, but long strings take ownership of the buffer rather than copying it.
+    fn from_vec(mc: MutationContext<'gc, '_>, bytes: Vec<u8>) -> String<'gc> {
+        if bytes.len() <= 32 {
+            String::new(mc, &bytes)
+        } else {
+            String::Long(Gc::allocate(mc, bytes.into_boxed_slice()))
+        }
+    }
+
     pub fn new_static(s: &'static [u8]) -> String<'gc> {
         String::Static(s)
     }
 
-    pub fn concat(
+    /// Formats an integer the way Lua converts integers to strings.
+    pub fn from_i64(mc: MutationContext<'gc, '_>, i: i64) -> String<'gc> {
+        let mut buf = [0; 20];
+        let mut w = &mut buf[..];
+        write!(w, "{}", i).unwrap();
+        let len = 20 - w.len();
+        String::new(mc, &buf[..len])
+    }
+
+    /// Formats a float the way Lua converts floats to strings, which is the C format `%.14g` with
+    /// a trailing `.0` added to anything that would otherwise look like an integer.
+    pub fn from_f64(mc: MutationContext<'gc, '_>, n: f64) -> String<'gc> {
+        let mut bytes = Vec::new();
+        write_number(&mut bytes, n).unwrap();
+        String::new(mc, &bytes)
+    }
+
+    /// Concatenates two strings.
+    pub fn concat(mc: MutationContext<'gc, '_>, a: String<'gc>, b: String<'gc>) -> String<'gc> {
+        let mut bytes = Vec::with_capacity(a.as_bytes().len() + b.as_bytes().len());
+        bytes.extend(a.as_bytes());
+        bytes.extend(b.as_bytes());
+        String::from_vec(mc, bytes)
+    }
+
+    /// Concatenates every value in a single pass, converting numbers to strings the way Lua does.
+    /// Any value other than a string or number is a `StringError::Concat`.
+    pub fn concat_all(
         mc: MutationContext<'gc, '_>,
         values: &[Value<'gc>],
     ) -> Result<String<'gc>, StringError> {
-        let mut bytes = Vec::new();
+        let mut bytes = Vec::with_capacity(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::String(s) => s.as_bytes().len(),
+                    _ => 0,
+                })
+                .sum(),
+        );
         for value in values {
             match value {
-                Value::Nil => write!(&mut bytes, "nil").unwrap(),
-                Value::Boolean(b) => write!(&mut bytes, "{}", b).unwrap(),
                 Value::Integer(i) => write!(&mut bytes, "{}", i).unwrap(),
-                Value::Number(n) => write!(&mut bytes, "{}", n).unwrap(),
+                Value::Number(n) => write_number(&mut bytes, *n).unwrap(),
                 Value::String(s) => bytes.extend(s.as_bytes()),
+                Value::Nil => return Err(StringError::Concat { bad_type: "nil" }),
+                Value::Boolean(_) => {
+                    return Err(StringError::Concat {
+                        bad_type: "boolean",
+                    })
+                }
                 Value::Table(_) => return Err(StringError::Concat { bad_type: "table" }),
                 Value::Function(_) => {
                     return Err(StringError::Concat {
@@ -101,7 +154,7 @@ impl<'gc> String<'gc> {
                 }
             }
         }
-        Ok(String::new(mc, &bytes))
+        Ok(String::from_vec(mc, bytes))
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -113,20 +166,93 @@ impl<'gc> String<'gc> {
         }
     }
 
-    pub fn len(&self) -> i64 {
-        fn as_i64(len: usize) -> i64 {
-            if len <= std::i64::MAX as usize {
-                len as i64
-            } else {
-                panic!("string is too long")
-            }
+    pub fn len(&self) -> usize {
+        match self {
+            String::Short8(l, _) | String::Short32(l, _) => *l as usize,
+            String::Long(b) => b.len(),
+            String::Static(b) => b.len(),
         }
+    }
 
-        match self {
-            String::Short8(l, _) | String::Short32(l, _) => *l as i64,
-            String::Long(b) => as_i64(b.len()),
-            String::Static(b) => as_i64(b.len()),
+    /// Returns the byte at the given 1-based index, negative indexes count backwards from the end
+    /// of the string as in `string.byte`.
+    pub fn byte_at(&self, i: i64) -> Option<u8> {
+        let len = self.len() as i64;
+        let i = relative_position(i, len);
+        if i >= 1 && i <= len {
+            Some(self.as_bytes()[i as usize - 1])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the substring from `start` to `end` inclusive, using the same 1-based indexing and
+    /// clamping rules as `string.sub`.
+    pub fn slice(&self, mc: MutationContext<'gc, '_>, start: i64, end: i64) -> String<'gc> {
+        // A string can be no longer than `isize::MAX` bytes, so its length always fits in an `i64`.
+        let len = self.len() as i64;
+        let start = relative_position(start, len).max(1);
+        let end = relative_position(end, len).min(len);
+        if start == 1 && end == len {
+            *self
+        } else if start <= end {
+            String::new(mc, &self.as_bytes()[start as usize - 1..end as usize])
+        } else {
+            String::new_static(b"")
+        }
+    }
+}
+
+// Converts a possibly negative Lua string position into a position counting from the start, where
+// positions before the start of the string become 0.
+fn relative_position(pos: i64, len: i64) -> i64 {
+    if pos >= 0 {
+        pos
+    } else if pos < -len {
+        0
+    } else {
+        len + pos + 1
+    }
+}
+
+// Writes a float using the Lua number to string conversion rules.
+pub(crate) fn write_number<W: Write>(mut w: W, n: f64) -> Result<(), io::Error> {
+    if n.is_nan() {
+        return w.write_all(if n.is_sign_negative() {
+            b"-nan"
+        } else {
+            b"nan"
+        });
+    } else if n.is_infinite() {
+        return w.write_all(if n < 0.0 { b"-inf" } else { b"inf" });
+    }
+
+    // Equivalent to "%.14g", rounding to 14 significant digits first to find the exponent.
+    const PRECISION: i32 = 14;
+    let sci = format!("{:.*e}", PRECISION as usize - 1, n);
+    let exp_pos = sci.find('e').unwrap();
+    let exp: i32 = sci[exp_pos + 1..].parse().unwrap();
+
+    fn trim_fraction(s: &str) -> &str {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            s
+        }
+    }
+
+    if !(-4..PRECISION).contains(&exp) {
+        let mantissa = trim_fraction(&sci[..exp_pos]);
+        let sign = if exp < 0 { '-' } else { '+' };
+        write!(w, "{}e{}{:02}", mantissa, sign, exp.abs())
+    } else {
+        let fixed = format!("{:.*}", (PRECISION - 1 - exp) as usize, n);
+        let fixed = trim_fraction(&fixed);
+        w.write_all(fixed.as_bytes())?;
+        if fixed.bytes().all(|b| b == b'-' || b.is_ascii_digit()) {
+            w.write_all(b".0")?;
         }
+        Ok(())
     }
 }
 
@@ -7,7 +7,7 @@ use rustc_hash::FxHashMap;
 
 use gc_arena::{Collect, GcCell, MutationContext};
 
-use crate::Value;
+use crate::{Function, String, Value};
 
 #[derive(Debug, Copy, Clone, Collect)]
 #[collect(require_copy)]
@@ -31,6 +31,66 @@ impl fmt::Display for InvalidTableKey {
     }
 }
 
+#[derive(Debug, Clone, Copy, Collect)]
+#[collect(require_static)]
+pub enum MetaError {
+    InvalidKey(InvalidTableKey),
+    /// An `__index` or `__newindex` metamethod that is neither a table nor a function, or a `__len`
+    /// metamethod that is not a function, along with its type name.
+    BadMetamethod(&'static str),
+    /// Too many tables were traversed through `__index` or `__newindex`, which is most likely a
+    /// loop.
+    ChainTooLong,
+}
+
+impl StdError for MetaError {}
+
+impl fmt::Display for MetaError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetaError::InvalidKey(error) => write!(fmt, "{}", error),
+            MetaError::BadMetamethod(type_name) => {
+                write!(fmt, "cannot use a {} value as a metamethod", type_name)
+            }
+            MetaError::ChainTooLong => write!(fmt, "metamethod chain too long, possible loop"),
+        }
+    }
+}
+
+impl From<InvalidTableKey> for MetaError {
+    fn from(error: InvalidTableKey) -> MetaError {
+        MetaError::InvalidKey(error)
+    }
+}
+
+/// A position given to `Table::insert` or `Table::remove` outside of the table's sequence.
+#[derive(Debug, Clone, Copy, Collect)]
+#[collect(require_static)]
+pub struct PositionOutOfBounds(pub i64);
+
+impl StdError for PositionOutOfBounds {}
+
+impl fmt::Display for PositionOutOfBounds {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "position {} out of bounds", self.0)
+    }
+}
+
+/// The result of a metatable aware table access.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MetaResult<'gc> {
+    /// The access is complete.  For `Table::get` this holds the value found, and for `Table::set`
+    /// the previous value of the key that was set.
+    Value(Value<'gc>),
+    /// The access reached a function metamethod, which the caller must call with the given table
+    /// and the key (and for `Table::set`, the value).  For `Table::length` the metamethod is called
+    /// with just the table.
+    Call(Function<'gc>, Table<'gc>),
+}
+
+// The maximum number of tables visited by a single `Table::get` or `Table::set`.
+const MAX_META_CHAIN: usize = 100;
+
 impl<'gc> PartialEq for Table<'gc> {
     fn eq(&self, other: &Table<'gc>) -> bool {
         GcCell::ptr_eq(self.0, other.0)
@@ -50,11 +110,29 @@ impl<'gc> Table<'gc> {
         Table(GcCell::allocate(mc, TableState::default()))
     }
 
-    pub fn get<K: Into<Value<'gc>>>(&self, key: K) -> Value<'gc> {
+    /// Creates a table with room for the given number of array and map entries.
+    pub fn with_capacity(
+        mc: MutationContext<'gc, '_>,
+        array_size: usize,
+        map_size: usize,
+    ) -> Table<'gc> {
+        Table(GcCell::allocate(
+            mc,
+            TableState {
+                array: Vec::with_capacity(array_size),
+                map: FxHashMap::with_capacity_and_hasher(map_size, Default::default()),
+                metatable: None,
+            },
+        ))
+    }
+
+    /// Gets the value of the given key, ignoring any metatable.
+    pub fn raw_get<K: Into<Value<'gc>>>(&self, key: K) -> Value<'gc> {
         self.0.read().get(key.into())
     }
 
-    pub fn set<K: Into<Value<'gc>>, V: Into<Value<'gc>>>(
+    /// Sets the value of the given key, ignoring any metatable, and returns the previous value.
+    pub fn raw_set<K: Into<Value<'gc>>, V: Into<Value<'gc>>>(
         &self,
         mc: MutationContext<'gc, '_>,
         key: K,
@@ -63,9 +141,122 @@ impl<'gc> Table<'gc> {
         self.0.write(mc).set(key.into(), value.into())
     }
 
-    pub fn length(&self) -> i64 {
+    pub fn metatable(&self) -> Option<Table<'gc>> {
+        self.0.read().metatable
+    }
+
+    /// Sets the metatable, returning the previous one.
+    pub fn set_metatable(
+        &self,
+        mc: MutationContext<'gc, '_>,
+        metatable: Option<Table<'gc>>,
+    ) -> Option<Table<'gc>> {
+        mem::replace(&mut self.0.write(mc).metatable, metatable)
+    }
+
+    /// Gets the value of the given key, following the `__index` metamethod of each table's
+    /// metatable while the key is absent.
+    pub fn get<K: Into<Value<'gc>>>(&self, key: K) -> Result<MetaResult<'gc>, MetaError> {
+        let key = key.into();
+        let mut table = *self;
+        for _ in 0..MAX_META_CHAIN {
+            let value = table.raw_get(key);
+            if !matches!(value, Value::Nil) {
+                return Ok(MetaResult::Value(value));
+            }
+            match table.metamethod(b"__index") {
+                Value::Nil => return Ok(MetaResult::Value(Value::Nil)),
+                Value::Table(next) => table = next,
+                Value::Function(function) => return Ok(MetaResult::Call(function, table)),
+                other => return Err(MetaError::BadMetamethod(other.type_name())),
+            }
+        }
+        Err(MetaError::ChainTooLong)
+    }
+
+    /// Sets the value of the given key.  If the key is absent, the `__newindex` metamethod of
+    /// each table's metatable is followed, and the key is set in the first table without one.
+    pub fn set<K: Into<Value<'gc>>, V: Into<Value<'gc>>>(
+        &self,
+        mc: MutationContext<'gc, '_>,
+        key: K,
+        value: V,
+    ) -> Result<MetaResult<'gc>, MetaError> {
+        let (key, value) = (key.into(), value.into());
+        let mut table = *self;
+        for _ in 0..MAX_META_CHAIN {
+            if let Value::Nil = table.raw_get(key) {
+                match table.metamethod(b"__newindex") {
+                    Value::Nil => {}
+                    Value::Table(next) => {
+                        table = next;
+                        continue;
+                    }
+                    Value::Function(function) => return Ok(MetaResult::Call(function, table)),
+                    other => return Err(MetaError::BadMetamethod(other.type_name())),
+                }
+            }
+            return Ok(MetaResult::Value(table.raw_set(mc, key, value)?));
+        }
+        Err(MetaError::ChainTooLong)
+    }
+
+    /// The length of the table ignoring any metatable, see `TableState::length`.
+    pub fn raw_length(&self) -> i64 {
         self.0.read().length()
     }
+
+    /// The length of the table, given by the `__len` metamethod if the table has one and by
+    /// `raw_length` otherwise.
+    pub fn length(&self) -> Result<MetaResult<'gc>, MetaError> {
+        match self.metamethod(b"__len") {
+            Value::Nil => Ok(MetaResult::Value(Value::Integer(self.raw_length()))),
+            Value::Function(function) => Ok(MetaResult::Call(function, *self)),
+            other => Err(MetaError::BadMetamethod(other.type_name())),
+        }
+    }
+
+    /// Removes every entry, keeping the metatable.  The array part keeps its size with every entry
+    /// set to nil, and the map part keeps its capacity, so refilling the table does not need to
+    /// allocate again.
+    pub fn clear(&self, mc: MutationContext<'gc, '_>) {
+        self.0.write(mc).clear()
+    }
+
+    /// Inserts the value at the given position, shifting the entries from that position up to the
+    /// border one place up, like `table.insert`.  The position must be between 1 and `raw_length() +
+    /// 1`.  Ignores any metatable.
+    pub fn insert<V: Into<Value<'gc>>>(
+        &self,
+        mc: MutationContext<'gc, '_>,
+        pos: i64,
+        value: V,
+    ) -> Result<(), PositionOutOfBounds> {
+        self.0.write(mc).insert(pos, value.into())
+    }
+
+    /// Removes and returns the value at the given position, shifting the entries above it up to the
+    /// border one place down, like `table.remove`.  The position must be between 1 and
+    /// `raw_length() + 1`, or equal to `raw_length()`.  Ignores any metatable.
+    pub fn remove(
+        &self,
+        mc: MutationContext<'gc, '_>,
+        pos: i64,
+    ) -> Result<Value<'gc>, PositionOutOfBounds> {
+        self.0.write(mc).remove(pos)
+    }
+
+    /// The size of the array part, counting nil entries.
+    pub fn len_array_part(&self) -> usize {
+        self.0.read().array.len()
+    }
+
+    fn metamethod(&self, name: &'static [u8]) -> Value<'gc> {
+        match self.metatable() {
+            Some(metatable) => metatable.raw_get(String::new_static(name)),
+            None => Value::Nil,
+        }
+    }
 }
 
 pub struct TableIterator<'gc>(std::collections::hash_map::IntoIter<TableKey<'gc>, Value<'gc>>);
@@ -93,6 +284,7 @@ impl<'gc> IntoIterator for Table<'gc> {
 pub struct TableState<'gc> {
     array: Vec<Value<'gc>>,
     map: FxHashMap<TableKey<'gc>, Value<'gc>>,
+    metatable: Option<Table<'gc>>,
 }
 
 impl<'gc> TableState<'gc> {
@@ -216,6 +408,54 @@ impl<'gc> TableState<'gc> {
         }
     }
 
+    pub fn insert(&mut self, pos: i64, value: Value<'gc>) -> Result<(), PositionOutOfBounds> {
+        let end = self.length() + 1;
+        if pos < 1 || pos > end {
+            return Err(PositionOutOfBounds(pos));
+        }
+        // When the sequence lies within the array part, `end` is a nil entry, and rotating it down
+        // to `pos` shifts everything else up.
+        if end as u64 <= self.array.len() as u64 {
+            self.array[pos as usize - 1..end as usize].rotate_right(1);
+            self.array[pos as usize - 1] = value;
+        } else {
+            for i in (pos + 1..=end).rev() {
+                let moved = self.get(Value::Integer(i - 1));
+                self.set(Value::Integer(i), moved).unwrap();
+            }
+            self.set(Value::Integer(pos), value).unwrap();
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, pos: i64) -> Result<Value<'gc>, PositionOutOfBounds> {
+        let size = self.length();
+        if pos != size && (pos < 1 || pos > size + 1) {
+            return Err(PositionOutOfBounds(pos));
+        }
+        if pos >= 1 && size as u64 <= self.array.len() as u64 && pos <= size {
+            let removed = self.array[pos as usize - 1];
+            self.array[pos as usize - 1..size as usize].rotate_left(1);
+            self.array[size as usize - 1] = Value::Nil;
+            Ok(removed)
+        } else {
+            let removed = self.get(Value::Integer(pos));
+            for i in pos..size {
+                let moved = self.get(Value::Integer(i + 1));
+                self.set(Value::Integer(i), moved).unwrap();
+            }
+            self.set(Value::Integer(pos.max(size)), Value::Nil).unwrap();
+            Ok(removed)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for value in &mut self.array {
+            *value = Value::Nil;
+        }
+        self.map.clear();
+    }
+
     /// Returns a 'border' for this table.
     ///
     /// A 'border' for a table is any i >= 0 where:
@@ -275,6 +515,11 @@ impl<'gc> TableState<'gc> {
 }
 
 // Value which implements Hash and Eq, and cannot contain Nil or NaN values.
+//
+// This implements Lua key equality, where a float with an exact integer representation is the same
+// key as that integer, so `t[2]` and `t[2.0]` refer to the same entry.  This is deliberately
+// different from `Constant`, which compares bit for bit so that the compiler keeps `2` and `2.0`
+// as distinct constants.
 #[derive(Debug, Clone, Collect, PartialEq)]
 #[collect(empty_drop)]
 struct TableKey<'gc>(Value<'gc>);
@@ -352,12 +597,10 @@ fn f64_to_i64(n: f64) -> Option<i64> {
 // corresponding f64s are equal (-0.0 and 0.0 return the same bit pattern).
 fn canonical_float_bytes(f: f64) -> u64 {
     assert!(!f.is_nan());
-    unsafe {
-        if f == 0.0 {
-            mem::transmute(0.0f64)
-        } else {
-            mem::transmute(f)
-        }
+    if f == 0.0 {
+        0.0f64.to_bits()
+    } else {
+        f.to_bits()
     }
 }
 
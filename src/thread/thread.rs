@@ -364,8 +364,9 @@ impl<'gc, 'a> LuaFrame<'gc, 'a> {
                         let fixed_params = closure.0.proto.fixed_params as usize;
                         let stack_size = closure.0.proto.stack_size as usize;
 
+                        // Discard anything past the arguments so that missing parameters are nil
+                        self.state.values.truncate(function_index + 1 + arg_count);
                         let base = if arg_count > fixed_params {
-                            self.state.values.truncate(function_index + 1 + arg_count);
                             self.state.values[function_index + 1..].rotate_left(fixed_params);
                             function_index + 1 + (arg_count - fixed_params)
                         } else {
@@ -517,8 +518,9 @@ impl<'gc, 'a> LuaFrame<'gc, 'a> {
                         let fixed_params = closure.0.proto.fixed_params as usize;
                         let stack_size = closure.0.proto.stack_size as usize;
 
+                        // Discard anything past the arguments so that missing parameters are nil
+                        self.state.values.truncate(bottom + 1 + arg_count);
                         let base = if arg_count > fixed_params {
-                            self.state.values.truncate(bottom + 1 + arg_count);
                             self.state.values[bottom + 1..].rotate_left(fixed_params);
                             bottom + 1 + (arg_count - fixed_params)
                         } else {
@@ -604,6 +606,12 @@ impl<'gc, 'a> LuaFrame<'gc, 'a> {
                             .map(|c| c as usize)
                             .unwrap_or(count);
 
+                        // The returning function's stack may be smaller than the number of
+                        // expected returns.
+                        if self.state.values.len() < bottom + returning {
+                            self.state.values.resize(bottom + returning, Value::Nil);
+                        }
+
                         for i in 0..returning.min(count) {
                             self.state.values[bottom + i] = self.state.values[start + i]
                         }
@@ -49,24 +49,32 @@ pub(crate) fn run_vm<'gc>(
                 }
             }
 
-            OpCode::NewTable { dest } => {
-                registers.stack_frame[dest.0 as usize] = Value::Table(Table::new(mc));
+            OpCode::NewTable {
+                dest,
+                array_size,
+                map_size,
+            } => {
+                registers.stack_frame[dest.0 as usize] = Value::Table(Table::with_capacity(
+                    mc,
+                    array_size as usize,
+                    map_size as usize,
+                ));
             }
 
             OpCode::GetTableR { dest, table, key } => {
                 registers.stack_frame[dest.0 as usize] =
                     get_table(registers.stack_frame[table.0 as usize])?
-                        .get(registers.stack_frame[key.0 as usize]);
+                        .raw_get(registers.stack_frame[key.0 as usize]);
             }
 
             OpCode::GetTableC { dest, table, key } => {
                 registers.stack_frame[dest.0 as usize] =
                     get_table(registers.stack_frame[table.0 as usize])?
-                        .get(current_function.0.proto.constants[key.0 as usize].to_value())
+                        .raw_get(current_function.0.proto.constants[key.0 as usize].to_value())
             }
 
             OpCode::SetTableRR { table, key, value } => {
-                get_table(registers.stack_frame[table.0 as usize])?.set(
+                get_table(registers.stack_frame[table.0 as usize])?.raw_set(
                     mc,
                     registers.stack_frame[key.0 as usize],
                     registers.stack_frame[value.0 as usize],
@@ -74,7 +82,7 @@ pub(crate) fn run_vm<'gc>(
             }
 
             OpCode::SetTableRC { table, key, value } => {
-                get_table(registers.stack_frame[table.0 as usize])?.set(
+                get_table(registers.stack_frame[table.0 as usize])?.raw_set(
                     mc,
                     registers.stack_frame[key.0 as usize],
                     current_function.0.proto.constants[value.0 as usize].to_value(),
@@ -82,7 +90,7 @@ pub(crate) fn run_vm<'gc>(
             }
 
             OpCode::SetTableCR { table, key, value } => {
-                get_table(registers.stack_frame[table.0 as usize])?.set(
+                get_table(registers.stack_frame[table.0 as usize])?.raw_set(
                     mc,
                     current_function.0.proto.constants[key.0 as usize].to_value(),
                     registers.stack_frame[value.0 as usize],
@@ -90,7 +98,7 @@ pub(crate) fn run_vm<'gc>(
             }
 
             OpCode::SetTableCC { table, key, value } => {
-                get_table(registers.stack_frame[table.0 as usize])?.set(
+                get_table(registers.stack_frame[table.0 as usize])?.raw_set(
                     mc,
                     current_function.0.proto.constants[key.0 as usize].to_value(),
                     current_function.0.proto.constants[value.0 as usize].to_value(),
@@ -101,18 +109,18 @@ pub(crate) fn run_vm<'gc>(
                 registers.stack_frame[dest.0 as usize] = get_table(
                     registers.get_upvalue(current_function.0.upvalues[table.0 as usize]),
                 )?
-                .get(registers.stack_frame[key.0 as usize]);
+                .raw_get(registers.stack_frame[key.0 as usize]);
             }
 
             OpCode::GetUpTableC { dest, table, key } => {
                 registers.stack_frame[dest.0 as usize] =
                     get_table(registers.get_upvalue(current_function.0.upvalues[table.0 as usize]))?
-                        .get(current_function.0.proto.constants[key.0 as usize].to_value())
+                        .raw_get(current_function.0.proto.constants[key.0 as usize].to_value())
             }
 
             OpCode::SetUpTableRR { table, key, value } => {
                 get_table(registers.get_upvalue(current_function.0.upvalues[table.0 as usize]))?
-                    .set(
+                    .raw_set(
                         mc,
                         registers.stack_frame[key.0 as usize],
                         registers.stack_frame[value.0 as usize],
@@ -121,7 +129,7 @@ pub(crate) fn run_vm<'gc>(
 
             OpCode::SetUpTableRC { table, key, value } => {
                 get_table(registers.get_upvalue(current_function.0.upvalues[table.0 as usize]))?
-                    .set(
+                    .raw_set(
                         mc,
                         registers.stack_frame[key.0 as usize],
                         current_function.0.proto.constants[value.0 as usize].to_value(),
@@ -130,7 +138,7 @@ pub(crate) fn run_vm<'gc>(
 
             OpCode::SetUpTableCR { table, key, value } => {
                 get_table(registers.get_upvalue(current_function.0.upvalues[table.0 as usize]))?
-                    .set(
+                    .raw_set(
                         mc,
                         current_function.0.proto.constants[key.0 as usize].to_value(),
                         registers.stack_frame[value.0 as usize],
@@ -139,13 +147,28 @@ pub(crate) fn run_vm<'gc>(
 
             OpCode::SetUpTableCC { table, key, value } => {
                 get_table(registers.get_upvalue(current_function.0.upvalues[table.0 as usize]))?
-                    .set(
+                    .raw_set(
                         mc,
                         current_function.0.proto.constants[key.0 as usize].to_value(),
                         current_function.0.proto.constants[value.0 as usize].to_value(),
                     )?;
             }
 
+            OpCode::GetGlobal { dest, env, name } => {
+                registers.stack_frame[dest.0 as usize] =
+                    get_table(registers.get_upvalue(current_function.0.upvalues[env.0 as usize]))?
+                        .raw_get(current_function.0.proto.constants[name.0 as usize].to_value())
+            }
+
+            OpCode::SetGlobal { env, name, value } => {
+                get_table(registers.get_upvalue(current_function.0.upvalues[env.0 as usize]))?
+                    .raw_set(
+                        mc,
+                        current_function.0.proto.constants[name.0 as usize].to_value(),
+                        registers.stack_frame[value.0 as usize],
+                    )?;
+            }
+
             OpCode::Call {
                 func,
                 args,
@@ -223,50 +246,36 @@ pub(crate) fn run_vm<'gc>(
             }
 
             OpCode::NumericForPrep { base, jump } => {
-                registers.stack_frame[base.0 as usize] = registers.stack_frame[base.0 as usize]
-                    .subtract(registers.stack_frame[base.0 as usize + 2])
-                    .ok_or(BinaryOperatorError::Subtract)?;
-                *registers.pc = add_offset(*registers.pc, jump);
+                if !numeric_for_prep(registers.stack_frame, base)? {
+                    *registers.pc = add_offset(*registers.pc, jump);
+                }
             }
 
             OpCode::NumericForLoop { base, jump } => {
+                if numeric_for_step(registers.stack_frame, base) {
+                    *registers.pc = add_offset(*registers.pc, jump);
+                }
+            }
+
+            OpCode::NumericForIncLoop { base, jump } => {
                 match (
                     registers.stack_frame[base.0 as usize],
                     registers.stack_frame[base.0 as usize + 1],
-                    registers.stack_frame[base.0 as usize + 2],
                 ) {
-                    (Value::Integer(index), Value::Integer(limit), Value::Integer(step)) => {
-                        let index = index + step;
-                        registers.stack_frame[base.0 as usize] = Value::Integer(index);
-
-                        let past_end = if step < 0 {
-                            index < limit
-                        } else {
-                            limit < index
-                        };
-                        if !past_end {
+                    (Value::Integer(index), Value::Integer(remaining)) => {
+                        // The iteration count bounds the index, so it can never overflow.
+                        if remaining != 0 {
+                            let index = Value::Integer(index + 1);
+                            registers.stack_frame[base.0 as usize] = index;
+                            registers.stack_frame[base.0 as usize + 1] =
+                                Value::Integer(remaining.wrapping_sub(1));
+                            registers.stack_frame[base.0 as usize + 3] = index;
                             *registers.pc = add_offset(*registers.pc, jump);
-                            registers.stack_frame[base.0 as usize + 3] = Value::Integer(index);
                         }
                     }
-                    (index, limit, step) => {
-                        if let (Some(index), Some(limit), Some(step)) =
-                            (index.to_number(), limit.to_number(), step.to_number())
-                        {
-                            let index = index + step;
-                            registers.stack_frame[base.0 as usize] = Value::Number(index);
-
-                            let past_end = if step < 0.0 {
-                                index < limit
-                            } else {
-                                limit < index
-                            };
-                            if !past_end {
-                                *registers.pc = add_offset(*registers.pc, jump);
-                                registers.stack_frame[base.0 as usize + 3] = Value::Number(index);
-                            }
-                        } else {
-                            return Err(BinaryOperatorError::Add.into());
+                    _ => {
+                        if numeric_for_step(registers.stack_frame, base) {
+                            *registers.pc = add_offset(*registers.pc, jump);
                         }
                     }
                 }
@@ -294,14 +303,14 @@ pub(crate) fn run_vm<'gc>(
                 let table = registers.stack_frame[table.0 as usize];
                 let key = current_function.0.proto.constants[key.0 as usize].to_value();
                 registers.stack_frame[base.0 as usize + 1] = table;
-                registers.stack_frame[base.0 as usize] = get_table(table)?.get(key);
+                registers.stack_frame[base.0 as usize] = get_table(table)?.raw_get(key);
             }
 
             OpCode::SelfC { base, table, key } => {
                 let table = registers.stack_frame[table.0 as usize];
                 let key = current_function.0.proto.constants[key.0 as usize].to_value();
                 registers.stack_frame[base.0 as usize + 1] = table;
-                registers.stack_frame[base.0 as usize] = get_table(table)?.get(key);
+                registers.stack_frame[base.0 as usize] = get_table(table)?.raw_get(key);
             }
 
             OpCode::Concat {
@@ -309,14 +318,10 @@ pub(crate) fn run_vm<'gc>(
                 source,
                 count,
             } => {
-                registers.stack_frame[dest.0 as usize] = Value::String(
-                    String::concat(
-                        mc,
-                        &registers.stack_frame
-                            [source.0 as usize..source.0 as usize + count as usize],
-                    )
-                    .unwrap(),
-                );
+                registers.stack_frame[dest.0 as usize] = Value::String(String::concat_all(
+                    mc,
+                    &registers.stack_frame[source.0 as usize..source.0 as usize + count as usize],
+                )?);
             }
 
             OpCode::GetUpValue { source, dest } => {
@@ -333,8 +338,9 @@ pub(crate) fn run_vm<'gc>(
             }
 
             OpCode::Length { dest, source } => {
-                registers.stack_frame[dest.0 as usize] =
-                    Value::Integer(get_table(registers.stack_frame[source.0 as usize])?.length());
+                registers.stack_frame[dest.0 as usize] = Value::Integer(
+                    get_table(registers.stack_frame[source.0 as usize])?.raw_length(),
+                );
             }
 
             OpCode::EqRR {
@@ -881,6 +887,148 @@ pub(crate) fn run_vm<'gc>(
     Ok(instructions)
 }
 
+// Sets up the numeric for loop with control registers at `base`, as described for
+// `OpCode::NumericForPrep`.  Returns whether the loop body runs at all.
+fn numeric_for_prep<'gc>(
+    stack_frame: &mut [Value<'gc>],
+    base: RegisterIndex,
+) -> Result<bool, BinaryOperatorError> {
+    let base = base.0 as usize;
+    match (
+        stack_frame[base],
+        stack_frame[base + 1],
+        stack_frame[base + 2],
+    ) {
+        (Value::Integer(initial), limit, Value::Integer(step)) => {
+            let limit = match for_integer_limit(limit, step)? {
+                Some(limit) => limit,
+                None => return Ok(false),
+            };
+
+            // Like PUC-Rio Lua, the number of remaining iterations is computed up front in
+            // unsigned arithmetic, so the index is never stepped past the limit and can never
+            // overflow, even for loops that end at `math.maxinteger` or `math.mininteger`.  A
+            // zero step never reaches the limit, and so runs for as long as it can.
+            let remaining = if step > 0 {
+                if initial > limit {
+                    return Ok(false);
+                }
+                (limit as u64).wrapping_sub(initial as u64) / step as u64
+            } else if step < 0 {
+                if initial < limit {
+                    return Ok(false);
+                }
+                (initial as u64).wrapping_sub(limit as u64) / (step as u64).wrapping_neg()
+            } else {
+                if initial > limit {
+                    return Ok(false);
+                }
+                u64::MAX
+            };
+
+            stack_frame[base + 1] = Value::Integer(remaining as i64);
+            stack_frame[base + 3] = Value::Integer(initial);
+            Ok(true)
+        }
+        (initial, limit, step) => {
+            let (initial, limit, step) =
+                match (initial.to_number(), limit.to_number(), step.to_number()) {
+                    (Some(initial), Some(limit), Some(step)) => (initial, limit, step),
+                    _ => return Err(BinaryOperatorError::Subtract),
+                };
+
+            stack_frame[base] = Value::Number(initial);
+            stack_frame[base + 1] = Value::Number(limit);
+            stack_frame[base + 2] = Value::Number(step);
+
+            let runs = if step < 0.0 {
+                limit <= initial
+            } else {
+                initial <= limit
+            };
+            if runs {
+                stack_frame[base + 3] = Value::Number(initial);
+            }
+            Ok(runs)
+        }
+    }
+}
+
+// Converts the limit of an integer for loop to an integer, rounding a float limit towards the
+// initial value and clipping it to the integer range.  Returns `None` if the loop cannot run at
+// all.
+fn for_integer_limit(limit: Value, step: i64) -> Result<Option<i64>, BinaryOperatorError> {
+    // The bounds of the `i64` range, both of which are exactly representable as floats.
+    const MIN: f64 = -9_223_372_036_854_775_808.0;
+    const MAX: f64 = 9_223_372_036_854_775_808.0;
+
+    match limit {
+        Value::Integer(limit) => Ok(Some(limit)),
+        limit => {
+            let limit = limit.to_number().ok_or(BinaryOperatorError::Subtract)?;
+            let limit = if step < 0 {
+                limit.ceil()
+            } else {
+                limit.floor()
+            };
+            Ok(if limit.is_nan() {
+                None
+            } else if limit >= MAX {
+                if step < 0 {
+                    None
+                } else {
+                    Some(i64::MAX)
+                }
+            } else if limit < MIN {
+                if step > 0 {
+                    None
+                } else {
+                    Some(i64::MIN)
+                }
+            } else {
+                Some(limit as i64)
+            })
+        }
+    }
+}
+
+// Steps the numeric for loop with control registers at `base`, as described for
+// `OpCode::NumericForLoop`.  Returns whether the loop continues.
+fn numeric_for_step<'gc>(stack_frame: &mut [Value<'gc>], base: RegisterIndex) -> bool {
+    let base = base.0 as usize;
+    match (
+        stack_frame[base],
+        stack_frame[base + 1],
+        stack_frame[base + 2],
+    ) {
+        (Value::Integer(index), Value::Integer(remaining), Value::Integer(step)) => {
+            if remaining == 0 {
+                return false;
+            }
+            let index = Value::Integer(index.wrapping_add(step));
+            stack_frame[base] = index;
+            stack_frame[base + 1] = Value::Integer(remaining.wrapping_sub(1));
+            stack_frame[base + 3] = index;
+            true
+        }
+        (Value::Number(index), Value::Number(limit), Value::Number(step)) => {
+            let index = index + step;
+            stack_frame[base] = Value::Number(index);
+
+            let past_end = if step < 0.0 {
+                index < limit
+            } else {
+                limit < index
+            };
+            if !past_end {
+                stack_frame[base + 3] = Value::Number(index);
+            }
+            !past_end
+        }
+        _ => false,
+    }
+}
+
 fn get_table<'gc>(value: Value<'gc>) -> Result<Table<'gc>, TypeError> {
     match value {
         Value::Table(t) => Ok(t),
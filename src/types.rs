@@ -1,5 +1,7 @@
 use std::fmt::{self, Debug};
 
+use num_traits::cast;
+
 use gc_arena::Collect;
 
 /// An index that points to a register in the stack relative to the current frame.
@@ -7,26 +9,101 @@ use gc_arena::Collect;
 #[collect(require_static)]
 pub struct RegisterIndex(pub u8);
 
+impl RegisterIndex {
+    pub fn new(index: u8) -> RegisterIndex {
+        RegisterIndex(index)
+    }
+
+    /// Returns None if the given index does not fit in an 8 bit register index.
+    pub fn try_new(index: usize) -> Option<RegisterIndex> {
+        cast(index).map(RegisterIndex)
+    }
+
+    pub fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// An 8 bit index into the constant table
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Collect)]
 #[collect(require_static)]
 pub struct ConstantIndex8(pub u8);
 
+impl ConstantIndex8 {
+    pub fn new(index: u8) -> ConstantIndex8 {
+        ConstantIndex8(index)
+    }
+
+    /// Returns None if the given index does not fit in an 8 bit constant index.
+    pub fn try_new(index: usize) -> Option<ConstantIndex8> {
+        cast(index).map(ConstantIndex8)
+    }
+
+    pub fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// A 16 bit index into the constant table
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Collect)]
 #[collect(require_static)]
 pub struct ConstantIndex16(pub u16);
 
+impl ConstantIndex16 {
+    pub fn new(index: u16) -> ConstantIndex16 {
+        ConstantIndex16(index)
+    }
+
+    /// Returns None if the given index does not fit in a 16 bit constant index.
+    pub fn try_new(index: usize) -> Option<ConstantIndex16> {
+        cast(index).map(ConstantIndex16)
+    }
+
+    pub fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// An index into the upvalue table
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Collect)]
 #[collect(require_static)]
 pub struct UpValueIndex(pub u8);
 
+impl UpValueIndex {
+    pub fn new(index: u8) -> UpValueIndex {
+        UpValueIndex(index)
+    }
+
+    /// Returns None if the given index does not fit in an 8 bit upvalue index.
+    pub fn try_new(index: usize) -> Option<UpValueIndex> {
+        cast(index).map(UpValueIndex)
+    }
+
+    pub fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// An index into the prototype table
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Collect)]
 #[collect(require_static)]
 pub struct PrototypeIndex(pub u8);
 
+impl PrototypeIndex {
+    pub fn new(index: u8) -> PrototypeIndex {
+        PrototypeIndex(index)
+    }
+
+    /// Returns None if the given index does not fit in an 8 bit prototype index.
+    pub fn try_new(index: usize) -> Option<PrototypeIndex> {
+        cast(index).map(PrototypeIndex)
+    }
+
+    pub fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// A one byte Option value that can either be Some(0-254) or None
 #[derive(Copy, Clone, Eq, PartialEq, Collect)]
 #[collect(require_static)]
@@ -1,9 +1,10 @@
-use std::{f64, i64, io};
+use std::{cmp::Ordering, f64, i64, io};
 
 use gc_arena::{Collect, Gc, GcCell, MutationContext};
 
 use crate::{
     lexer::{read_float, read_hex_float},
+    string::write_number,
     Callback, Closure, String, Table, Thread,
 };
 
@@ -37,11 +38,15 @@ impl<'gc> PartialEq for Value<'gc> {
             (Value::Boolean(_), _) => false,
 
             (Value::Integer(a), Value::Integer(b)) => a == b,
-            (Value::Integer(a), Value::Number(b)) => a as f64 == b,
+            (Value::Integer(a), Value::Number(b)) => {
+                compare_integer_number(a, b) == Some(Ordering::Equal)
+            }
             (Value::Integer(_), _) => false,
 
             (Value::Number(a), Value::Number(b)) => a == b,
-            (Value::Number(a), Value::Integer(b)) => b as f64 == a,
+            (Value::Number(a), Value::Integer(b)) => {
+                compare_integer_number(b, a) == Some(Ordering::Equal)
+            }
             (Value::Number(_), _) => false,
 
             (Value::String(a), Value::String(b)) => a == b,
@@ -129,8 +134,8 @@ impl<'gc> Value<'gc> {
     /// Interprets Numbers, Integers, and Strings as a String, if possible.
     pub fn to_string(self, mc: MutationContext<'gc, '_>) -> Option<String<'gc>> {
         match self {
-            Value::Integer(a) => Some(String::concat(mc, &[Value::Integer(a)]).unwrap()),
-            Value::Number(a) => Some(String::concat(mc, &[Value::Number(a)]).unwrap()),
+            Value::Integer(a) => Some(String::from_i64(mc, a)),
+            Value::Number(a) => Some(String::from_f64(mc, a)),
             Value::String(a) => Some(a),
             _ => None,
         }
@@ -194,11 +199,24 @@ impl<'gc> Value<'gc> {
             if b == 0 {
                 None
             } else {
-                Some(Value::Integer(((a % b) + b) % b))
+                // The result must have the same sign as the divisor
+                let m = a.wrapping_rem(b);
+                Some(Value::Integer(if m != 0 && (m ^ b) < 0 {
+                    m + b
+                } else {
+                    m
+                }))
             }
         } else {
             let (a, b) = (self.to_number()?, other.to_number()?);
-            Some(Value::Number(((a % b) + b) % b))
+            let m = a % b;
+            Some(Value::Number(
+                if (m > 0.0 && b < 0.0) || (m < 0.0 && b > 0.0) {
+                    m + b
+                } else {
+                    m
+                },
+            ))
         }
     }
 
@@ -248,6 +266,10 @@ impl<'gc> Value<'gc> {
     pub fn less_than(self, other: Value<'gc>) -> Option<bool> {
         if let (Value::Integer(a), Value::Integer(b)) = (self, other) {
             Some(a < b)
+        } else if let (Value::Integer(a), Value::Number(b)) = (self, other) {
+            Some(compare_integer_number(a, b) == Some(Ordering::Less))
+        } else if let (Value::Number(a), Value::Integer(b)) = (self, other) {
+            Some(compare_integer_number(b, a) == Some(Ordering::Greater))
         } else if let (Value::String(a), Value::String(b)) = (self, other) {
             Some(a.as_bytes() < b.as_bytes())
         } else {
@@ -258,6 +280,16 @@ impl<'gc> Value<'gc> {
     pub fn less_equal(self, other: Value<'gc>) -> Option<bool> {
         if let (Value::Integer(a), Value::Integer(b)) = (self, other) {
             Some(a <= b)
+        } else if let (Value::Integer(a), Value::Number(b)) = (self, other) {
+            Some(matches!(
+                compare_integer_number(a, b),
+                Some(Ordering::Less) | Some(Ordering::Equal)
+            ))
+        } else if let (Value::Number(a), Value::Integer(b)) = (self, other) {
+            Some(matches!(
+                compare_integer_number(b, a),
+                Some(Ordering::Greater) | Some(Ordering::Equal)
+            ))
         } else if let (Value::String(a), Value::String(b)) = (self, other) {
             Some(a.as_bytes() <= b.as_bytes())
         } else {
@@ -270,7 +302,7 @@ impl<'gc> Value<'gc> {
             Value::Nil => write!(w, "nil"),
             Value::Boolean(b) => write!(w, "{}", b),
             Value::Integer(i) => write!(w, "{}", i),
-            Value::Number(f) => write!(w, "{}", f),
+            Value::Number(f) => write_number(w, f),
             Value::String(s) => w.write_all(s.as_bytes()),
             Value::Table(t) => write!(w, "<table {:?}>", t.0.as_ptr()),
             Value::Function(Function::Closure(c)) => write!(w, "<function {:?}>", Gc::as_ptr(c.0)),
@@ -333,3 +365,30 @@ impl<'gc> From<Callback<'gc>> for Value<'gc> {
         Value::Function(Function::Callback(v))
     }
 }
+
+// Compares an integer with a float exactly, rather than converting the integer to a float, which
+// loses precision past 2^53.  Returns None if the float is NaN.
+fn compare_integer_number(i: i64, f: f64) -> Option<Ordering> {
+    // 2^63, the smallest float past the end of the integer range
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+    if f.is_nan() {
+        None
+    } else if f >= LIMIT {
+        Some(Ordering::Less)
+    } else if f < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        // The float is within the integer range, so its integral part converts exactly.
+        let trunc = f.trunc();
+        Some(i.cmp(&(trunc as i64)).then_with(|| {
+            if f > trunc {
+                Ordering::Less
+            } else if f < trunc {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        }))
+    }
+}
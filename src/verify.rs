@@ -0,0 +1,405 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::{
+    ConstantIndex16, ConstantIndex8, FunctionProto, OpCode, PrototypeIndex, RegisterIndex,
+    UpValueDescriptor, UpValueIndex, VarCount,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VerifyError {
+    BadRegister { pc: usize },
+    BadConstant { pc: usize },
+    BadUpValue { pc: usize },
+    BadPrototype { pc: usize },
+    BadJump { pc: usize },
+    BadUpValueDescriptor { index: usize },
+    NoReturn,
+}
+
+impl StdError for VerifyError {}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::BadRegister { pc } => write!(fmt, "register out of range at {}", pc),
+            VerifyError::BadConstant { pc } => write!(fmt, "constant out of range at {}", pc),
+            VerifyError::BadUpValue { pc } => write!(fmt, "upvalue out of range at {}", pc),
+            VerifyError::BadPrototype { pc } => write!(fmt, "prototype out of range at {}", pc),
+            VerifyError::BadJump { pc } => write!(fmt, "jump target out of range at {}", pc),
+            VerifyError::BadUpValueDescriptor { index } => {
+                write!(
+                    fmt,
+                    "upvalue descriptor {} is invalid for its parent",
+                    index
+                )
+            }
+            VerifyError::NoReturn => write!(fmt, "function does not end in a return"),
+        }
+    }
+}
+
+/// Checks that every index in the given prototype (and all of its nested prototypes) is in range,
+/// every jump lands on an opcode in the function, and the function ends with a return.  The upvalues
+/// of nested prototypes must refer to registers or upvalues that exist in their parent, and only
+/// the top-level prototype may have an `Environment` upvalue.
+///
+/// Prototypes produced by the compiler always pass, this is intended to catch compiler bugs or
+/// prototypes that come from elsewhere before they reach the VM.
+pub fn verify(proto: &FunctionProto) -> Result<(), VerifyError> {
+    verify_proto(proto, None)
+}
+
+fn verify_proto(proto: &FunctionProto, parent: Option<&FunctionProto>) -> Result<(), VerifyError> {
+    if let Some(parent) = parent {
+        for (index, &desc) in proto.upvalues.iter().enumerate() {
+            let valid = match desc {
+                UpValueDescriptor::Environment => false,
+                UpValueDescriptor::ParentLocal(reg) => (reg.0 as u16) < parent.stack_size,
+                UpValueDescriptor::Outer(uvindex) => (uvindex.0 as usize) < parent.upvalues.len(),
+            };
+            if !valid {
+                return Err(VerifyError::BadUpValueDescriptor { index });
+            }
+        }
+    }
+
+    match proto.opcodes.last() {
+        Some(OpCode::Return { .. }) | Some(OpCode::TailCall { .. }) => {}
+        _ => return Err(VerifyError::NoReturn),
+    }
+
+    for pc in 0..proto.opcodes.len() {
+        Verifier { proto, pc }.opcode(proto.opcodes[pc])?;
+    }
+
+    for prototype in &proto.prototypes {
+        verify_proto(prototype, Some(proto))?;
+    }
+
+    Ok(())
+}
+
+struct Verifier<'a, 'gc> {
+    proto: &'a FunctionProto<'gc>,
+    pc: usize,
+}
+
+impl<'a, 'gc> Verifier<'a, 'gc> {
+    fn opcode(&self, opcode: OpCode) -> Result<(), VerifyError> {
+        match opcode {
+            OpCode::Move { dest, source }
+            | OpCode::Not { dest, source }
+            | OpCode::Minus { dest, source }
+            | OpCode::BitNot { dest, source }
+            | OpCode::Length { dest, source } => {
+                self.register(dest)?;
+                self.register(source)?;
+            }
+            OpCode::LoadConstant { dest, constant } => {
+                self.register(dest)?;
+                self.constant16(constant)?;
+            }
+            OpCode::LoadBool {
+                dest, skip_next, ..
+            } => {
+                self.register(dest)?;
+                if skip_next {
+                    self.jump(1)?;
+                }
+            }
+            OpCode::LoadNil { dest, count } => {
+                self.registers(dest, count as usize)?;
+            }
+            OpCode::NewTable { dest, .. } => {
+                self.register(dest)?;
+            }
+            OpCode::GetTableR { dest, table, key } => {
+                self.register(dest)?;
+                self.register(table)?;
+                self.register(key)?;
+            }
+            OpCode::GetTableC { dest, table, key } => {
+                self.register(dest)?;
+                self.register(table)?;
+                self.constant8(key)?;
+            }
+            OpCode::SetTableRR { table, key, value } => {
+                self.register(table)?;
+                self.register(key)?;
+                self.register(value)?;
+            }
+            OpCode::SetTableRC { table, key, value } => {
+                self.register(table)?;
+                self.register(key)?;
+                self.constant8(value)?;
+            }
+            OpCode::SetTableCR { table, key, value } => {
+                self.register(table)?;
+                self.constant8(key)?;
+                self.register(value)?;
+            }
+            OpCode::SetTableCC { table, key, value } => {
+                self.register(table)?;
+                self.constant8(key)?;
+                self.constant8(value)?;
+            }
+            OpCode::GetUpTableR { dest, table, key } => {
+                self.register(dest)?;
+                self.upvalue(table)?;
+                self.register(key)?;
+            }
+            OpCode::GetUpTableC { dest, table, key } => {
+                self.register(dest)?;
+                self.upvalue(table)?;
+                self.constant8(key)?;
+            }
+            OpCode::SetUpTableRR { table, key, value } => {
+                self.upvalue(table)?;
+                self.register(key)?;
+                self.register(value)?;
+            }
+            OpCode::SetUpTableRC { table, key, value } => {
+                self.upvalue(table)?;
+                self.register(key)?;
+                self.constant8(value)?;
+            }
+            OpCode::SetUpTableCR { table, key, value } => {
+                self.upvalue(table)?;
+                self.constant8(key)?;
+                self.register(value)?;
+            }
+            OpCode::SetUpTableCC { table, key, value } => {
+                self.upvalue(table)?;
+                self.constant8(key)?;
+                self.constant8(value)?;
+            }
+            OpCode::GetGlobal { dest, env, name } => {
+                self.register(dest)?;
+                self.upvalue(env)?;
+                self.constant8(name)?;
+            }
+            OpCode::SetGlobal { env, name, value } => {
+                self.upvalue(env)?;
+                self.constant8(name)?;
+                self.register(value)?;
+            }
+            OpCode::Call { func, args, .. } | OpCode::TailCall { func, args } => {
+                self.registers(func, 1 + args.to_constant().unwrap_or(0) as usize)?;
+            }
+            OpCode::Return { start, count } | OpCode::VarArgs { dest: start, count } => {
+                self.var_registers(start, count)?;
+            }
+            OpCode::Jump {
+                offset,
+                close_upvalues,
+            } => {
+                self.jump(offset)?;
+                if let Some(r) = close_upvalues.to_u8() {
+                    self.registers(RegisterIndex(r), 0)?;
+                }
+            }
+            OpCode::Test { value, .. } => {
+                self.register(value)?;
+                self.jump(1)?;
+            }
+            OpCode::TestSet { dest, value, .. } => {
+                self.register(dest)?;
+                self.register(value)?;
+                self.jump(1)?;
+            }
+            OpCode::Closure { dest, proto } => {
+                self.register(dest)?;
+                self.prototype(proto)?;
+            }
+            OpCode::NumericForPrep { base, jump }
+            | OpCode::NumericForLoop { base, jump }
+            | OpCode::NumericForIncLoop { base, jump } => {
+                self.registers(base, 4)?;
+                self.jump(jump)?;
+            }
+            OpCode::GenericForCall { base, var_count } => {
+                self.registers(base, 3 + var_count as usize)?;
+            }
+            OpCode::GenericForLoop { base, jump } => {
+                self.registers(base, 2)?;
+                self.jump(jump)?;
+            }
+            OpCode::SelfR { base, table, key } => {
+                self.registers(base, 2)?;
+                self.register(table)?;
+                self.register(key)?;
+            }
+            OpCode::SelfC { base, table, key } => {
+                self.registers(base, 2)?;
+                self.register(table)?;
+                self.constant8(key)?;
+            }
+            OpCode::Concat {
+                dest,
+                source,
+                count,
+            } => {
+                self.register(dest)?;
+                self.registers(source, count as usize)?;
+            }
+            OpCode::GetUpValue { dest, source } => {
+                self.register(dest)?;
+                self.upvalue(source)?;
+            }
+            OpCode::SetUpValue { dest, source } => {
+                self.upvalue(dest)?;
+                self.register(source)?;
+            }
+            OpCode::EqRR { left, right, .. }
+            | OpCode::LessRR { left, right, .. }
+            | OpCode::LessEqRR { left, right, .. } => {
+                self.register(left)?;
+                self.register(right)?;
+                self.jump(1)?;
+            }
+            OpCode::EqRC { left, right, .. }
+            | OpCode::LessRC { left, right, .. }
+            | OpCode::LessEqRC { left, right, .. } => {
+                self.register(left)?;
+                self.constant8(right)?;
+                self.jump(1)?;
+            }
+            OpCode::EqCR { left, right, .. }
+            | OpCode::LessCR { left, right, .. }
+            | OpCode::LessEqCR { left, right, .. } => {
+                self.constant8(left)?;
+                self.register(right)?;
+                self.jump(1)?;
+            }
+            OpCode::EqCC { left, right, .. }
+            | OpCode::LessCC { left, right, .. }
+            | OpCode::LessEqCC { left, right, .. } => {
+                self.constant8(left)?;
+                self.constant8(right)?;
+                self.jump(1)?;
+            }
+            OpCode::AddRR { dest, left, right }
+            | OpCode::SubRR { dest, left, right }
+            | OpCode::MulRR { dest, left, right }
+            | OpCode::DivRR { dest, left, right }
+            | OpCode::IDivRR { dest, left, right }
+            | OpCode::ModRR { dest, left, right }
+            | OpCode::PowRR { dest, left, right }
+            | OpCode::BitAndRR { dest, left, right }
+            | OpCode::BitOrRR { dest, left, right }
+            | OpCode::BitXorRR { dest, left, right }
+            | OpCode::ShiftLeftRR { dest, left, right }
+            | OpCode::ShiftRightRR { dest, left, right } => {
+                self.register(dest)?;
+                self.register(left)?;
+                self.register(right)?;
+            }
+            OpCode::AddRC { dest, left, right }
+            | OpCode::SubRC { dest, left, right }
+            | OpCode::MulRC { dest, left, right }
+            | OpCode::DivRC { dest, left, right }
+            | OpCode::IDivRC { dest, left, right }
+            | OpCode::ModRC { dest, left, right }
+            | OpCode::PowRC { dest, left, right }
+            | OpCode::BitAndRC { dest, left, right }
+            | OpCode::BitOrRC { dest, left, right }
+            | OpCode::BitXorRC { dest, left, right }
+            | OpCode::ShiftLeftRC { dest, left, right }
+            | OpCode::ShiftRightRC { dest, left, right } => {
+                self.register(dest)?;
+                self.register(left)?;
+                self.constant8(right)?;
+            }
+            OpCode::AddCR { dest, left, right }
+            | OpCode::SubCR { dest, left, right }
+            | OpCode::MulCR { dest, left, right }
+            | OpCode::DivCR { dest, left, right }
+            | OpCode::IDivCR { dest, left, right }
+            | OpCode::ModCR { dest, left, right }
+            | OpCode::PowCR { dest, left, right }
+            | OpCode::BitAndCR { dest, left, right }
+            | OpCode::BitOrCR { dest, left, right }
+            | OpCode::BitXorCR { dest, left, right }
+            | OpCode::ShiftLeftCR { dest, left, right }
+            | OpCode::ShiftRightCR { dest, left, right } => {
+                self.register(dest)?;
+                self.constant8(left)?;
+                self.register(right)?;
+            }
+            OpCode::AddCC { dest, left, right }
+            | OpCode::SubCC { dest, left, right }
+            | OpCode::MulCC { dest, left, right }
+            | OpCode::DivCC { dest, left, right }
+            | OpCode::IDivCC { dest, left, right }
+            | OpCode::ModCC { dest, left, right }
+            | OpCode::PowCC { dest, left, right }
+            | OpCode::BitAndCC { dest, left, right }
+            | OpCode::BitOrCC { dest, left, right }
+            | OpCode::BitXorCC { dest, left, right }
+            | OpCode::ShiftLeftCC { dest, left, right }
+            | OpCode::ShiftRightCC { dest, left, right } => {
+                self.register(dest)?;
+                self.constant8(left)?;
+                self.constant8(right)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn register(&self, register: RegisterIndex) -> Result<(), VerifyError> {
+        self.registers(register, 1)
+    }
+
+    // Checks that `count` registers starting at `start` are all in range
+    fn registers(&self, start: RegisterIndex, count: usize) -> Result<(), VerifyError> {
+        if start.0 as usize + count <= self.proto.stack_size as usize {
+            Ok(())
+        } else {
+            Err(VerifyError::BadRegister { pc: self.pc })
+        }
+    }
+
+    // A variable count extends to the top of the stack, so only the start is checked
+    fn var_registers(&self, start: RegisterIndex, count: VarCount) -> Result<(), VerifyError> {
+        self.registers(start, count.to_constant().unwrap_or(0) as usize)
+    }
+
+    fn constant8(&self, constant: ConstantIndex8) -> Result<(), VerifyError> {
+        self.constant16(ConstantIndex16(constant.0 as u16))
+    }
+
+    fn constant16(&self, constant: ConstantIndex16) -> Result<(), VerifyError> {
+        if (constant.0 as usize) < self.proto.constants.len() {
+            Ok(())
+        } else {
+            Err(VerifyError::BadConstant { pc: self.pc })
+        }
+    }
+
+    fn upvalue(&self, upvalue: UpValueIndex) -> Result<(), VerifyError> {
+        if (upvalue.0 as usize) < self.proto.upvalues.len() {
+            Ok(())
+        } else {
+            Err(VerifyError::BadUpValue { pc: self.pc })
+        }
+    }
+
+    fn prototype(&self, prototype: PrototypeIndex) -> Result<(), VerifyError> {
+        if (prototype.0 as usize) < self.proto.prototypes.len() {
+            Ok(())
+        } else {
+            Err(VerifyError::BadPrototype { pc: self.pc })
+        }
+    }
+
+    // Checks that jumping by `offset` from after the current instruction lands on an opcode
+    fn jump(&self, offset: i16) -> Result<(), VerifyError> {
+        let target = self.pc as isize + 1 + offset as isize;
+        if target >= 0 && (target as usize) < self.proto.opcodes.len() {
+            Ok(())
+        } else {
+            Err(VerifyError::BadJump { pc: self.pc })
+        }
+    }
+}
@@ -15,7 +15,7 @@ fn callback() -> Result<(), Box<StaticError>> {
                 Ok(CallbackResult::Return(ret))
             });
             root.globals
-                .set(mc, String::new_static(b"callback"), callback)?;
+                .raw_set(mc, String::new_static(b"callback"), callback)?;
             Ok(())
         })
         .and_then_with(root, |mc, root, _| {
@@ -59,7 +59,7 @@ fn tail_call_trivial_callback() -> Result<(), Box<StaticError>> {
                 Ok(CallbackResult::Return(ret))
             });
             root.globals
-                .set(mc, String::new_static(b"callback"), callback)?;
+                .raw_set(mc, String::new_static(b"callback"), callback)?;
             Ok(())
         })
         .and_then_with(root, |mc, root, _| {
@@ -0,0 +1,2305 @@
+use std::collections::HashSet;
+use std::iter;
+
+use gc_arena::MutationContext;
+use gc_sequence::{self as sequence, SequenceExt, SequenceResultExt};
+use luster::parser::desugar_chunk;
+use luster::{
+    compile, compile_chunk_with_cache, compile_chunk_with_options, compile_expression,
+    compile_with_options, parse_chunk, verify, Closure, CompileStats, CompilerCache, CompilerError,
+    CompilerOptions, CompilerWarning, Constant, ConstantIndex16, ConstantIndex8, Error, Function,
+    FunctionProto, Listing, Lua, OpCode, Opt254, OwnedConstant, OwnedProto, ParserError,
+    RegisterIndex, Root, StaticError, ThreadSequence, UpValueDescriptor, UpValueIndex, Value,
+    VarCount,
+};
+
+// Compiles the prototype returned by `build` into a closure, calls it with the given integer
+// arguments on the main thread, and passes the returned values to `check`.
+fn run<B, C>(build: B, args: &[i64], check: C) -> Result<(), Box<StaticError>>
+where
+    B: 'static
+        + for<'gc> FnOnce(
+            MutationContext<'gc, '_>,
+            Root<'gc>,
+        ) -> Result<FunctionProto<'gc>, Error<'gc>>,
+    C: 'static + for<'gc> FnOnce(Vec<Value<'gc>>),
+{
+    let args = args.to_vec();
+    Lua::new().sequence(move |root| {
+        sequence::from_fn_with(root, move |mc, root| {
+            let proto = build(mc, root)?;
+            verify(&proto).unwrap();
+            Ok(Closure::new(mc, proto, Some(root.globals))?)
+        })
+        .and_chain_with(root, move |mc, root, closure| {
+            let args = args.into_iter().map(Value::Integer).collect::<Vec<_>>();
+            Ok(ThreadSequence::call_function(
+                mc,
+                root.main_thread,
+                Function::Closure(closure),
+                &args,
+            )?)
+        })
+        .map_ok(check)
+        .map_err(Error::to_static)
+        .boxed()
+    })?;
+    Ok(())
+}
+
+#[test]
+fn concat_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &br#"
+                local a, b = ...
+                return a .. "x" .. 1 .. "y" .. b, "a" .. 2 .. 3.5
+            "#[..],
+        )
+        .unwrap();
+
+        let strings = proto
+            .constants
+            .iter()
+            .filter_map(|c| match c {
+                Constant::String(s) => Some(s.as_bytes()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(strings, vec![&b"x1y"[..], &b"a23.5"[..]]);
+
+        let concats = proto
+            .opcodes
+            .iter()
+            .filter_map(|op| match op {
+                OpCode::Concat { count, .. } => Some(*count),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(concats, vec![3]);
+    });
+}
+
+#[test]
+fn length_concat_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &br#"local a = ... return #("ab" .. "cd"), #(1 .. 2.5 .. "x"), #("ab" .. a)"#[..],
+        )
+        .unwrap();
+        assert_eq!(
+            proto.constants[..2],
+            [Constant::Integer(4), Constant::Integer(5)]
+        );
+        assert_eq!(
+            proto
+                .opcodes
+                .iter()
+                .filter(|op| matches!(op, OpCode::Length { .. }))
+                .count(),
+            1
+        );
+    });
+}
+
+#[test]
+fn operator_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        // Each operator is folded independently of the others, so each case uses a single operator.
+        let cases: &[(&str, Option<Constant>)] = &[
+            ("1 + 2", Some(Constant::Integer(3))),
+            ("1 - 2.5", Some(Constant::Number(-1.5))),
+            ("3 * 4", Some(Constant::Integer(12))),
+            ("7 / 2", Some(Constant::Number(3.5))),
+            ("7 // 2", Some(Constant::Integer(3))),
+            ("7 % 3", Some(Constant::Integer(1))),
+            ("2 ^ 3", Some(Constant::Number(8.0))),
+            ("1 + {}", None),
+            ("1 + nil", None),
+            ("6 & 3", None),
+            ("6 | 3", None),
+            ("6 ~ 3", None),
+            ("1 << 2", None),
+            ("4 >> 1", None),
+            ("1 == 1.0", Some(Constant::Boolean(true))),
+            ("1 ~= 2", Some(Constant::Boolean(true))),
+            ("1 < 2", Some(Constant::Boolean(true))),
+            ("2 <= 1", Some(Constant::Boolean(false))),
+            ("2 > 1", Some(Constant::Boolean(true))),
+            ("1 >= 2", Some(Constant::Boolean(false))),
+            ("1 < 'a'", None),
+            ("-1.5", Some(Constant::Number(-1.5))),
+            ("- 'a'", None),
+            ("not nil", Some(Constant::Boolean(true))),
+            ("~5", Some(Constant::Integer(-6))),
+            ("~1.5", None),
+            ("#'abc'", Some(Constant::Integer(3))),
+            ("#1", None),
+        ];
+        for (expression, expected) in cases {
+            let proto =
+                compile_expression(mc, root.interned_strings, expression.as_bytes()).unwrap();
+            let folded = match proto.opcodes[0] {
+                OpCode::LoadConstant { constant, .. } if proto.opcodes.len() == 3 => {
+                    Some(proto.constants[constant.0 as usize])
+                }
+                OpCode::LoadBool { value, .. } if proto.opcodes.len() == 3 => {
+                    Some(Constant::Boolean(value))
+                }
+                _ => None,
+            };
+            assert_eq!(&folded, expected, "folding {}", expression);
+        }
+    });
+}
+
+#[test]
+fn modulo_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"return 5 % -3, -5 % 3, 5.5 % 2"[..],
+        )
+        .unwrap();
+        match &proto.constants[..] {
+            &[Constant::Integer(-1), Constant::Integer(1), Constant::Number(n)] => {
+                assert_eq!(n, 1.5)
+            }
+            constants => panic!("unexpected constants {:?}", constants),
+        }
+
+        // Integer modulo by zero is a runtime error and must not be folded
+        let proto = compile(mc, root.interned_strings, &b"return 1 % 0"[..]).unwrap();
+        assert!(proto
+            .opcodes
+            .iter()
+            .any(|op| matches!(op, OpCode::ModCC { .. })));
+    });
+}
+
+#[test]
+fn pow_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, &b"return 2 ^ 10, 2 ^ 3 ^ 2"[..]).unwrap();
+        match &proto.constants[..] {
+            &[Constant::Number(a), Constant::Number(b)] => {
+                assert_eq!(a, 1024.0);
+                assert_eq!(b, 512.0);
+            }
+            constants => panic!("unexpected constants {:?}", constants),
+        }
+    });
+}
+
+#[test]
+fn if_return_no_jump() {
+    fn count_jumps(proto: &FunctionProto) -> usize {
+        proto
+            .opcodes
+            .iter()
+            .filter(|op| matches!(op, OpCode::Jump { .. }))
+            .count()
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let optimized = compile(
+            mc,
+            root.interned_strings,
+            &br#"
+                local a, b = ...
+                if a then
+                    return 1
+                elseif b then
+                    return 2
+                else
+                    return 3
+                end
+            "#[..],
+        )
+        .unwrap();
+
+        let unoptimized = compile(
+            mc,
+            root.interned_strings,
+            &br#"
+                local a, b = ...
+                if a then
+                    do return 1 end
+                elseif b then
+                    do return 2 end
+                else
+                    do return 3 end
+                end
+            "#[..],
+        )
+        .unwrap();
+
+        assert_eq!(count_jumps(&optimized) + 2, count_jumps(&unoptimized));
+        assert_eq!(optimized.opcodes.len() + 2, unoptimized.opcodes.len());
+    });
+}
+
+#[test]
+fn always_emit_env() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let compile_with = |source: &[u8], options| {
+            let chunk = parse_chunk(source, |s| root.interned_strings.new_string(mc, s)).unwrap();
+            compile_chunk_with_options(mc, &chunk, options)
+                .unwrap()
+                .proto
+        };
+
+        let lazy = compile_with(b"return 1", CompilerOptions::default());
+        assert!(lazy.upvalues.is_empty());
+
+        let always = CompilerOptions {
+            always_emit_env: true,
+            ..CompilerOptions::default()
+        };
+
+        let proto = compile_with(b"return 1", always);
+        assert_eq!(proto.upvalues, vec![UpValueDescriptor::Environment]);
+
+        let proto = compile_with(b"x = print; return _ENV", always);
+        assert_eq!(proto.upvalues, vec![UpValueDescriptor::Environment]);
+    });
+}
+
+#[test]
+fn proto_equality() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let source = &b"local function f(a) return function() return a end end return f(1)"[..];
+        let first = compile(mc, root.interned_strings, source).unwrap();
+        let second = compile(mc, root.interned_strings, source).unwrap();
+        assert_eq!(first, second);
+
+        let other = compile(
+            mc,
+            root.interned_strings,
+            &b"local function f(a) return function() return a, a end end return f(1)"[..],
+        )
+        .unwrap();
+        assert_ne!(first, other);
+
+        let proto = compile(mc, root.interned_strings, &b"return 1"[..]).unwrap();
+        assert_eq!(
+            proto,
+            FunctionProto {
+                fixed_params: 0,
+                has_varargs: true,
+                stack_size: 1,
+                constants: vec![Constant::Integer(1)],
+                opcodes: vec![
+                    OpCode::LoadConstant {
+                        dest: RegisterIndex(0),
+                        constant: ConstantIndex16(0),
+                    },
+                    OpCode::Return {
+                        start: RegisterIndex(0),
+                        count: VarCount::constant(1),
+                    },
+                    OpCode::Return {
+                        start: RegisterIndex(0),
+                        count: VarCount::constant(0),
+                    },
+                ],
+                packed_opcodes: vec![],
+                upvalues: vec![],
+                upvalue_names: vec![],
+                local_vars: vec![],
+                prototypes: vec![],
+            }
+        );
+    });
+}
+
+#[test]
+fn operand_constants_stay_in_8_bits() {
+    let mut source = String::from("local f, x = ...\n");
+    for i in 0..300 {
+        source.push_str(&format!("f(\"s{}\")\n", i));
+    }
+    for i in 0..50 {
+        source.push_str(&format!("x = x + {}\n", 1000 + i));
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+        let load_constants = proto
+            .opcodes
+            .iter()
+            .filter(|op| matches!(op, OpCode::LoadConstant { .. }))
+            .count();
+        assert_eq!(load_constants, 300);
+        assert_eq!(proto.constants[0], Constant::Integer(1000));
+    });
+}
+
+#[test]
+fn wide_load_constants() -> Result<(), Box<StaticError>> {
+    fn assignments(count: usize) -> String {
+        let mut source = String::from("local x\n");
+        for i in 0..count {
+            source.push_str(&format!("x = \"c{}\"\n", i));
+        }
+        source.push_str("return x\n");
+        source
+    }
+
+    let options = CompilerOptions {
+        max_constants: 300,
+        ..CompilerOptions::default()
+    };
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        compile_with_options(
+            mc,
+            root.interned_strings,
+            assignments(300).as_bytes(),
+            options,
+        )
+        .unwrap();
+        match compile_with_options(
+            mc,
+            root.interned_strings,
+            assignments(301).as_bytes(),
+            options,
+        ) {
+            Err(Error::CompilerError(CompilerError::Constants)) => {}
+            _ => panic!("expected a constant limit error"),
+        }
+    });
+
+    run(
+        move |mc, root| {
+            let proto = compile(mc, root.interned_strings, assignments(300).as_bytes())?;
+            let last_load = proto
+                .opcodes
+                .iter()
+                .rev()
+                .find_map(|op| match *op {
+                    OpCode::LoadConstant { constant, .. } => Some(constant),
+                    _ => None,
+                })
+                .unwrap();
+            assert_eq!(last_load, ConstantIndex16(299));
+            Ok(proto)
+        },
+        &[],
+        |values| {
+            assert!(matches!(values[..], [Value::String(s)] if s == b"c299"));
+        },
+    )?;
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn default_constant_limit() {
+    fn assignments(count: usize) -> String {
+        let mut source = String::from("local x\n");
+        for i in 0..count {
+            source.push_str(&format!("x = \"c{}\"\n", i));
+        }
+        source
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        compile(mc, root.interned_strings, assignments(1 << 16).as_bytes()).unwrap();
+        match compile(
+            mc,
+            root.interned_strings,
+            assignments((1 << 16) + 1).as_bytes(),
+        ) {
+            Err(Error::CompilerError(CompilerError::Constants)) => {}
+            _ => panic!("expected a constant limit error"),
+        }
+    });
+}
+
+#[test]
+fn negate_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"return -(-9223372036854775807 - 1), -9223372036854775808, -0.0, - -0.0"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            proto.constants,
+            vec![
+                Constant::Integer(i64::MIN),
+                Constant::Number(-9223372036854775808.0),
+                Constant::Number(-0.0),
+                Constant::Number(0.0),
+            ]
+        );
+    });
+}
+
+#[test]
+fn signed_zero_constants() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"return 0.0, -0.0, 0.0, -0.0, 0.0 / 0.0, 0.0 / 0.0"[..],
+        )
+        .unwrap();
+        assert_eq!(proto.constants.len(), 3);
+        assert_eq!(proto.constants[0], Constant::Number(0.0));
+        assert_eq!(proto.constants[1], Constant::Number(-0.0));
+        assert_ne!(proto.constants[0], proto.constants[1]);
+        // Constants are compared bit for bit, so NaN is equal to itself.
+        assert_eq!(proto.constants[2], proto.constants[2]);
+
+        let constants = proto.constants.iter().collect::<HashSet<_>>();
+        assert_eq!(constants.len(), 3);
+    });
+}
+
+#[test]
+fn nan_comparison_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &br#"
+                return 0.0 / 0.0 == 0.0 / 0.0, 0.0 / 0.0 ~= 0.0 / 0.0,
+                    0.0 / 0.0 < 1, 0.0 / 0.0 <= 1, 0.0 / 0.0 > 1, 0.0 / 0.0 >= 1,
+                    1 < 0.0 / 0.0, 1 <= 0.0 / 0.0, 1 > 0.0 / 0.0, 1 >= 0.0 / 0.0
+            "#[..],
+        )
+        .unwrap();
+
+        let bools = proto
+            .opcodes
+            .iter()
+            .filter_map(|op| match op {
+                OpCode::LoadBool { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut expected = vec![false; 10];
+        expected[1] = true;
+        assert_eq!(bools, expected);
+        assert!(!proto.opcodes.iter().any(|op| matches!(
+            op,
+            OpCode::EqCC { .. } | OpCode::LessCC { .. } | OpCode::LessEqCC { .. }
+        )));
+    });
+}
+
+#[test]
+fn mixed_comparison_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &br#"
+                return 9007199254740993 == 9007199254740992.0, 9007199254740992 == 9007199254740992.0,
+                    9007199254740992.0 < 9007199254740993, 9007199254740993 <= 9007199254740992.0,
+                    1 < 2.5, 2.0 == 2, 2 >= 2.0, 3 > 2.5
+            "#[..],
+        )
+        .unwrap();
+
+        let bools = proto
+            .opcodes
+            .iter()
+            .filter_map(|op| match op {
+                OpCode::LoadBool { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(bools, [false, true, true, false, true, true, true, true]);
+    });
+}
+
+#[test]
+fn constant_table_length_folding() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let folded = compile(mc, root.interned_strings, &b"return #{1, 2, 3}"[..]).unwrap();
+        assert!(folded.constants.contains(&Constant::Integer(3)));
+        assert!(!folded
+            .opcodes
+            .iter()
+            .any(|op| matches!(op, OpCode::NewTable { .. } | OpCode::Length { .. })));
+
+        for source in &[
+            "return #{1, nil, 3}",
+            "return #{1, 2, [4] = 4}",
+            "local x = ... return #{1, x}",
+            "return #{1, ...}",
+        ] {
+            let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+            assert!(proto
+                .opcodes
+                .iter()
+                .any(|op| matches!(op, OpCode::Length { .. })));
+        }
+    });
+}
+
+#[test]
+fn empty_statements() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let plain = compile(mc, root.interned_strings, &b"local x = 1 return x"[..]).unwrap();
+        let with_semicolons = compile(
+            mc,
+            root.interned_strings,
+            &b";;local x = 1;; ;return x;"[..],
+        )
+        .unwrap();
+        assert_eq!(plain, with_semicolons);
+    });
+}
+
+#[test]
+fn return_semicolon() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        for (plain, with_semicolon) in &[
+            ("return", "return;"),
+            ("return 1, 2", "return 1, 2;"),
+            ("do return end return 1", "do return; end return 1;"),
+        ] {
+            let plain = compile(mc, root.interned_strings, plain.as_bytes()).unwrap();
+            let with_semicolon =
+                compile(mc, root.interned_strings, with_semicolon.as_bytes()).unwrap();
+            assert_eq!(plain, with_semicolon);
+        }
+
+        for source in &["return 1;;", "return; return", "do return 1; x = 1 end"] {
+            assert!(compile(mc, root.interned_strings, source.as_bytes()).is_err());
+        }
+    });
+}
+
+#[test]
+fn opcode_limit() {
+    fn assignments(count: usize) -> String {
+        let mut source = String::from("local x\n");
+        for _ in 0..count {
+            source.push_str("x = 1\n");
+        }
+        source
+    }
+
+    // One opcode for the local, one per assignment, and the final return.
+    let options = CompilerOptions {
+        max_opcodes: 64,
+        ..CompilerOptions::default()
+    };
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let output = compile_with_options(
+            mc,
+            root.interned_strings,
+            assignments(62).as_bytes(),
+            options,
+        )
+        .unwrap();
+        assert_eq!(output.proto.opcodes.len(), 64);
+        match compile_with_options(
+            mc,
+            root.interned_strings,
+            assignments(63).as_bytes(),
+            options,
+        ) {
+            Err(Error::CompilerError(CompilerError::OpCodes)) => {}
+            _ => panic!("expected an opcode limit error"),
+        }
+    });
+}
+
+#[test]
+#[ignore]
+fn default_opcode_limit() {
+    let mut source = String::from("local x\n");
+    for _ in 0..(1 << 20) {
+        source.push_str("x = 1\n");
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(
+        |mc, root| match compile(mc, root.interned_strings, source.as_bytes()) {
+            Err(Error::CompilerError(CompilerError::OpCodes)) => {}
+            _ => panic!("expected an opcode limit error"),
+        },
+    );
+}
+
+#[test]
+fn argument_and_return_limits() {
+    fn list(count: usize) -> String {
+        vec!["1"; count].join(", ")
+    }
+    fn names(count: usize) -> String {
+        (0..count)
+            .map(|i| format!("a{}", i))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    let too_many_arguments = [format!("f({})", list(255)), format!("t:m({})", list(254))];
+    let too_many_returns = [
+        format!("return {}", list(255)),
+        format!("local {} = f()", names(255)),
+    ];
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        assert!(compile(
+            mc,
+            root.interned_strings,
+            format!("f({})", list(254)).as_bytes()
+        )
+        .is_ok());
+
+        for source in &too_many_arguments {
+            match compile(mc, root.interned_strings, source.as_bytes()) {
+                Err(Error::CompilerError(CompilerError::Arguments)) => {}
+                _ => panic!("expected an argument limit error"),
+            }
+        }
+        for source in &too_many_returns {
+            match compile(mc, root.interned_strings, source.as_bytes()) {
+                Err(Error::CompilerError(CompilerError::Returns)) => {}
+                _ => panic!("expected a return limit error"),
+            }
+        }
+    });
+}
+
+#[test]
+fn unused_local_warnings() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let source = br#"
+            local a, b, _c = 1, 2, 3
+            local function f(unused_param)
+                local d = a
+                return function() return d end
+            end
+            for i = 1, 10 do end
+            do
+                local e
+            end
+            return f
+        "#;
+        let chunk = parse_chunk(&source[..], |s| root.interned_strings.new_string(mc, s)).unwrap();
+
+        let warnings = compile_chunk_with_options(
+            mc,
+            &chunk,
+            CompilerOptions {
+                collect_warnings: true,
+                ..CompilerOptions::default()
+            },
+        )
+        .unwrap()
+        .warnings;
+        let unused = warnings
+            .iter()
+            .filter_map(|w| match w {
+                CompilerWarning::UnusedLocal(name) => Some(name.as_bytes()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(unused, vec![&b"i"[..], &b"e"[..], &b"b"[..]]);
+
+        let warnings = compile_chunk_with_options(mc, &chunk, CompilerOptions::default())
+            .unwrap()
+            .warnings;
+        assert!(warnings.is_empty());
+    });
+}
+
+#[test]
+fn assigned_local_warnings() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let source = br#"
+            local x
+            x = 1
+            local y = 2
+            local function f() y = 3 end
+            local z
+            z = 4
+            return f, z
+        "#;
+        let chunk = parse_chunk(&source[..], |s| root.interned_strings.new_string(mc, s)).unwrap();
+
+        let warnings = compile_chunk_with_options(
+            mc,
+            &chunk,
+            CompilerOptions {
+                collect_warnings: true,
+                ..CompilerOptions::default()
+            },
+        )
+        .unwrap()
+        .warnings;
+        let unused = warnings
+            .iter()
+            .filter_map(|w| match w {
+                CompilerWarning::UnusedLocal(name) => Some(name.as_bytes()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // Assigning to a local, even from a closure, does not count as using it.
+        assert_eq!(unused, vec![&b"y"[..], &b"x"[..]]);
+    });
+}
+
+#[test]
+fn argument_count_warnings() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let source = br#"
+            local function f(a) return a end
+            f(1, 2, 3)
+            f()
+            f(1)
+            f(...)
+
+            local function g(...) end
+            g(1, 2)
+
+            local function h(a) end
+            h = print
+            h(1, 2)
+
+            local function i(a) end
+            local function j() i = print end
+            i(1, 2)
+
+            local k = function(a) end
+            k(1, 2)
+
+            print(1, 2)
+            return f(1, 2)
+        "#;
+        let chunk = parse_chunk(&source[..], |s| root.interned_strings.new_string(mc, s)).unwrap();
+
+        let warnings = compile_chunk_with_options(
+            mc,
+            &chunk,
+            CompilerOptions {
+                collect_warnings: true,
+                ..CompilerOptions::default()
+            },
+        )
+        .unwrap()
+        .warnings;
+        let counts = warnings
+            .iter()
+            .filter_map(|w| match w {
+                CompilerWarning::ArgumentCount {
+                    name,
+                    parameters,
+                    arguments,
+                } => Some((name.as_bytes(), *parameters, *arguments)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            vec![(&b"f"[..], 1, 3), (&b"f"[..], 1, 0), (&b"f"[..], 1, 2)]
+        );
+    });
+}
+
+#[test]
+fn unknown_global_warnings() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let source = br#"
+            result = print(string.len("abc"), prnt, strng.len)
+            function defined() end
+            function string.extra() end
+            local print = nil
+            return print
+        "#;
+        let chunk = parse_chunk(&source[..], |s| root.interned_strings.new_string(mc, s)).unwrap();
+
+        let known_globals = ["print", "string"]
+            .iter()
+            .map(|name| name.as_bytes().to_vec())
+            .collect::<HashSet<_>>();
+        let warnings = compile_chunk_with_options(
+            mc,
+            &chunk,
+            CompilerOptions {
+                collect_warnings: true,
+                known_globals: Some(&known_globals),
+                ..CompilerOptions::default()
+            },
+        )
+        .unwrap()
+        .warnings;
+        let unknown = warnings
+            .iter()
+            .filter_map(|w| match w {
+                CompilerWarning::UnknownGlobal(name) => Some(name.as_bytes()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(unknown, vec![&b"prnt"[..], &b"strng"[..]]);
+
+        for options in &[
+            CompilerOptions {
+                collect_warnings: true,
+                ..CompilerOptions::default()
+            },
+            CompilerOptions {
+                known_globals: Some(&known_globals),
+                ..CompilerOptions::default()
+            },
+        ] {
+            let warnings = compile_chunk_with_options(mc, &chunk, *options)
+                .unwrap()
+                .warnings;
+            assert!(warnings.is_empty());
+        }
+    });
+}
+
+#[test]
+fn local_nil_coalescing() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        for (source, dest, count) in &[
+            ("local a, b, c return a, b, c", 0, 3),
+            ("local a, b, c = 1 return a, b, c", 1, 2),
+        ] {
+            let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+            let nils = proto
+                .opcodes
+                .iter()
+                .filter_map(|op| match *op {
+                    OpCode::LoadNil { dest, count } => Some((dest.0, count)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(nils, vec![(*dest, *count)], "{}", source);
+        }
+    });
+}
+
+#[test]
+fn constant_while_conditions() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local f = ... while false do f() end while nil do f() end"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            proto.opcodes,
+            vec![
+                OpCode::VarArgs {
+                    dest: RegisterIndex(0),
+                    count: VarCount::constant(1),
+                },
+                OpCode::Return {
+                    start: RegisterIndex(0),
+                    count: VarCount::constant(0),
+                },
+            ]
+        );
+
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local f = ... while true do f() end"[..],
+        )
+        .unwrap();
+        assert!(!proto.opcodes.iter().any(|op| matches!(
+            op,
+            OpCode::Test { .. } | OpCode::TestSet { .. } | OpCode::LoadBool { .. }
+        )));
+        let jumps = proto
+            .opcodes
+            .iter()
+            .filter_map(|op| match *op {
+                OpCode::Jump { offset, .. } => Some(offset),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // The only jump is the one back to the start of the body, just after the `VarArgs`.
+        assert_eq!(jumps, vec![-3]);
+
+        // The body of a loop that never runs is still checked for errors.
+        for source in &[
+            "while false do goto nowhere end",
+            "while nil do goto skip local y ::skip:: y = 1 end",
+            "while false do ::l:: ::l:: end",
+        ] {
+            assert!(
+                compile(mc, root.interned_strings, source.as_bytes()).is_err(),
+                "{}",
+                source
+            );
+        }
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"while false do break end ::top:: while false do goto top end"[..],
+        )
+        .unwrap();
+        assert_eq!(proto.opcodes.len(), 1);
+    });
+}
+
+#[test]
+fn tail_calls() {
+    fn has_tail_call(proto: &FunctionProto) -> bool {
+        proto
+            .opcodes
+            .iter()
+            .any(|op| matches!(op, OpCode::TailCall { .. }))
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local f, x = ... return f(x)"[..],
+        )
+        .unwrap();
+        assert!(has_tail_call(&proto));
+
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local f, x = ... return f(x), 1"[..],
+        )
+        .unwrap();
+        assert!(!has_tail_call(&proto));
+        assert!(proto
+            .opcodes
+            .iter()
+            .any(|op| matches!(op, OpCode::Call { .. })));
+
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local obj, x = ... return obj:m(x)"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            proto.opcodes[1..],
+            [
+                OpCode::SelfC {
+                    base: RegisterIndex(2),
+                    table: RegisterIndex(0),
+                    key: ConstantIndex8(0),
+                },
+                OpCode::Move {
+                    dest: RegisterIndex(4),
+                    source: RegisterIndex(1),
+                },
+                OpCode::TailCall {
+                    func: RegisterIndex(2),
+                    args: VarCount::constant(2),
+                },
+                OpCode::Return {
+                    start: RegisterIndex(0),
+                    count: VarCount::constant(0),
+                },
+            ]
+        );
+    });
+}
+
+#[test]
+fn field_chain_registers() {
+    let chain = (0..30).map(|i| format!(".f{}", i)).collect::<String>();
+    let source = format!("local a = ... a{} = a{}", chain, chain);
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+        assert!(proto.stack_size <= 3);
+    });
+}
+
+#[test]
+fn empty_for_range() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let empty = compile(mc, root.interned_strings, &b""[..]).unwrap();
+        for source in &[
+            &b"for i = 5, 1 do error() end"[..],
+            &b"for i = 1, 5, -1 do error() end"[..],
+        ] {
+            let proto = compile(mc, root.interned_strings, *source).unwrap();
+            assert_eq!(proto.opcodes, empty.opcodes);
+        }
+
+        for source in &[
+            &b"for i = 1, 1 do error() end"[..],
+            &b"for i = 5, 1, 0 do error() end"[..],
+            &b"for i = 5.0, 1 do error() end"[..],
+        ] {
+            let proto = compile(mc, root.interned_strings, *source).unwrap();
+            assert!(proto
+                .opcodes
+                .iter()
+                .any(|op| matches!(op, OpCode::NumericForPrep { .. })));
+        }
+    });
+}
+
+#[test]
+fn long_elseif_chain() {
+    const BRANCHES: usize = 2000;
+
+    let mut source = String::from("local x = ... local r ");
+    for i in 0..BRANCHES {
+        if i != 0 {
+            source.push_str("else");
+        }
+        source.push_str(&format!("if x == {} then r = {} ", i, i));
+    }
+    source.push_str("end return r");
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+        verify(&proto).unwrap();
+
+        // The first instruction after the if statement moves `r` into place for the return.
+        let end = proto
+            .opcodes
+            .iter()
+            .position(|op| matches!(op, OpCode::Move { .. }))
+            .unwrap();
+        let targets = proto
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter_map(|(pc, op)| match op {
+                OpCode::Jump { offset, .. } => Some((pc as isize + 1 + *offset as isize) as usize),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Every branch but the last jumps to the end once it is done, as does the final failed
+        // condition, and every other failed condition jumps to the next clause.
+        assert_eq!(targets.iter().filter(|&&t| t == end).count(), BRANCHES);
+        assert_eq!(targets.len(), BRANCHES * 2 - 1);
+    });
+}
+
+#[test]
+fn nesting_too_deep() {
+    fn compile_source(source: &str, max_expression_depth: usize) -> Result<(), CompilerError> {
+        let mut lua = Lua::new();
+        lua.mutate(|mc, root| {
+            let chunk = parse_chunk(source.as_bytes(), |s| {
+                root.interned_strings.new_string(mc, s)
+            })
+            .unwrap();
+            let options = CompilerOptions {
+                max_expression_depth,
+                ..CompilerOptions::default()
+            };
+            compile_chunk_with_options(mc, &chunk, options).map(|_| ())
+        })
+    }
+
+    fn chain(count: usize, op: &str) -> String {
+        format!("local x = ... return x{}", op.repeat(count))
+    }
+
+    let default_depth = CompilerOptions::default().max_expression_depth;
+    for op in &["+x", ".a", "[x]", "()", ":m()", " and x", " < x"] {
+        match compile_source(&chain(100_000, op), default_depth) {
+            Err(CompilerError::NestingTooDeep) => {}
+            res => panic!("expected nesting error for {:?}, got {:?}", op, res),
+        }
+        compile_source(&chain(190, op), default_depth).unwrap();
+        match compile_source(&chain(100, op), 50) {
+            Err(CompilerError::NestingTooDeep) => {}
+            res => panic!("expected nesting error for {:?}, got {:?}", op, res),
+        }
+    }
+
+    // Chains of constants fold away as they are compiled and never nest.
+    compile_source(&format!("return 1{}", "+1".repeat(100_000)), default_depth).unwrap();
+    compile_source(
+        &format!("return {}1{}", "(".repeat(150), ")".repeat(150)),
+        default_depth,
+    )
+    .unwrap();
+}
+
+#[test]
+fn table_access_operands() {
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    enum Operand {
+        Register(u8),
+        Constant(u8),
+        UpValue(u8),
+    }
+
+    // Returns the table, key and (for sets) value operands of a table access opcode.
+    fn decode(op: &OpCode) -> Option<(Operand, Operand, Option<Operand>)> {
+        use Operand::*;
+        Some(match *op {
+            OpCode::GetTableR { table, key, .. } => (Register(table.0), Register(key.0), None),
+            OpCode::GetTableC { table, key, .. } => (Register(table.0), Constant(key.0), None),
+            OpCode::GetUpTableR { table, key, .. } => (UpValue(table.0), Register(key.0), None),
+            OpCode::GetUpTableC { table, key, .. } => (UpValue(table.0), Constant(key.0), None),
+            OpCode::SetTableRR { table, key, value } => {
+                (Register(table.0), Register(key.0), Some(Register(value.0)))
+            }
+            OpCode::SetTableRC { table, key, value } => {
+                (Register(table.0), Register(key.0), Some(Constant(value.0)))
+            }
+            OpCode::SetTableCR { table, key, value } => {
+                (Register(table.0), Constant(key.0), Some(Register(value.0)))
+            }
+            OpCode::SetTableCC { table, key, value } => {
+                (Register(table.0), Constant(key.0), Some(Constant(value.0)))
+            }
+            OpCode::SetUpTableRR { table, key, value } => {
+                (UpValue(table.0), Register(key.0), Some(Register(value.0)))
+            }
+            OpCode::SetUpTableRC { table, key, value } => {
+                (UpValue(table.0), Register(key.0), Some(Constant(value.0)))
+            }
+            OpCode::SetUpTableCR { table, key, value } => {
+                (UpValue(table.0), Constant(key.0), Some(Register(value.0)))
+            }
+            OpCode::SetUpTableCC { table, key, value } => {
+                (UpValue(table.0), Constant(key.0), Some(Constant(value.0)))
+            }
+            _ => return None,
+        })
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        for &upvalue_table in &[false, true] {
+            for &constant_key in &[false, true] {
+                for &value in &[None, Some(false), Some(true)] {
+                    let key = if constant_key { "'key'" } else { "k" };
+                    let statement = match value {
+                        None => format!("return t[{}]", key),
+                        Some(false) => format!("t[{}] = v", key),
+                        Some(true) => format!("t[{}] = 'value'", key),
+                    };
+                    // The table is either the first parameter of the inner function or an upvalue,
+                    // and the key and value parameters always follow it.
+                    let source = if upvalue_table {
+                        format!("local t = ... return function(k, v) {} end", statement)
+                    } else {
+                        format!("return function(t, k, v) {} end", statement)
+                    };
+
+                    let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+                    let proto = &proto.prototypes[0];
+                    let constant = |s: &[u8]| {
+                        let index = proto
+                            .constants
+                            .iter()
+                            .position(|c| match c {
+                                Constant::String(c) => c.as_bytes() == s,
+                                _ => false,
+                            })
+                            .unwrap();
+                        Operand::Constant(index as u8)
+                    };
+                    let params = if upvalue_table { 0 } else { 1 };
+
+                    let expected = (
+                        if upvalue_table {
+                            Operand::UpValue(0)
+                        } else {
+                            Operand::Register(0)
+                        },
+                        if constant_key {
+                            constant(b"key")
+                        } else {
+                            Operand::Register(params)
+                        },
+                        value.map(|constant_value| {
+                            if constant_value {
+                                constant(b"value")
+                            } else {
+                                Operand::Register(params + 1)
+                            }
+                        }),
+                    );
+
+                    let decoded = proto.opcodes.iter().filter_map(decode).collect::<Vec<_>>();
+                    assert_eq!(decoded, vec![expected], "{}", source);
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn function_arity() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &br#"
+                local u = 1
+                return function(a, b, ...) return u end, function(a) return a + u end
+            "#[..],
+        )
+        .unwrap();
+        assert_eq!(proto.fixed_params(), 0);
+        assert!(proto.has_varargs());
+        assert_eq!(proto.upvalue_count(), 0);
+
+        let inner = &proto.prototypes[0];
+        assert_eq!(inner.fixed_params(), 2);
+        assert!(inner.has_varargs());
+        assert_eq!(inner.upvalue_count(), 1);
+        assert!(inner.stack_size() >= 2);
+
+        let inner = &proto.prototypes[1];
+        assert_eq!(inner.fixed_params(), 1);
+        assert!(!inner.has_varargs());
+        assert_eq!(inner.upvalue_count(), 1);
+        assert!(inner.stack_size() >= 2);
+
+        let closure = Closure::new(mc, proto, Some(root.globals)).unwrap();
+        assert_eq!(closure.0.proto.fixed_params(), 0);
+    });
+}
+
+#[test]
+fn malformed_ast_is_internal_error() {
+    use luster::parser::{Block, Chunk, ForStatement, Statement};
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let name = root.interned_strings.new_string(mc, b"i");
+        let chunk = Chunk {
+            block: Block {
+                statements: vec![Statement::For(ForStatement::Generic {
+                    names: vec![name],
+                    arguments: Vec::new(),
+                    body: Block {
+                        statements: Vec::new(),
+                        return_statement: None,
+                    },
+                })],
+                return_statement: None,
+            },
+        };
+        match compile_chunk_with_options(mc, &chunk, CompilerOptions::default()) {
+            Err(CompilerError::Internal(_)) => {}
+            res => panic!("expected internal error, got {:?}", res.map(|_| ())),
+        }
+    });
+}
+
+#[test]
+fn shared_string_constants() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let string_constants = |proto: &FunctionProto| {
+            proto
+                .constants
+                .iter()
+                .filter_map(|c| match c {
+                    Constant::String(s) => Some(s.as_bytes().as_ptr()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let a = compile(
+            mc,
+            root.interned_strings,
+            &b"return { name = 'a shared string constant' }"[..],
+        )
+        .unwrap();
+        let b = compile(
+            mc,
+            root.interned_strings,
+            &b"local t = ... return t.name, 'a shared string constant'"[..],
+        )
+        .unwrap();
+        assert_eq!(string_constants(&a), string_constants(&b));
+    });
+}
+
+#[test]
+fn compiler_cache() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let string_constant = |proto: &FunctionProto| match proto.constants[..] {
+            [Constant::String(s)] => s.as_bytes().as_ptr(),
+            _ => panic!("expected a single string constant"),
+        };
+
+        // Parse without interning, so that every chunk gets its own copy of the string.
+        let parse = |source: &[u8]| parse_chunk(source, |s| luster::String::new(mc, s)).unwrap();
+        let options = CompilerOptions::default;
+
+        let a = compile_chunk_with_options(mc, &parse(b"return 'shared'"), options()).unwrap();
+        let b = compile_chunk_with_options(mc, &parse(b"return 'shared'"), options()).unwrap();
+        assert_ne!(string_constant(&a.proto), string_constant(&b.proto));
+
+        let mut cache = CompilerCache::new();
+        let mut compile = |source: &[u8]| {
+            compile_chunk_with_cache(mc, &parse(source), options(), &mut cache)
+                .unwrap()
+                .proto
+        };
+        let a = compile(b"return 'shared'");
+        let b = compile(b"return 'sha' .. 'red'");
+        let c = compile(b"local t = ... return t.shared");
+        assert_eq!(string_constant(&a), string_constant(&b));
+        assert_eq!(string_constant(&a), string_constant(&c));
+    });
+}
+
+#[test]
+fn global_opcodes() -> Result<(), Box<StaticError>> {
+    const SOURCE: &[u8] = br#"
+        counter = 0
+        for i = 1, 10 do
+            counter = counter + i
+        end
+        local function f()
+            total = counter * 2
+        end
+        f()
+        do
+            local _ENV = { total = 1 }
+            total = total + 1
+        end
+        return total == 110 and counter == 55
+    "#;
+
+    run(
+        move |mc, root| {
+            let chunk = parse_chunk(SOURCE, |s| root.interned_strings.new_string(mc, s))?;
+            let options = CompilerOptions {
+                global_opcodes: true,
+                ..CompilerOptions::default()
+            };
+            let proto = compile_chunk_with_options(mc, &chunk, options)?.proto;
+
+            let ops = proto
+                .opcodes
+                .iter()
+                .chain(&proto.prototypes[0].opcodes)
+                .collect::<Vec<_>>();
+            assert!(ops.iter().any(|op| matches!(op, OpCode::GetGlobal { .. })));
+            assert!(ops.iter().any(|op| matches!(op, OpCode::SetGlobal { .. })));
+            // `_ENV` is a local inside the `do` block, so it goes through the table opcodes.
+            assert!(ops.iter().any(|op| matches!(op, OpCode::GetTableC { .. })));
+            assert!(!ops
+                .iter()
+                .any(|op| matches!(op, OpCode::GetUpTableC { .. } | OpCode::SetUpTableCR { .. })));
+
+            Ok(proto)
+        },
+        &[],
+        |b| assert_eq!(b, vec![Value::Boolean(true)]),
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn expression_function() -> Result<(), Box<StaticError>> {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        for source in &["x = 1", "a + b end", "return a", ""] {
+            match compile_expression(mc, root.interned_strings, source.as_bytes()) {
+                Err(Error::ParserError(_)) => {}
+                res => panic!("expected parser error for {:?}, got {:?}", source, res),
+            }
+        }
+    });
+
+    run(
+        move |mc, root| {
+            root.globals
+                .raw_set(mc, luster::String::new_static(b"a"), 1)?;
+            root.globals
+                .raw_set(mc, luster::String::new_static(b"b"), 3)?;
+            compile_expression(mc, root.interned_strings, &b"a + b * 2"[..])
+        },
+        &[],
+        |values| assert_eq!(values, vec![Value::Integer(7)]),
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn owned_proto_round_trip() -> Result<(), Box<StaticError>> {
+    const SOURCE: &[u8] = br#"
+        local prefix = "a string constant longer than thirty two bytes: "
+        local function greet(name)
+            return prefix .. name .. "!"
+        end
+        return greet("lua"), 1, 2.5, true, nil
+    "#;
+
+    let owned: OwnedProto = Lua::new().mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, SOURCE).unwrap();
+        let owned = proto.to_owned_proto();
+        assert_eq!(FunctionProto::reify(mc, &owned), proto);
+        owned
+    });
+    assert!(owned.constants.contains(&OwnedConstant::String(
+        b"a string constant longer than thirty two bytes: ".to_vec()
+    )));
+    assert_eq!(owned.prototypes.len(), 1);
+    assert!(owned.prototypes[0]
+        .constants
+        .contains(&OwnedConstant::String(b"!".to_vec())));
+
+    run(
+        move |mc, _| Ok(FunctionProto::reify(mc, &owned)),
+        &[],
+        |values| {
+            assert_eq!(values.len(), 5);
+            assert!(
+                matches!(values[0], Value::String(s) if s == b"a string constant longer than thirty two bytes: lua!")
+            );
+            assert_eq!(
+                values[1..],
+                [
+                    Value::Integer(1),
+                    Value::Number(2.5),
+                    Value::Boolean(true),
+                    Value::Nil
+                ]
+            );
+        },
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn implicit_return() -> Result<(), Box<StaticError>> {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        match compile_with_options(
+            mc,
+            root.interned_strings,
+            &b"1 + 2"[..],
+            CompilerOptions::default(),
+        ) {
+            Err(Error::ParserError(ParserError::Unexpected { .. })) => {}
+            res => panic!("expected a syntax error, got {:?}", res),
+        }
+    });
+
+    run(
+        move |mc, root| {
+            let options = CompilerOptions {
+                implicit_return: true,
+                ..CompilerOptions::default()
+            };
+            let proto =
+                compile_with_options(mc, root.interned_strings, &b"1 + 2"[..], options)?.proto;
+            Ok(proto)
+        },
+        &[],
+        |values| assert_eq!(values, vec![Value::Integer(3)]),
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn desugared_methods() -> Result<(), Box<StaticError>> {
+    let source = std::fs::read("tests/running/methods.lua").unwrap();
+
+    run(
+        move |mc, root| {
+            let chunk = parse_chunk(&source[..], |s| root.interned_strings.new_string(mc, s))?;
+            let chunk = desugar_chunk(chunk, luster::String::new_static(b"self"));
+            Ok(compile_chunk_with_options(mc, &chunk, CompilerOptions::default())?.proto)
+        },
+        &[],
+        |values| assert_eq!(values, vec![Value::Boolean(true)]),
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn table_size_hints() {
+    let array = (0..300)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let source = format!(
+        "local k = ... return {{}}, {{1, 2, 3}}, {{a = 1, [k] = 2}}, {{1, a = 2, 3, [k] = 4, 5}}, {{{}}}",
+        array
+    );
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+        let hints = proto
+            .opcodes
+            .iter()
+            .filter_map(|op| match *op {
+                OpCode::NewTable {
+                    array_size,
+                    map_size,
+                    ..
+                } => Some((array_size, map_size)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hints, vec![(0, 0), (3, 0), (0, 2), (3, 2), (255, 0)]);
+    });
+}
+
+#[test]
+fn call_stack_size() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        // `f` itself, a copy of `f` in the call frame, then 20 arguments.
+        let args = (1..=20)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            format!("local f = ... f({})", args).as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(proto.stack_size, 22);
+
+        // Returns are placed starting at the function register, so `f` plus 10 results.
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local f = ... do local a, b, c, d, e, f, g, h, i, j = f() end return 1"[..],
+        )
+        .unwrap();
+        assert_eq!(proto.stack_size, 11);
+
+        // A method call needs both the method and the table in the call frame.
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local t = ... t:m(1, 2, 3)"[..],
+        )
+        .unwrap();
+        assert_eq!(proto.stack_size, 6);
+    });
+}
+
+#[test]
+fn and_or_idiom() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local x, y, z = ... local r = x and y or z"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            &proto.opcodes[1..proto.opcodes.len() - 1],
+            &[
+                OpCode::Test {
+                    value: RegisterIndex(0),
+                    is_true: true,
+                },
+                OpCode::Jump {
+                    offset: 2,
+                    close_upvalues: Opt254::none(),
+                },
+                OpCode::TestSet {
+                    dest: RegisterIndex(3),
+                    value: RegisterIndex(1),
+                    is_true: false,
+                },
+                OpCode::Jump {
+                    offset: 1,
+                    close_upvalues: Opt254::none(),
+                },
+                OpCode::Move {
+                    dest: RegisterIndex(3),
+                    source: RegisterIndex(2),
+                },
+            ][..]
+        );
+        assert_eq!(proto.stack_size, 4);
+    });
+}
+
+#[test]
+fn short_circuit_if_conditions() {
+    fn test(value: u8, is_true: bool) -> OpCode {
+        OpCode::Test {
+            value: RegisterIndex(value),
+            is_true,
+        }
+    }
+
+    fn jump(offset: i16) -> OpCode {
+        OpCode::Jump {
+            offset,
+            close_upvalues: Opt254::none(),
+        }
+    }
+
+    let assign = OpCode::Move {
+        dest: RegisterIndex(0),
+        source: RegisterIndex(1),
+    };
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        for (condition, opcodes) in &[
+            (
+                "a and b",
+                vec![test(0, true), jump(3), test(1, true), jump(1), assign],
+            ),
+            (
+                "a or b",
+                vec![test(0, false), jump(2), test(1, true), jump(1), assign],
+            ),
+            ("not a", vec![test(0, false), jump(1), assign]),
+            (
+                "not (a and b)",
+                vec![test(0, true), jump(2), test(1, false), jump(1), assign],
+            ),
+        ] {
+            let source = format!("local a, b = ... if {} then a = b end", condition);
+            let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+            assert_eq!(
+                &proto.opcodes[1..proto.opcodes.len() - 1],
+                &opcodes[..],
+                "{}",
+                condition
+            );
+            assert_eq!(proto.stack_size, 2);
+        }
+    });
+}
+
+#[test]
+fn method_call_chain() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local a = ... local r = a:b():c()"[..],
+        )
+        .unwrap();
+        let call = OpCode::Call {
+            func: RegisterIndex(1),
+            args: VarCount::constant(1),
+            returns: VarCount::constant(1),
+        };
+        match &proto.opcodes[1..proto.opcodes.len() - 1] {
+            [OpCode::SelfC {
+                base: RegisterIndex(1),
+                table: RegisterIndex(0),
+                ..
+            }, first_call, OpCode::SelfC {
+                base: RegisterIndex(1),
+                table: RegisterIndex(1),
+                ..
+            }, second_call]
+                if *first_call == call && *second_call == call => {}
+            opcodes => panic!("unexpected method chain opcodes {:?}", opcodes),
+        }
+        // Each link reuses the registers of the previous one.
+        assert_eq!(proto.stack_size, 3);
+    });
+}
+
+#[test]
+fn numeric_for_step_one() {
+    fn loop_opcode(source: &str) -> OpCode {
+        let mut lua = Lua::new();
+        lua.mutate(|mc, root| {
+            let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+            *proto
+                .opcodes
+                .iter()
+                .find(|op| {
+                    matches!(
+                        op,
+                        OpCode::NumericForLoop { .. } | OpCode::NumericForIncLoop { .. }
+                    )
+                })
+                .unwrap()
+        })
+    }
+
+    for source in &[
+        "local n = ... for i = 1, n do end",
+        "local n = ... for i = n, 10, 1 do end",
+    ] {
+        match loop_opcode(source) {
+            OpCode::NumericForIncLoop { .. } => {}
+            op => panic!("expected increment loop for {:?}, got {:?}", source, op),
+        }
+    }
+    for source in &[
+        "local n = ... for i = 1, n, 2 do end",
+        "local n = ... for i = 10, n, -1 do end",
+        "local n = ... for i = 1, n, 1.0 do end",
+        "local n, s = ... for i = 1, n, s do end",
+    ] {
+        match loop_opcode(source) {
+            OpCode::NumericForLoop { .. } => {}
+            op => panic!("expected general loop for {:?}, got {:?}", source, op),
+        }
+    }
+}
+
+#[test]
+fn compile_stats() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let chunk = parse_chunk(
+            &br#"
+                local a = 1
+                local function f(x)
+                    local function g() return a + x end
+                    return g
+                end
+                return f, "s"
+            "#[..],
+            |s| root.interned_strings.new_string(mc, s),
+        )
+        .unwrap();
+
+        let output = compile_chunk_with_options(
+            mc,
+            &chunk,
+            CompilerOptions {
+                collect_stats: true,
+                ..CompilerOptions::default()
+            },
+        )
+        .unwrap();
+        let f = &output.proto.prototypes[0];
+        let g = &f.prototypes[0];
+        assert_eq!(
+            output.stats,
+            Some(CompileStats {
+                opcodes: output.proto.opcodes.len() + f.opcodes.len() + g.opcodes.len(),
+                constants: output.proto.constants.len() + f.constants.len() + g.constants.len(),
+                prototypes: 3,
+                max_stack_size: output.proto.stack_size.max(f.stack_size).max(g.stack_size),
+                upvalues: 3,
+            })
+        );
+        assert_eq!(output.stats, Some(CompileStats::new(&output.proto)));
+
+        let output = compile_chunk_with_options(mc, &chunk, CompilerOptions::default()).unwrap();
+        assert_eq!(output.stats, None);
+    });
+}
+
+#[test]
+fn call_result_without_move() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        // The call is made at the stack top, which is exactly where a new local is placed.
+        for source in &[
+            "local f = ... local x = f()",
+            "local t = ... local x = t:m()",
+            "local f = ... local x, y = 1, f()",
+        ] {
+            let proto = compile(mc, root.interned_strings, source.as_bytes()).unwrap();
+            let call = proto
+                .opcodes
+                .iter()
+                .position(|op| matches!(op, OpCode::Call { .. }))
+                .unwrap();
+            assert!(
+                !matches!(proto.opcodes[call + 1], OpCode::Move { .. }),
+                "spurious move in {:?}",
+                source
+            );
+        }
+
+        // An existing local is live during the call, so the result must be moved into it.
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local f, x = ... x = f(x) return x"[..],
+        )
+        .unwrap();
+        let call = proto
+            .opcodes
+            .iter()
+            .position(|op| matches!(op, OpCode::Call { .. }))
+            .unwrap();
+        assert!(matches!(
+            proto.opcodes[call + 1],
+            OpCode::Move {
+                dest: RegisterIndex(1),
+                ..
+            }
+        ));
+    });
+}
+
+#[test]
+fn proto_listing() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let source = &br#"
+            local t = {}
+            function t.f(a)
+                return a + 2.5, t.name
+            end
+            return t.f(1) == "x"
+        "#[..];
+        let proto = compile(mc, root.interned_strings, source).unwrap();
+        verify(&proto).unwrap();
+        let listing = proto.to_listing();
+        assert_eq!(
+            listing,
+            compile(mc, root.interned_strings, source)
+                .unwrap()
+                .to_listing()
+        );
+        assert_eq!(listing.opcodes.len(), proto.opcodes.len());
+
+        let string = |s: &'static [u8]| Constant::String(luster::String::new_static(s));
+        fn constants<'gc>(listing: &Listing<'gc>) -> Vec<Constant<'gc>> {
+            listing
+                .opcodes
+                .iter()
+                .flat_map(|op| op.constants.iter().cloned())
+                .collect()
+        }
+        assert!(constants(&listing).contains(&string(b"x")));
+        assert!(constants(&listing).contains(&string(b"f")));
+
+        let inner = &listing.prototypes[0];
+        assert_eq!(inner.fixed_params, 1);
+        assert!(constants(inner).contains(&Constant::Number(2.5)));
+        assert!(constants(inner).contains(&string(b"name")));
+
+        let other = compile(
+            mc,
+            root.interned_strings,
+            &b"local t = {} function t.f(a) return a + 3.5, t.name end return t.f(1) == 'x'"[..],
+        )
+        .unwrap();
+        assert_ne!(listing, other.to_listing());
+    });
+}
+
+#[test]
+fn configurable_limits() {
+    fn compile_source(source: &str, options: CompilerOptions) -> Result<(), CompilerError> {
+        let mut lua = Lua::new();
+        lua.mutate(|mc, root| {
+            let chunk = parse_chunk(source.as_bytes(), |s| {
+                root.interned_strings.new_string(mc, s)
+            })
+            .unwrap();
+            compile_chunk_with_options(mc, &chunk, options).map(|_| ())
+        })
+    }
+
+    let locals = "local a, b, c, d = 1, 2, 3, 4 return a, b, c, d";
+    compile_source(locals, CompilerOptions::default()).unwrap();
+    let registers = CompilerOptions {
+        max_registers: 4,
+        ..CompilerOptions::default()
+    };
+    match compile_source(locals, registers) {
+        Err(CompilerError::Registers) => {}
+        res => panic!("expected register limit error, got {:?}", res),
+    }
+
+    let constants = "return 'a', 'b', 'c'";
+    compile_source(constants, CompilerOptions::default()).unwrap();
+    let max_constants = CompilerOptions {
+        max_constants: 2,
+        ..CompilerOptions::default()
+    };
+    match compile_source(constants, max_constants) {
+        Err(CompilerError::Constants) => {}
+        res => panic!("expected constant limit error, got {:?}", res),
+    }
+
+    let upvalues = "local a, b local function f() return a, b end";
+    compile_source(upvalues, CompilerOptions::default()).unwrap();
+    let max_upvalues = CompilerOptions {
+        max_upvalues: 1,
+        ..CompilerOptions::default()
+    };
+    match compile_source(upvalues, max_upvalues) {
+        Err(CompilerError::UpValues { name, depth }) => {
+            assert_eq!(&*name, b"b");
+            assert_eq!(depth, 1);
+        }
+        res => panic!("expected upvalue limit error, got {:?}", res),
+    }
+}
+
+#[test]
+fn upvalue_names() {
+    const SOURCE: &[u8] = br#"
+        local a, b = 1, 2
+        return function() return b, a, print end
+    "#;
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let options = CompilerOptions {
+            debug_info: true,
+            ..CompilerOptions::default()
+        };
+        let proto = compile_with_options(mc, root.interned_strings, SOURCE, options)
+            .unwrap()
+            .proto;
+        assert_eq!(proto.upvalue_name(UpValueIndex(0)), Some(&b"_ENV"[..]));
+        assert_eq!(proto.upvalue_name(UpValueIndex(1)), None);
+        let inner = &proto.prototypes[0];
+        assert_eq!(inner.upvalue_name(UpValueIndex(0)), Some(&b"b"[..]));
+        assert_eq!(inner.upvalue_name(UpValueIndex(1)), Some(&b"a"[..]));
+        assert_eq!(inner.upvalue_name(UpValueIndex(2)), Some(&b"_ENV"[..]));
+        assert_eq!(FunctionProto::reify(mc, &proto.to_owned_proto()), proto);
+
+        let proto = compile(mc, root.interned_strings, SOURCE).unwrap();
+        assert!(proto.upvalue_names.is_empty());
+        assert_eq!(proto.prototypes[0].upvalue_name(UpValueIndex(0)), None);
+    });
+}
+
+#[test]
+fn local_names() {
+    const SOURCE: &[u8] = br#"
+        local function f(x)
+            do
+                local a = x
+                print(a)
+            end
+            do
+                local b = x
+                print(b)
+            end
+        end
+    "#;
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let options = CompilerOptions {
+            debug_info: true,
+            ..CompilerOptions::default()
+        };
+        let proto = compile_with_options(mc, root.interned_strings, SOURCE, options)
+            .unwrap()
+            .proto;
+        assert_eq!(proto.local_vars.len(), 1);
+        // A local function is in scope in its own body, so from before its closure is created.
+        assert_eq!(proto.local_name(RegisterIndex(0), 0), Some(&b"f"[..]));
+        assert_eq!(proto.local_name(RegisterIndex(1), 0), None);
+
+        let f = &proto.prototypes[0];
+        let names = f
+            .local_vars
+            .iter()
+            .map(|local| (local.name.as_bytes(), local.reg))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (&b"x"[..], RegisterIndex(0)),
+                (&b"a"[..], RegisterIndex(1)),
+                (&b"b"[..], RegisterIndex(1)),
+            ]
+        );
+        let (a, b) = (&f.local_vars[1], &f.local_vars[2]);
+        assert!(a.start_pc < a.end_pc && a.end_pc <= b.start_pc && b.start_pc < b.end_pc);
+        for pc in 0..f.opcodes.len() {
+            assert_eq!(f.local_name(RegisterIndex(0), pc), Some(&b"x"[..]));
+        }
+        assert_eq!(f.local_name(RegisterIndex(1), a.start_pc), Some(&b"a"[..]));
+        assert_eq!(f.local_name(RegisterIndex(1), b.start_pc), Some(&b"b"[..]));
+        assert_eq!(f.local_name(RegisterIndex(1), b.end_pc), None);
+        assert_eq!(FunctionProto::reify(mc, &proto.to_owned_proto()), proto);
+
+        let proto = compile(mc, root.interned_strings, SOURCE).unwrap();
+        assert!(proto.prototypes[0].local_vars.is_empty());
+        assert_eq!(proto.prototypes[0].local_name(RegisterIndex(0), 0), None);
+    });
+}
+
+#[test]
+fn packed_opcodes() {
+    const SOURCE: &[u8] = b"local t = {} for i = 1, 10 do t[i] = function() return i end end";
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let options = CompilerOptions {
+            packed_opcodes: true,
+            ..CompilerOptions::default()
+        };
+        let proto = compile_with_options(mc, root.interned_strings, SOURCE, options)
+            .unwrap()
+            .proto;
+        for proto in iter::once(&proto).chain(proto.prototypes.iter().map(|p| &**p)) {
+            let decoded = proto
+                .packed_opcodes
+                .iter()
+                .map(|packed| packed.decode())
+                .collect::<Vec<_>>();
+            assert_eq!(decoded, proto.opcodes);
+        }
+
+        let proto = compile(mc, root.interned_strings, SOURCE).unwrap();
+        assert!(proto.packed_opcodes.is_empty());
+    });
+}
+
+#[test]
+fn backward_goto() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"local i = 0 ::top:: i = i + 1 if i < 5 then goto top end return i"[..],
+        )
+        .unwrap();
+        // The label is at the `AddRC` just after `i` is initialized.
+        assert!(matches!(proto.opcodes[1], OpCode::AddRC { .. }));
+        let (index, offset) = proto
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, op)| match *op {
+                OpCode::Jump { offset, .. } if offset < 0 => Some((i, offset)),
+                _ => None,
+            })
+            .next()
+            .unwrap();
+        assert_eq!(index as isize + 1 + offset as isize, 1);
+    });
+}
+
+#[test]
+fn repeat_break_and_continue() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(
+            mc,
+            root.interned_strings,
+            &b"
+                local i = 0
+                repeat
+                    i = i + 1
+                    if i == 2 then goto continue end
+                    if i == 4 then break end
+                    ::continue::
+                until i > 5
+                return i
+            "[..],
+        )
+        .unwrap();
+        let targets = proto
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, op)| match *op {
+                OpCode::Jump { offset, .. } => {
+                    Some((i, (i as isize + 1 + offset as isize) as usize))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // The only backward jump is the one taken when the `until` condition is false.
+        let until_jump = targets.iter().find(|&&(i, t)| t <= i).unwrap().0;
+        let condition = until_jump - 1;
+        assert!(matches!(proto.opcodes[condition], OpCode::LessEqRC { .. }));
+        // `goto continue` lands on the `until` condition so that it is still evaluated...
+        assert!(targets.iter().any(|&(_, t)| t == condition));
+        // ...while `break` skips past the conditional jump entirely.
+        assert!(targets.iter().any(|&(_, t)| t == until_jump + 1));
+    });
+}
+
+#[test]
+fn top_level_varargs() -> Result<(), Box<StaticError>> {
+    const SOURCE: &[u8] = b"local first = ... return first, ...";
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let proto = compile(mc, root.interned_strings, &b"return ..."[..]).unwrap();
+        assert!(proto.has_varargs);
+        assert_eq!(proto.fixed_params, 0);
+        assert_eq!(
+            proto.opcodes[0],
+            OpCode::VarArgs {
+                dest: RegisterIndex(0),
+                count: VarCount::variable(),
+            }
+        );
+    });
+
+    run(
+        move |mc, root| compile(mc, root.interned_strings, SOURCE),
+        &[1, 2],
+        |values| {
+            assert_eq!(
+                values,
+                vec![Value::Integer(1), Value::Integer(1), Value::Integer(2)]
+            );
+        },
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn upvalue_overflow() {
+    // 300 distinct locals across two enclosing functions, all captured by the innermost one.
+    let outer = (0..200).map(|i| format!("o{}", i)).collect::<Vec<_>>();
+    let middle = (0..100).map(|i| format!("m{}", i)).collect::<Vec<_>>();
+    let source = format!(
+        "local {} return function() local {} return function() return {}, {} end end",
+        outer.join(", "),
+        middle.join(", "),
+        outer.join(", "),
+        middle.join(", "),
+    );
+
+    let mut lua = Lua::new();
+    lua.mutate(
+        |mc, root| match compile(mc, root.interned_strings, source.as_bytes()) {
+            Err(Error::CompilerError(error @ CompilerError::UpValues { .. })) => {
+                let message = error.to_string();
+                match error {
+                    CompilerError::UpValues { name, depth } => {
+                        assert_eq!(&*name, b"m56");
+                        assert_eq!(depth, 2);
+                    }
+                    _ => unreachable!(),
+                }
+                assert!(message.contains("'m56'"), "{}", message);
+            }
+            res => panic!("expected upvalue overflow, got {:?}", res),
+        },
+    );
+}
+
+#[test]
+fn constant_if_conditions() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let compile_opcodes = |source: &str| {
+            compile(mc, root.interned_strings, source.as_bytes())
+                .unwrap()
+                .opcodes
+                .clone()
+        };
+
+        let only_b = compile_opcodes("local f = ... f(2)");
+        for source in &[
+            "local f = ... if false then f(1) elseif true then f(2) else f(3) end",
+            "local f = ... if nil then f(1) elseif false then f(4) elseif 1 then f(2) end",
+            "local f = ... if true then f(2) elseif f then f(1) else f(3) end",
+            "local f = ... if false then f(1) else f(2) end",
+        ] {
+            assert_eq!(compile_opcodes(source), only_b, "{}", source);
+        }
+        assert_eq!(
+            compile_opcodes("local f = ... if false then f(1) end"),
+            compile_opcodes("local f = ...")
+        );
+
+        // Branches before a constant true condition are still tested.
+        let opcodes =
+            compile_opcodes("local f = ... if f then f(1) elseif true then f(2) else f(3) end");
+        assert_eq!(
+            opcodes,
+            compile_opcodes("local f = ... if f then f(1) else f(2) end")
+        );
+    });
+}
+
+#[test]
+fn constant_if_dead_code_errors() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        for source in &[
+            "if false then goto nowhere end",
+            "if true then else goto nowhere end",
+            "if true then elseif x then goto nowhere end",
+            "if false then break end",
+            "if nil then local x ::l:: ::l:: end",
+        ] {
+            assert!(
+                compile(mc, root.interned_strings, source.as_bytes()).is_err(),
+                "{}",
+                source
+            );
+        }
+
+        // Jumps out of the discarded code still find their targets, and the code is still
+        // discarded.
+        let opcodes = |source: &str| {
+            compile(mc, root.interned_strings, source.as_bytes())
+                .unwrap()
+                .opcodes
+                .clone()
+        };
+        assert_eq!(
+            opcodes("local f = ... while f() do if false then break end f(1) end"),
+            opcodes("local f = ... while f() do f(1) end")
+        );
+        assert_eq!(
+            opcodes("local f = ... if false then goto done end f(1) ::done::"),
+            opcodes("local f = ... f(1) ::done::")
+        );
+    });
+}
@@ -1,5 +1,10 @@
+use std::error::Error as StdError;
+
 use gc_sequence::{self as sequence, SequenceExt, SequenceResultExt};
-use luster::{compile, Closure, Error, Function, Lua, StaticError, ThreadSequence};
+use luster::{
+    compile, compile_chunk, parse_chunk, Closure, CompilerError, Error, Function, LexerError, Lua,
+    ParserError, StaticError, ThreadSequence,
+};
 
 #[test]
 fn error_unwind() -> Result<(), Box<StaticError>> {
@@ -52,3 +57,58 @@ fn error_unwind() -> Result<(), Box<StaticError>> {
 
     Ok(())
 }
+
+#[test]
+fn compile_error_phases() {
+    fn compile_source(source: &str) -> StaticError {
+        let mut lua = Lua::new();
+        lua.mutate(|mc, root| {
+            compile(mc, root.interned_strings, source.as_bytes())
+                .map(|_| ())
+                .unwrap_err()
+                .to_static()
+        })
+    }
+
+    match compile_source("local a = 1 $") {
+        StaticError::LexerError(LexerError::UnexpectedCharacter(b'$')) => {}
+        err => panic!("expected lexer error, got {:?}", err),
+    }
+    match compile_source("local a = \"unfinished") {
+        StaticError::LexerError(LexerError::UnfinishedShortString(b'"')) => {}
+        err => panic!("expected lexer error, got {:?}", err),
+    }
+    match compile_source("local = 1") {
+        StaticError::ParserError(ParserError::Unexpected { .. }) => {}
+        err => panic!("expected parser error, got {:?}", err),
+    }
+    match compile_source("goto nowhere") {
+        StaticError::CompilerError(CompilerError::GotoInvalid) => {}
+        err => panic!("expected compiler error, got {:?}", err),
+    }
+
+    let err = compile_source("return 1 $");
+    assert!(err.to_string().starts_with("lexer error: "));
+}
+
+#[test]
+fn compiler_error_is_std_error() {
+    fn compile_source(source: &str) -> Result<(), Box<dyn StdError>> {
+        let mut lua = Lua::new();
+        lua.mutate(|mc, root| {
+            let chunk = parse_chunk(source.as_bytes(), |s| {
+                root.interned_strings.new_string(mc, s)
+            })?;
+            compile_chunk(mc, &chunk)?;
+            Ok(())
+        })
+    }
+
+    compile_source("return 1").unwrap();
+    let err = compile_source("goto nowhere").unwrap_err();
+    assert_eq!(err.to_string(), "goto target label not found");
+    match err.downcast_ref::<CompilerError>() {
+        Some(CompilerError::GotoInvalid) => {}
+        err => panic!("expected compiler error, got {:?}", err),
+    }
+}
@@ -1,6 +1,6 @@
 use std::f64;
 
-use luster::{Lexer, Token};
+use luster::{compile_chunk, parser::parse, Constant, Lexer, LexerError, LexerOptions, Lua, Token};
 
 fn test_tokens(source: &str, tokens: &[Token<Box<[u8]>>]) {
     let mut lexer = Lexer::new(source.as_bytes(), |s| s.to_vec().into_boxed_slice());
@@ -63,6 +63,29 @@ fn comments() {
     );
 }
 
+#[test]
+fn comment_at_end_of_input() {
+    test_tokens(
+        "x = 1 -- end of file",
+        &[name_token("x"), Token::Assign, Token::Integer(1)],
+    );
+    test_tokens("x --", &[name_token("x")]);
+    test_tokens("x --[", &[name_token("x")]);
+    test_tokens("x --[=", &[name_token("x")]);
+    test_tokens("x --[==x\ny", &[name_token("x"), name_token("y")]);
+    test_tokens("--", &[]);
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let tokens = Lexer::new(&b"return 1 -- no newline"[..], |s| {
+            root.interned_strings.new_string(mc, s)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        compile_chunk(mc, &parse(&tokens).unwrap()).unwrap();
+    });
+}
+
 #[test]
 fn long_string() {
     test_tokens(
@@ -100,6 +123,18 @@ fn short_string() {
     );
 }
 
+#[test]
+fn escaped_line_ends() {
+    test_tokens_lines(
+        "\"one\\\ntwo\" 'a\\\r\nb\\\rc\\\n\rd' x",
+        &[
+            (str_token("one\ntwo"), 0),
+            (str_token("a\nb\nc\nd"), 1),
+            (name_token("x"), 4),
+        ],
+    );
+}
+
 #[test]
 fn numerals() {
     test_tokens(
@@ -210,3 +245,150 @@ fn ops() {
         ],
     );
 }
+
+#[test]
+fn iterator() {
+    let lexer = Lexer::new(&b"local a = 'b'"[..], |s| s.to_vec().into_boxed_slice());
+    let tokens = lexer.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        tokens,
+        vec![Token::Local, name_token("a"), Token::Assign, str_token("b"),]
+    );
+
+    let mut lexer = Lexer::new(&b"a $ b"[..], |s| s.to_vec().into_boxed_slice());
+    assert_eq!(lexer.next().unwrap().unwrap(), name_token("a"));
+    assert!(lexer.next().unwrap().is_err());
+    assert!(lexer.next().is_none());
+}
+
+#[test]
+fn decimal_escapes() {
+    fn bytes_token(b: &[u8]) -> Token<Box<[u8]>> {
+        Token::String(b.to_vec().into_boxed_slice())
+    }
+
+    test_tokens(
+        r#" "\0" "\65" "\255" "\1234" "\12a" "\0019" "#,
+        &[
+            bytes_token(&[0]),
+            str_token("A"),
+            bytes_token(&[255]),
+            str_token("{4"),
+            bytes_token(b"\x0ca"),
+            bytes_token(b"\x019"),
+        ],
+    );
+
+    let mut lexer = Lexer::new(&br#""\256""#[..], |s| s.to_vec().into_boxed_slice());
+    match lexer.read_token() {
+        Err(LexerError::EscapeDecimalTooLarge) => {}
+        _ => panic!("expected decimal escape error"),
+    }
+}
+
+#[test]
+fn unicode_escapes() {
+    test_tokens(
+        r#" "\u{41}" "\u{00000041}" "\u{20AC}" "#,
+        &[str_token("A"), str_token("A"), str_token("\u{20AC}")],
+    );
+
+    // Escapes too large for 32 bits must not wrap around to a valid character.
+    for source in &[&br#""\u{110000}""#[..], &br#""\u{100000041}""#[..]] {
+        let mut lexer = Lexer::new(*source, |s| s.to_vec().into_boxed_slice());
+        match lexer.read_token() {
+            Err(LexerError::EscapeUnicodeInvalid) => {}
+            res => panic!("expected unicode escape error, got {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn non_ascii() {
+    test_tokens(
+        "-- commentaire en français ☃\n\
+         --[[ многострочный\n комментарий ]]\n\
+         \"héllo wörld ☃\" [[日本語\n テキスト]] '\u{1F600}'",
+        &[
+            str_token("héllo wörld ☃"),
+            str_token("日本語\n テキスト"),
+            str_token("\u{1F600}"),
+        ],
+    );
+
+    let mut lexer = Lexer::new("abcé".as_bytes(), |s| s.to_vec().into_boxed_slice());
+    assert_eq!(lexer.read_token().unwrap(), Some(name_token("abc")));
+    match lexer.read_token() {
+        Err(LexerError::UnexpectedCharacter(0xc3)) => {}
+        res => panic!("expected unexpected character error, got {:?}", res),
+    }
+}
+
+#[test]
+fn binary_literals() {
+    fn lex(source: &str, binary_literals: bool) -> Result<Vec<Token<Box<[u8]>>>, LexerError> {
+        Lexer::with_options(
+            source.as_bytes(),
+            |s| s.to_vec().into_boxed_slice(),
+            LexerOptions { binary_literals },
+        )
+        .collect()
+    }
+
+    assert_eq!(
+        lex("0b10 0B0111", false).unwrap(),
+        vec![
+            Token::Integer(0),
+            Token::Name(b"b10".to_vec().into_boxed_slice()),
+            Token::Integer(0),
+            Token::Name(b"B0111".to_vec().into_boxed_slice()),
+        ]
+    );
+
+    assert_eq!(
+        lex("0b10 0B0111 0b0", true).unwrap(),
+        vec![Token::Integer(2), Token::Integer(7), Token::Integer(0)]
+    );
+    assert_eq!(
+        lex(&format!("0b1{}", "0".repeat(63)), true).unwrap(),
+        vec![Token::Integer(i64::MIN)]
+    );
+    assert_eq!(
+        lex(&format!("0b1{}1", "0".repeat(63)), true).unwrap(),
+        vec![Token::Integer(1)]
+    );
+    assert_eq!(lex("0x10", true).unwrap(), vec![Token::Integer(16)]);
+
+    for bad in &["0b", "0b2", "0b102", "0bx"] {
+        match lex(bad, true) {
+            Err(LexerError::BadNumber) => {}
+            res => panic!("expected bad number for {:?}, got {:?}", bad, res),
+        }
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let tokens = Lexer::with_options(
+            &b"return 0b101 + 1"[..],
+            |s| root.interned_strings.new_string(mc, s),
+            LexerOptions {
+                binary_literals: true,
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let proto = compile_chunk(mc, &parse(&tokens).unwrap()).unwrap();
+        assert!(proto.constants.contains(&Constant::Integer(6)));
+    });
+}
+
+#[test]
+fn token_display() {
+    assert_eq!(Token::<&[u8]>::End.to_string(), "'end'");
+    assert_eq!(Token::<&[u8]>::Assign.to_string(), "'='");
+    assert_eq!(Token::<&[u8]>::Dots.to_string(), "'...'");
+    assert_eq!(Token::<&[u8]>::Integer(1).to_string(), "number");
+    assert_eq!(Token::<&[u8]>::Float(1.5).to_string(), "number");
+    assert_eq!(Token::String(&b"foo"[..]).to_string(), "string");
+    assert_eq!(Token::Name(&b"foo"[..]).to_string(), "identifier 'foo'");
+}
@@ -0,0 +1,236 @@
+use luster::{
+    ConstantIndex16, ConstantIndex8, OpCode, Opt254, PackedOpCode, PrototypeIndex, RegisterIndex,
+    UpValueIndex, VarCount,
+};
+
+#[test]
+fn jump_offset_mut() {
+    let mut jump = OpCode::Jump {
+        offset: 0,
+        close_upvalues: Opt254::none(),
+    };
+    *jump.jump_offset_mut().unwrap() = 5;
+    assert_eq!(
+        jump,
+        OpCode::Jump {
+            offset: 5,
+            close_upvalues: Opt254::none(),
+        }
+    );
+
+    let mut for_loop = OpCode::NumericForLoop {
+        base: RegisterIndex(0),
+        jump: -3,
+    };
+    assert_eq!(for_loop.jump_offset_mut(), Some(&mut -3));
+
+    let mut call = OpCode::Call {
+        func: RegisterIndex(0),
+        args: VarCount::constant(0),
+        returns: VarCount::constant(1),
+    };
+    assert_eq!(call.jump_offset_mut(), None);
+}
+
+// Operand values built from the bits of a seed.
+trait Sample {
+    fn sample(seed: u32) -> Self;
+}
+
+impl Sample for bool {
+    fn sample(seed: u32) -> bool {
+        seed & 1 != 0
+    }
+}
+
+impl Sample for u8 {
+    fn sample(seed: u32) -> u8 {
+        seed as u8
+    }
+}
+
+impl Sample for i16 {
+    fn sample(seed: u32) -> i16 {
+        seed as u16 as i16
+    }
+}
+
+impl Sample for RegisterIndex {
+    fn sample(seed: u32) -> RegisterIndex {
+        RegisterIndex(Sample::sample(seed))
+    }
+}
+
+impl Sample for ConstantIndex8 {
+    fn sample(seed: u32) -> ConstantIndex8 {
+        ConstantIndex8(Sample::sample(seed))
+    }
+}
+
+impl Sample for ConstantIndex16 {
+    fn sample(seed: u32) -> ConstantIndex16 {
+        ConstantIndex16(seed as u16)
+    }
+}
+
+impl Sample for UpValueIndex {
+    fn sample(seed: u32) -> UpValueIndex {
+        UpValueIndex(Sample::sample(seed))
+    }
+}
+
+impl Sample for PrototypeIndex {
+    fn sample(seed: u32) -> PrototypeIndex {
+        PrototypeIndex(Sample::sample(seed))
+    }
+}
+
+impl Sample for Opt254 {
+    fn sample(seed: u32) -> Opt254 {
+        Opt254::try_some(seed as u8).unwrap_or_else(Opt254::none)
+    }
+}
+
+impl Sample for VarCount {
+    fn sample(seed: u32) -> VarCount {
+        VarCount::try_constant(seed as u8).unwrap_or_else(VarCount::variable)
+    }
+}
+
+// Each field of an opcode gets different bits of the seed, so that swapped operands are caught.
+fn field<T: Sample>(seed: u32, index: u32) -> T {
+    T::sample(seed.rotate_left(5 * index))
+}
+
+#[rustfmt::skip]
+fn every_opcode(seed: u32) -> Vec<OpCode> {
+    vec![
+        OpCode::Move { dest: field(seed, 0), source: field(seed, 1) },
+        OpCode::LoadConstant { dest: field(seed, 0), constant: field(seed, 1) },
+        OpCode::LoadBool { dest: field(seed, 0), value: field(seed, 1), skip_next: field(seed, 2) },
+        OpCode::LoadNil { dest: field(seed, 0), count: field(seed, 1) },
+        OpCode::NewTable { dest: field(seed, 0), array_size: field(seed, 1), map_size: field(seed, 2) },
+        OpCode::GetTableR { dest: field(seed, 0), table: field(seed, 1), key: field(seed, 2) },
+        OpCode::GetTableC { dest: field(seed, 0), table: field(seed, 1), key: field(seed, 2) },
+        OpCode::SetTableRR { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::SetTableRC { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::SetTableCR { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::SetTableCC { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::GetUpTableR { dest: field(seed, 0), table: field(seed, 1), key: field(seed, 2) },
+        OpCode::GetUpTableC { dest: field(seed, 0), table: field(seed, 1), key: field(seed, 2) },
+        OpCode::SetUpTableRR { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::SetUpTableRC { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::SetUpTableCR { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::SetUpTableCC { table: field(seed, 0), key: field(seed, 1), value: field(seed, 2) },
+        OpCode::GetGlobal { dest: field(seed, 0), env: field(seed, 1), name: field(seed, 2) },
+        OpCode::SetGlobal { env: field(seed, 0), name: field(seed, 1), value: field(seed, 2) },
+        OpCode::Call { func: field(seed, 0), args: field(seed, 1), returns: field(seed, 2) },
+        OpCode::TailCall { func: field(seed, 0), args: field(seed, 1) },
+        OpCode::Return { start: field(seed, 0), count: field(seed, 1) },
+        OpCode::VarArgs { dest: field(seed, 0), count: field(seed, 1) },
+        OpCode::Jump { offset: field(seed, 0), close_upvalues: field(seed, 1) },
+        OpCode::Test { value: field(seed, 0), is_true: field(seed, 1) },
+        OpCode::TestSet { dest: field(seed, 0), value: field(seed, 1), is_true: field(seed, 2) },
+        OpCode::Closure { dest: field(seed, 0), proto: field(seed, 1) },
+        OpCode::NumericForPrep { base: field(seed, 0), jump: field(seed, 1) },
+        OpCode::NumericForLoop { base: field(seed, 0), jump: field(seed, 1) },
+        OpCode::NumericForIncLoop { base: field(seed, 0), jump: field(seed, 1) },
+        OpCode::GenericForCall { base: field(seed, 0), var_count: field(seed, 1) },
+        OpCode::GenericForLoop { base: field(seed, 0), jump: field(seed, 1) },
+        OpCode::SelfR { base: field(seed, 0), table: field(seed, 1), key: field(seed, 2) },
+        OpCode::SelfC { base: field(seed, 0), table: field(seed, 1), key: field(seed, 2) },
+        OpCode::Concat { dest: field(seed, 0), source: field(seed, 1), count: field(seed, 2) },
+        OpCode::GetUpValue { dest: field(seed, 0), source: field(seed, 1) },
+        OpCode::SetUpValue { dest: field(seed, 0), source: field(seed, 1) },
+        OpCode::Length { dest: field(seed, 0), source: field(seed, 1) },
+        OpCode::EqRR { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::EqRC { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::EqCR { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::EqCC { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessRR { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessRC { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessCR { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessCC { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessEqRR { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessEqRC { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessEqCR { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::LessEqCC { skip_if: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::Not { dest: field(seed, 0), source: field(seed, 1) },
+        OpCode::Minus { dest: field(seed, 0), source: field(seed, 1) },
+        OpCode::AddRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::AddRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::AddCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::AddCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::SubRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::SubRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::SubCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::SubCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::MulRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::MulRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::MulCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::MulCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::DivRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::DivRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::DivCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::DivCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::IDivRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::IDivRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::IDivCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::IDivCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ModRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ModRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ModCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ModCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::PowRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::PowRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::PowCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::PowCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitAndRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitAndRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitAndCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitAndCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitOrRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitOrRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitOrCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitOrCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitXorRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitXorRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitXorCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitXorCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftLeftRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftLeftRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftLeftCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftLeftCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftRightRR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftRightRC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftRightCR { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::ShiftRightCC { dest: field(seed, 0), left: field(seed, 1), right: field(seed, 2) },
+        OpCode::BitNot { dest: field(seed, 0), source: field(seed, 1) },
+    ]
+}
+
+#[test]
+fn packed_round_trip() {
+    // All operands zero, all operands at their maximum, the extremes of the signed jump offsets,
+    // and a spread of pseudo-random operands.
+    let mut seeds = vec![0, u32::MAX, 0x7fff_7fff, 0x8000_8000];
+    let mut seed = 1u32;
+    for _ in 0..1000 {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        seeds.push(seed);
+    }
+
+    for &seed in &seeds {
+        for opcode in every_opcode(seed) {
+            assert_eq!(PackedOpCode::encode(opcode).decode(), opcode);
+        }
+    }
+
+    let mut tags = every_opcode(0)
+        .into_iter()
+        .map(|opcode| PackedOpCode::encode(opcode).to_u32() & 0xff)
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    assert_eq!(tags.len(), every_opcode(0).len());
+}
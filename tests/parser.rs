@@ -1,8 +1,11 @@
+use luster::parser::visit::walk_function_call;
 use luster::parser::{
-    parse_chunk, Block, CallSuffix, Chunk, ConstructorField, Expression, FunctionCallStatement,
-    HeadExpression, PrimaryExpression, SimpleExpression, Statement, SuffixedExpression,
-    TableConstructor,
+    desugar_chunk, parse, parse_bytes, parse_chunk, parse_script, BinaryOperator, Block,
+    CallSuffix, Chunk, ConstructorField, Expression, FunctionCallStatement, HeadExpression,
+    ParserError, PrimaryExpression, SimpleExpression, Statement, SuffixedExpression,
+    TableConstructor, Visit,
 };
+use luster::Lexer;
 
 #[test]
 fn test_function_call() {
@@ -77,3 +80,278 @@ fn test_function_call() {
         }
     );
 }
+
+#[test]
+fn test_end_of_stream() {
+    fn parse(source: &str) -> Result<Chunk<Box<[u8]>>, ParserError> {
+        parse_bytes(source.as_bytes())
+    }
+
+    match parse("function f()") {
+        Err(ParserError::EndOfStream { .. }) => {}
+        res => panic!("expected end of stream error, got {:?}", res),
+    }
+    match parse("if x then") {
+        Err(ParserError::EndOfStream { .. }) => {}
+        res => panic!("expected end of stream error, got {:?}", res),
+    }
+    match parse("return 1 +") {
+        Err(ParserError::EndOfStream { .. }) => {}
+        res => panic!("expected end of stream error, got {:?}", res),
+    }
+    match parse("return 1 1") {
+        Err(ParserError::Unexpected { .. }) => {}
+        res => panic!("expected unexpected token error, got {:?}", res),
+    }
+    match parse("x = 1 end") {
+        Err(ParserError::Unexpected { .. }) => {}
+        res => panic!("expected unexpected token error, got {:?}", res),
+    }
+}
+
+#[test]
+fn test_error_messages() {
+    let err = parse_bytes(b"x = 1 end").unwrap_err();
+    assert!(
+        err.to_string().contains("unexpected 'end'"),
+        "bad message: {}",
+        err
+    );
+    let err = parse_bytes(b"local 1 = 2").unwrap_err();
+    assert!(
+        err.to_string().contains("unexpected number"),
+        "bad message: {}",
+        err
+    );
+}
+
+#[test]
+fn test_parse_tokens() {
+    let source = b"local a = {1, 2} return a[1] + #a";
+    let tokens = Lexer::new(&source[..], |s| s.to_vec().into_boxed_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(parse(&tokens).unwrap(), parse_bytes(&source[..]).unwrap());
+
+    match parse(&tokens[..tokens.len() - 1]) {
+        Err(ParserError::EndOfStream { .. }) => {}
+        res => panic!("expected end of stream error, got {:?}", res),
+    }
+    match parse_bytes(b"return $") {
+        Err(ParserError::LexerError(_)) => {}
+        res => panic!("expected lexer error, got {:?}", res),
+    }
+}
+
+#[test]
+fn test_concat_right_associative() {
+    fn name(n: &str) -> Expression<Box<[u8]>> {
+        Expression {
+            head: Box::new(HeadExpression::Simple(SimpleExpression::Suffixed(
+                SuffixedExpression {
+                    primary: PrimaryExpression::Name(n.as_bytes().to_vec().into_boxed_slice()),
+                    suffixes: vec![],
+                },
+            ))),
+            tail: vec![],
+        }
+    }
+
+    fn binop(
+        left: Expression<Box<[u8]>>,
+        op: BinaryOperator,
+        right: Expression<Box<[u8]>>,
+    ) -> Expression<Box<[u8]>> {
+        let mut left = left;
+        left.tail.push((op, right));
+        left
+    }
+
+    fn returned(source: &str) -> Expression<Box<[u8]>> {
+        parse_bytes(source.as_bytes())
+            .unwrap()
+            .block
+            .return_statement
+            .unwrap()
+            .returns
+            .remove(0)
+    }
+
+    let concat = BinaryOperator::Concat;
+    assert_eq!(
+        returned("return a .. b .. c"),
+        binop(name("a"), concat, binop(name("b"), concat, name("c")))
+    );
+    assert_eq!(
+        returned("return a .. b .. c .. d"),
+        binop(
+            name("a"),
+            concat,
+            binop(name("b"), concat, binop(name("c"), concat, name("d")))
+        )
+    );
+    assert_eq!(
+        returned("return a + b .. c"),
+        binop(
+            binop(name("a"), BinaryOperator::Add, name("b")),
+            concat,
+            name("c")
+        )
+    );
+    assert_eq!(
+        returned("return a .. b + c"),
+        binop(
+            name("a"),
+            concat,
+            binop(name("b"), BinaryOperator::Add, name("c"))
+        )
+    );
+    assert_eq!(
+        returned("return a .. b == c"),
+        binop(
+            binop(name("a"), concat, name("b")),
+            BinaryOperator::Equal,
+            name("c")
+        )
+    );
+}
+
+#[test]
+fn test_invalid_assignment_targets() {
+    for source in &[
+        "f() = 1",
+        "a.b() = 1",
+        "a:m() = 1",
+        "(a) = 1",
+        "a, f() = 1, 2",
+        "f(), a = 1, 2",
+    ] {
+        match parse_bytes(source.as_bytes()) {
+            Err(ParserError::AssignToExpression) => {}
+            res => panic!(
+                "expected assign to expression error for {:?}, got {:?}",
+                source, res
+            ),
+        }
+    }
+
+    parse_bytes(b"f().a = 1").unwrap();
+    parse_bytes(b"(a).b, f()[1] = 1, 2").unwrap();
+}
+
+#[test]
+fn test_parse_script() {
+    let script = |source: &str| parse_script(source.as_bytes(), |s| s.to_vec());
+
+    let chunk = script("x = 1; f(); x + 1;").unwrap();
+    assert_eq!(chunk.block.statements.len(), 2);
+    assert_eq!(chunk.block.return_statement.unwrap().returns.len(), 1);
+
+    let chunk = script("f() g()").unwrap();
+    assert_eq!(chunk.block.statements.len(), 1);
+    assert!(chunk.block.return_statement.is_some());
+
+    let chunk = script("local x = 1 do x = 2 end").unwrap();
+    assert!(chunk.block.return_statement.is_none());
+
+    // Only the end of the chunk itself may be a bare expression.
+    assert!(script("do 1 + 2 end").is_err());
+    for source in &["1 + 2 x = 1", "a.b x = 1", "1 + 2 = 3"] {
+        match script(source) {
+            Err(ParserError::ExpressionNotStatement) => {}
+            res => panic!(
+                "expected expression not statement error for {:?}, got {:?}",
+                source, res
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_desugar_function_statements() {
+    let desugar = |source: &str| {
+        desugar_chunk(
+            parse_bytes(source.as_bytes()).unwrap(),
+            b"self".to_vec().into_boxed_slice(),
+        )
+    };
+
+    for (sugared, plain) in &[
+        ("function f(x) return x end", "f = function(x) return x end"),
+        ("function a.b.c(...) end", "a.b.c = function(...) end"),
+        ("function a:m(x) end", "a.m = function(self, x) end"),
+        ("function a.b:m() end", "a.b.m = function(self) end"),
+        (
+            "local t = {f = function() function t.g() end end} \
+             if x then while y do function a:m() end end end",
+            "local t = {f = function() t.g = function() end end} \
+             if x then while y do a.m = function(self) end end end",
+        ),
+    ] {
+        let plain = parse_bytes(plain.as_bytes()).unwrap();
+        assert_eq!(desugar(sugared), plain, "{}", sugared);
+        assert_eq!(
+            desugar_chunk(plain.clone(), b"self".to_vec().into_boxed_slice()),
+            plain
+        );
+    }
+}
+
+#[test]
+fn test_visit_function_calls() {
+    struct CallCounter(usize);
+
+    impl<S> Visit<S> for CallCounter {
+        fn visit_function_call(&mut self, call: &CallSuffix<S>) {
+            self.0 += 1;
+            walk_function_call(self, call);
+        }
+    }
+
+    let chunk = parse_bytes(
+        br#"
+            print(f(1), g())
+            local t = { h(), k = obj:m() }
+            local function inner()
+                return x.y.z(w())
+            end
+            if a then
+                repeat b() until c()
+            end
+            local s = "print(not_a_call)"
+        "#,
+    )
+    .unwrap();
+
+    let mut counter = CallCounter(0);
+    counter.visit_chunk(&chunk);
+    assert_eq!(counter.0, 9);
+}
+
+#[test]
+fn test_visit_names() {
+    struct NameCollector(Vec<Box<[u8]>>);
+
+    impl Visit<Box<[u8]>> for NameCollector {
+        fn visit_name(&mut self, name: &Box<[u8]>) {
+            self.0.push(name.clone());
+        }
+    }
+
+    let chunk = parse_bytes(
+        br#"
+            function f() return g end
+            function a.b:m() end
+            local function l() end
+            x, t.k = y[z]
+        "#,
+    )
+    .unwrap();
+
+    let mut collector = NameCollector(Vec::new());
+    collector.visit_chunk(&chunk);
+    assert_eq!(
+        collector.0.iter().map(|n| &n[..]).collect::<Vec<_>>(),
+        vec![&b"f"[..], b"g", b"a", b"x", b"t", b"y", b"z"]
+    );
+}
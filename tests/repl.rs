@@ -0,0 +1,143 @@
+use gc_sequence::{self as sequence, SequenceExt, SequenceResultExt};
+use luster::{Error, Function, Lua, ReplCompiler, StaticError, ThreadSequence, Value};
+
+const REPL_CLOSURE: i64 = 1;
+
+// Compiles and runs a single REPL input, returning its integer results and `None` for any other
+// value.
+fn run(
+    lua: &mut Lua,
+    repl: &mut ReplCompiler,
+    source: &str,
+) -> Result<Vec<Option<i64>>, StaticError> {
+    lua.mutate(|mc, root| {
+        let proto = repl
+            .compile(mc, root.interned_strings, source.as_bytes())
+            .map_err(Error::to_static)?;
+        let previous = match root.registry.raw_get(REPL_CLOSURE) {
+            Value::Function(Function::Closure(closure)) => Some(closure),
+            _ => None,
+        };
+        let closure = repl.closure(mc, proto, root.globals, previous);
+        root.registry
+            .raw_set(mc, REPL_CLOSURE, Function::Closure(closure))
+            .map_err(|e| Error::from(e).to_static())?;
+        Ok(())
+    })?;
+
+    lua.sequence(|root| {
+        sequence::from_fn_with(root, |_, root| match root.registry.raw_get(REPL_CLOSURE) {
+            Value::Function(function) => Ok(function),
+            _ => unreachable!(),
+        })
+        .and_chain_with(root, |mc, root, function| {
+            Ok(ThreadSequence::call_function(
+                mc,
+                root.main_thread,
+                function,
+                &[],
+            )?)
+        })
+        .map_ok(|values| {
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Integer(i) => Some(*i),
+                    _ => None,
+                })
+                .collect()
+        })
+        .map_err(Error::to_static)
+        .boxed()
+    })
+}
+
+#[test]
+fn repl_locals() -> Result<(), StaticError> {
+    let mut lua = Lua::new();
+    let mut repl = ReplCompiler::new();
+
+    run(&mut lua, &mut repl, "local x = 1")?;
+    run(
+        &mut lua,
+        &mut repl,
+        "local function inc() x = x + 1 return x end",
+    )?;
+    assert_eq!(run(&mut lua, &mut repl, "inc()")?, vec![Some(2)]);
+    assert_eq!(run(&mut lua, &mut repl, "x")?, vec![Some(2)]);
+    assert_eq!(run(&mut lua, &mut repl, "return x + 1")?, vec![Some(3)]);
+
+    // Locals are shared with closures that capture them in the same input.
+    run(
+        &mut lua,
+        &mut repl,
+        "local n = 0 function f() n = n + 1 end",
+    )?;
+    run(&mut lua, &mut repl, "f() f()")?;
+    assert_eq!(run(&mut lua, &mut repl, "n")?, vec![Some(2)]);
+
+    // A new declaration shadows the old local, which closures keep referring to.
+    run(&mut lua, &mut repl, "local x = 10")?;
+    assert_eq!(
+        run(&mut lua, &mut repl, "inc(), x")?,
+        vec![Some(3), Some(10)]
+    );
+
+    // Only locals of the top-level block persist.
+    run(&mut lua, &mut repl, "local a, b = 4, 5 do local z = 6 end")?;
+    assert_eq!(
+        run(&mut lua, &mut repl, "a, b, z")?,
+        vec![Some(4), Some(5), None]
+    );
+
+    // A failed input declares nothing.
+    assert!(run(&mut lua, &mut repl, "local y = 1 local = 2").is_err());
+    assert_eq!(run(&mut lua, &mut repl, "y")?, vec![None]);
+    match run(&mut lua, &mut repl, "for i = 1, 2 do") {
+        Err(StaticError::ParserError(_)) => {}
+        res => panic!("expected incomplete input error, got {:?}", res),
+    }
+
+    assert_eq!(
+        repl.locals().collect::<Vec<_>>(),
+        vec![&b"x"[..], b"inc", b"n", b"a", b"b"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn repl_redeclared_locals() -> Result<(), StaticError> {
+    let mut lua = Lua::new();
+    let mut repl = ReplCompiler::new();
+
+    run(
+        &mut lua,
+        &mut repl,
+        "local x = 0 function first() return x end",
+    )?;
+    // Redeclaring a local reuses its upvalue slot, so any number of inputs may do it.
+    for i in 1..=300 {
+        run(&mut lua, &mut repl, &format!("local x = {}", i))?;
+    }
+    run(&mut lua, &mut repl, "local y = x")?;
+    assert_eq!(
+        run(&mut lua, &mut repl, "x, y, first()")?,
+        vec![Some(300), Some(300), Some(0)]
+    );
+    assert_eq!(repl.locals().collect::<Vec<_>>(), vec![&b"x"[..], b"y"]);
+
+    // Within a single input every declaration is still distinct.
+    run(
+        &mut lua,
+        &mut repl,
+        "local x = 1 function second() return x end local x = 2",
+    )?;
+    assert_eq!(
+        run(&mut lua, &mut repl, "x, second(), first()")?,
+        vec![Some(2), Some(1), Some(0)]
+    );
+    assert_eq!(repl.locals().collect::<Vec<_>>(), vec![&b"x"[..], b"y"]);
+
+    Ok(())
+}
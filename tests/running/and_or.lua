@@ -0,0 +1,48 @@
+local function ternary(c, t, f)
+    return c and t or f
+end
+
+function test1()
+    return
+        ternary(true, 1, 2) == 1 and
+        ternary(false, 1, 2) == 2 and
+        ternary(nil, 1, 2) == 2 and
+        ternary(true, false, 2) == 2 and
+        ternary(true, nil, false) == false and
+        ternary(0, "a", "b") == "a"
+end
+
+function test2()
+    local x = 1
+    local y = true
+    x = y and nil or x
+    local z = 2
+    z = not y and 3 or z
+    return x == 1 and z == 2
+end
+
+function test3()
+    local t = {n = 4}
+    local a = t.n > 3 and t.n * 2 or t.n
+    local b = t.n > 5 and t.n * 2 or -t.n
+    local calls = 0
+    local function f()
+        calls = calls + 1
+        return calls
+    end
+    local c = f() and f() or f()
+    return a == 8 and b == -4 and c == 2 and calls == 2
+end
+
+function test4()
+    local a = 1 and 2 or 3 and 4
+    local b = false and 2 or nil and 4 or 5
+    local c = (nil and 1 or false) and 2 or 3
+    return a == 2 and b == 5 and c == 3
+end
+
+return
+    test1() and
+    test2() and
+    test3() and
+    test4()
@@ -0,0 +1,79 @@
+local function test1()
+    local a, b = 1, 2
+    a, b = b, a
+    return a == 2 and b == 1
+end
+
+local function test2()
+    local a, b, c = 1, 2, 3
+    a, b, c = c, a, b
+    return a == 3 and b == 1 and c == 2
+end
+
+local function test3()
+    local old = {}
+    local a = old
+    a, a.x = {}, 2
+    return old.x == 2 and a.x == nil
+end
+
+local function test4()
+    local t = {}
+    local i = 1
+    i, t[i] = 2, "first"
+    return i == 2 and t[1] == "first" and t[2] == nil
+end
+
+local function test5()
+    local t = {1, 2}
+    t[1], t[2] = t[2], t[1]
+    return t[1] == 2 and t[2] == 1
+end
+
+local function test6()
+    local function f()
+        return 1, 2, 3
+    end
+
+    local a, b, c
+    a, b, c = 0, f()
+    local d, e
+    d, e = f(), 5, 6
+    return a == 0 and b == 1 and c == 2 and d == 1 and e == 5
+end
+
+local function test7()
+    x, y = 1, 2
+    x, y = y, x
+    local r = x == 2 and y == 1
+    x, y = nil, nil
+    return r
+end
+
+local function test8()
+    local t = 5
+    t = {t}
+    local z = 6
+    z = {n = z}
+    local x = 7
+    x = x and {x}
+    local p = 8
+    p = nil or {p}
+    local f = 9
+    f = {x = f, f}
+    local y = 10
+    local c = true
+    y = c and y
+    return t[1] == 5 and z.n == 6 and x[1] == 7 and p[1] == 8 and f.x == 9 and f[1] == 9 and
+        y == 10
+end
+
+return
+    test1() and
+    test2() and
+    test3() and
+    test4() and
+    test5() and
+    test6() and
+    test7() and
+    test8()
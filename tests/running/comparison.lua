@@ -0,0 +1,65 @@
+function test1()
+    local x, y = 1, 2
+    local lt = x < y
+    local le = x <= y
+    local gt = x > y
+    local ge = x >= y
+    local eq = x == y
+    local ne = x ~= y
+    return
+        lt == true and
+        le == true and
+        gt == false and
+        ge == false and
+        eq == false and
+        ne == true
+end
+
+function test2()
+    local x = 2
+    local lt = x < 3
+    local le = 3 <= x
+    local gt = x > 3
+    local ge = 3 >= x
+    local eq = x == 2
+    local ne = 2 ~= x
+    return
+        lt == true and
+        le == false and
+        gt == false and
+        ge == true and
+        eq == true and
+        ne == false
+end
+
+function test3()
+    local function eq(a, b)
+        return a == b
+    end
+    local function ne(a, b)
+        return a ~= b
+    end
+    return
+        eq(1, 1) == true and
+        eq(1, 2) == false and
+        eq("a", "a") == true and
+        ne(1, 1) == false and
+        ne(1, 2) == true and
+        ne("a", "b") == true and
+        ne(nil, false) == true
+end
+
+function test4()
+    local a, b = 1, 2
+    return
+        (a ~= b + 1) == true and
+        (a ~= b - 1) == false and
+        (a + 1 ~= b) == false and
+        (a == b - 1) == true
+end
+
+return
+    test1() and
+    test2() and
+    test3() and
+    test4()
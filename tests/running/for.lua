@@ -81,7 +81,136 @@ function test_break_scope()
     return true
 end
 
+function test_numeric_step_one()
+    local count, last = 0, nil
+    for i = 1.5, 4 do
+        count = count + 1
+        last = i
+    end
+    if count ~= 3 or last ~= 3.5 then
+        return false
+    end
+
+    count = 0
+    for i = 1, 3.5 do
+        count = count + 1
+        last = i
+    end
+    if count ~= 3 or last ~= 3 then
+        return false
+    end
+
+    count = 0
+    for i = math.maxinteger - 2, math.maxinteger do
+        count = count + 1
+        last = i
+    end
+    if count ~= 3 or last ~= math.maxinteger then
+        return false
+    end
+
+    count = 0
+    for i = 5, 4 do
+        count = count + 1
+    end
+    for i = 4, 4, 1 do
+        count = count + 1
+        last = i
+    end
+    return count == 1 and last == 4
+end
+
+function test_generic_arguments()
+    local t = {10, 20, 30}
+    local function next_index(t, i)
+        i = i + 1
+        if t[i] then
+            return i, t[i]
+        end
+    end
+    local function state()
+        return t, 0
+    end
+
+    local sum = 0
+    for i, v in next_index, t, 0 do
+        sum = sum + i * v
+    end
+    for i, v in next_index, state() do
+        sum = sum + i * v
+    end
+    local evaluated = false
+    for i in next_index, t, 2, nil, (function() evaluated = true end)() do
+        sum = sum + i
+    end
+    return sum == 283 and evaluated
+end
+
+function test_numeric_overflow()
+    local count = 0
+    for i = math.maxinteger - 2, math.maxinteger do
+        count = count + 1
+    end
+    for i = math.maxinteger - 5, math.maxinteger, 2 do
+        count = count + 1
+    end
+    for i = math.mininteger + 2, math.mininteger, -1 do
+        count = count + 1
+    end
+    for i = math.mininteger, math.mininteger + 4, 3 do
+        count = count + 1
+    end
+    local step = 1
+    local last
+    for i = math.maxinteger - 1, math.maxinteger, step do
+        last = i
+        count = count + 1
+    end
+    for i = 1, 0 do
+        count = count + 100
+    end
+    local first
+    for i = math.maxinteger, math.maxinteger - 1, -step do
+        first = first or i
+        count = count + 1
+    end
+
+    -- Float limits are rounded towards the initial value and clipped to the integer range.
+    local ints = 0
+    for i = 1, 3.5 do
+        ints = ints + i
+        assert(math.type(i) == "integer")
+    end
+    for i = math.maxinteger - 1, 1e100 do
+        ints = ints + 1
+    end
+    for i = 0, -1e100 do
+        ints = ints + 1000
+    end
+    for i = 1, 0 / 0 do
+        ints = ints + 1000
+    end
+
+    return count == 15 and last == math.maxinteger and first == math.maxinteger and ints == 8
+end
+
+function test_numeric_float()
+    local sum = 0
+    for i = 1.0, 2.0, 0.5 do
+        sum = sum + i
+        assert(math.type(i) == "float")
+    end
+    for i = 1, 2, 0.5 do
+        sum = sum + i
+    end
+    return sum == 9
+end
+
 return
+    test_numeric_overflow() and
+    test_numeric_float() and
+    test_generic_arguments() and
+    test_numeric_step_one() and
     test_generic() and
     test_numeric() and
     test_numeric_closure() and
@@ -30,6 +30,53 @@ function test2()
     goto start
 end
 
+function test3()
+    local sum = 0
+    for i = 1, 10 do
+        if i % 2 == 0 then
+            goto continue
+        end
+        local odd = i
+        sum = sum + odd
+        ::continue::
+    end
+
+    local i, count = 0, 0
+    while i < 10 do
+        i = i + 1
+        for j = 1, 3 do
+            if j == i then
+                goto continue
+            end
+            local inner = j
+            count = count + inner
+            ::continue::
+        end
+        if i > 5 then
+            goto continue
+        end
+        local outer = i
+        count = count + outer
+        ::continue::
+    end
+
+    return sum == 25 and i == 10 and count == 5 + 4 + 3 + 7 * 6 + 15
+end
+
+function test4()
+    local i, sum = 0, 0
+    ::top::
+    i = i + 1
+    sum = sum + i
+    if i < 5 then
+        goto top
+    end
+
+    return i == 5 and sum == 15
+end
+
 return
     test1() and
-    test2()
\ No newline at end of file
+    test2() and
+    test3() and
+    test4()
\ No newline at end of file
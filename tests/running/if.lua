@@ -29,6 +29,60 @@ function test2()
         test(true) == 2
 end
 
+function test3()
+    local function test(a, b)
+        local r = 0
+        if a and b then
+            r = r + 1
+        end
+        if a or b then
+            r = r + 10
+        end
+        if not (a or b) then
+            r = r + 100
+        elseif not (a and b) then
+            r = r + 1000
+        end
+        return r
+    end
+
+    local calls = 0
+    local function f(v)
+        calls = calls + 1
+        return v
+    end
+    if f(false) and f(true) then
+        return false
+    end
+    if f(1) or f(2) then
+        calls = calls + 10
+    end
+
+    return
+        test(1, 2) == 11 and
+        test(1, nil) == 1010 and
+        test(nil, false) == 100 and
+        test(false, 2) == 1010 and
+        calls == 12
+end
+
+function test4()
+    local taken = {}
+    local function take(branch)
+        taken[#taken + 1] = branch
+    end
+
+    if false then take(1) elseif true then take(2) else take(3) end
+    if nil then take(4) elseif false then take(5) else take(6) end
+    if true then take(7) elseif take then take(8) end
+    local x = 1
+    if x == 2 then take(9) elseif 0 then take(10) else take(11) end
+
+    return #taken == 4 and taken[1] == 2 and taken[2] == 6 and taken[3] == 7 and taken[4] == 10
+end
+
 return
     test1() and
-    test2()
\ No newline at end of file
+    test2() and
+    test3() and
+    test4()
\ No newline at end of file
@@ -0,0 +1,68 @@
+local function two()
+    return 1, 2
+end
+
+local function none()
+end
+
+function test1()
+    local a, b, c = two()
+    return a == 1 and b == 2 and c == nil
+end
+
+function test2()
+    local a = two()
+    local b, c = none()
+    return a == 1 and b == nil and c == nil
+end
+
+function test3()
+    local a, b = 3, two()
+    local c, d, e = 3, two()
+    return a == 3 and b == 1 and c == 3 and d == 1 and e == 2
+end
+
+function test4()
+    local a, b = two(), 3
+    local c, d, e = two(), two()
+    return a == 1 and b == 3 and c == 1 and d == 1 and e == 2
+end
+
+function test5()
+    local called = false
+    local function f()
+        called = true
+        return 4
+    end
+    local a = 1, f(), two()
+    local b, c = 2, 3, 4
+    return a == 1 and b == 2 and c == 3 and called
+end
+
+function test6()
+    local x = 1
+    local x, y = 2, x
+    local z = 3
+    local z = z + 1
+    return x == 2 and y == 1 and z == 4
+end
+
+function test7()
+    -- A local is only in scope after its declaration, so locals can never be defined in terms of
+    -- each other.  Here `a` reads the global `b`.
+    b = 1
+    local a = b
+    local b = a + 1
+    local ok = a == 1 and b == 2
+    _ENV.b = nil
+    return ok
+end
+
+return
+    test1() and
+    test2() and
+    test3() and
+    test4() and
+    test5() and
+    test6() and
+    test7()
@@ -23,6 +23,66 @@ function test2()
     return t:method(42) == 42
 end
 
+function test3()
+    self = "global"
+    local t = {name = "receiver"}
+
+    function t:get()
+        return self.name
+    end
+
+    function t:get_later()
+        return function()
+            return self.name
+        end
+    end
+
+    function t.plain()
+        return self
+    end
+
+    local function get_self()
+        return self
+    end
+
+    local ok = self == "global" and get_self() == "global" and
+        t:get() == "receiver" and t:get_later()() == "receiver" and
+        t.plain() == "global"
+    self = nil
+    return ok
+end
+
+function test4()
+    local self = 1
+    local t = {}
+
+    function t:get()
+        return self
+    end
+
+    return self == 1 and t:get() == t
+end
+
+function test5()
+    local counter = {n = 0}
+
+    function counter:inc(by)
+        self.n = self.n + (by or 1)
+        return self
+    end
+
+    function counter:wrap()
+        return {inner = self}
+    end
+
+    local n = counter:inc():inc(2):inc().n
+    local m = counter:wrap().inner:inc(10):wrap().inner.n
+    return n == 4 and m == 14
+end
+
 return
     test1() and
-    test2()
+    test2() and
+    test3() and
+    test4() and
+    test5()
@@ -57,10 +57,42 @@ local function test6()
     return a == 1 and b == 2 and c == 3
 end
 
+local function test7()
+    local function f()
+        return 1, 2, 3
+    end
+
+    local function or_tail(x)
+        return x or f()
+    end
+
+    local function and_tail(x)
+        return x and f()
+    end
+
+    local function second(_, b)
+        return b
+    end
+
+    -- 'and' and 'or' always adjust their result to a single value
+    local a, b = or_tail(nil)
+    local c, d = or_tail(7)
+    local e, g = and_tail(true)
+    local h, i = and_tail(false)
+    return
+        a == 1 and b == nil and
+        c == 7 and d == nil and
+        e == 1 and g == nil and
+        h == false and i == nil and
+        second(nil or f()) == nil and
+        second(true and f(), f()) == 1
+end
+
 return
     test1() and
     test2() and
     test3() and
     test4() and
     test5() and
-    test6()
+    test6() and
+    test7()
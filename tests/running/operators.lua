@@ -142,6 +142,18 @@ function test17()
         "0x10" + "4" == 20
 end
 
+function test18()
+    local two, three = 2, 3
+    return
+        two ^ 2 == 4.0 and
+        math.type(two ^ 2) == "float" and
+        two ^ three ^ 2 == 512.0 and
+        (two ^ three) ^ 2 == 64.0 and
+        -two ^ 2 == -4.0 and
+        2 ^ 3 ^ 2 == 512.0 and
+        math.type(2 ^ 10) == "float"
+end
+
 return
     test1() and
     test2() and
@@ -159,4 +171,5 @@ return
     test14() and
     test15() and
     test16() and
-    test17()
+    test17() and
+    test18()
@@ -0,0 +1,45 @@
+local function test1()
+    local function fact(n)
+        if n <= 1 then
+            return 1
+        end
+        return n * fact(n - 1)
+    end
+    return fact(5) == 120
+end
+
+local function test2()
+    local is_even, is_odd
+
+    function is_even(n)
+        if n == 0 then
+            return true
+        end
+        return is_odd(n - 1)
+    end
+
+    function is_odd(n)
+        if n == 0 then
+            return false
+        end
+        return is_even(n - 1)
+    end
+
+    return is_even(10) and is_odd(7) and not is_even(3) and _ENV.is_even == nil
+end
+
+local function test3()
+    local f
+    local function outer()
+        function f()
+            return 3
+        end
+    end
+    outer()
+    return f() == 3 and _ENV.f == nil
+end
+
+return
+    test1() and
+    test2() and
+    test3()
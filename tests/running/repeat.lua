@@ -28,6 +28,25 @@ function test2()
     return closure() == 2
 end
 
+function test3()
+    local sum = 0
+    local i = 0
+    repeat
+        i = i + 1
+        if i % 2 == 0 then
+            goto continue
+        end
+        if i > 7 then
+            break
+        end
+        sum = sum + i
+        ::continue::
+    until i >= 10
+
+    return sum == 16 and i == 9
+end
+
 return
     test1() and
-    test2()
+    test2() and
+    test3()
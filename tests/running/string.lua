@@ -9,6 +9,15 @@ function test_concat()
         1 .. 2 .. 3 == "123"
 end
 
+function test_concat_fold()
+    local a, b = "a", "b"
+    return
+        a .. "x" .. "y" .. b == "axyb" and
+        "x" .. 1 .. "y" == "x1y" and
+        a .. 1 .. 2 == "a12" and
+        1 .. 2 .. b == "12b"
+end
+
 function test_coroutine_len()
     return nil
 end
@@ -35,5 +44,17 @@ function test_len()
         string.len(-2147483648) == 11
 end
 
+function test_number_concat()
+    local three, half, big = 3.0, 0.5, 1e100
+    return
+        three .. "" == "3.0" and
+        half .. "" == "0.5" and
+        big .. "" == "1e+100" and
+        3.0 .. "" == "3.0" and
+        -3 .. "" == "-3"
+end
+
 return test_concat() and
-       test_len()
+       test_concat_fold() and
+       test_len() and
+       test_number_concat()
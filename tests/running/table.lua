@@ -94,9 +94,18 @@ function test5()
     return t[1] == 1 and t[2] == 2 and t[3] == 3 and t.a == "a"
 end
 
+local function test6()
+    local t = {}
+    local two = 2
+    t[2] = 1
+    t[two + 0.0] = t[two + 0.0] + 1
+    return t[2] == 2 and t[2.0] == 2 and t[2.5] == nil
+end
+
 return
     test1() and
     test2() and
     test3() and
     test4() and
-    test5()
+    test5() and
+    test6()
@@ -24,6 +24,28 @@ function test2()
     return a == 1 and b == 2 and c == 3
 end
 
+function test3()
+    local obj = { value = 3 }
+
+    function obj:get(extra)
+        return self.value + extra
+    end
+
+    function obj:forward(extra)
+        return self:get(extra)
+    end
+
+    function obj:count(n)
+        if n == 0 then
+            return self.value
+        end
+        return self:count(n - 1)
+    end
+
+    return obj:forward(4) == 7 and obj:count(100000) == 3
+end
+
 return
     test1() and
-    test2()
+    test2() and
+    test3()
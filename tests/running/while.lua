@@ -62,7 +62,25 @@ function test_break_scope()
     return true
 end
 
+function test_constant_conditions()
+    local i = 0
+    while true do
+        i = i + 1
+        if i == 5 then
+            break
+        end
+    end
+    while false do
+        i = 0
+    end
+    while nil do
+        i = 0
+    end
+    return i == 5
+end
+
 return
+    test_constant_conditions() and
     test_sum() and
     test_break() and
     test_endlabel() and
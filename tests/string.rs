@@ -0,0 +1,110 @@
+use luster::{Lua, String, StringError, Table, Value};
+
+#[test]
+fn from_numbers() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        assert_eq!(String::from_i64(mc, -5).as_bytes(), b"-5");
+        assert_eq!(
+            String::from_i64(mc, i64::MIN).as_bytes(),
+            b"-9223372036854775808"
+        );
+
+        let f = |n| String::from_f64(mc, n);
+        assert_eq!(f(3.0).as_bytes(), b"3.0");
+        assert_eq!(f(-0.0).as_bytes(), b"-0.0");
+        assert_eq!(f(3.5).as_bytes(), b"3.5");
+        assert_eq!(f(0.1).as_bytes(), b"0.1");
+        assert_eq!(f(1.0 / 3.0).as_bytes(), b"0.33333333333333");
+        assert_eq!(f(1e14).as_bytes(), b"1e+14");
+        assert_eq!(f(123456789012345.0).as_bytes(), b"1.2345678901234e+14");
+        assert_eq!(f(1e13).as_bytes(), b"10000000000000.0");
+        assert_eq!(f(0.0001).as_bytes(), b"0.0001");
+        assert_eq!(f(0.00001).as_bytes(), b"1e-05");
+        assert_eq!(f(2f64.powi(63)).as_bytes(), b"9.2233720368548e+18");
+        assert_eq!(f(f64::INFINITY).as_bytes(), b"inf");
+        assert_eq!(f(f64::NEG_INFINITY).as_bytes(), b"-inf");
+    });
+}
+
+#[test]
+fn slice() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let s = String::new(mc, b"hello world");
+        let sub = |i, j| s.slice(mc, i, j);
+        assert_eq!(sub(1, 5).as_bytes(), b"hello");
+        assert_eq!(sub(7, -1).as_bytes(), b"world");
+        assert_eq!(sub(-5, -3).as_bytes(), b"wor");
+        assert_eq!(sub(-100, 2).as_bytes(), b"he");
+        assert_eq!(sub(0, 100).as_bytes(), b"hello world");
+        assert_eq!(sub(i64::MIN, i64::MAX).as_bytes(), b"hello world");
+        assert_eq!(sub(5, 4).as_bytes(), b"");
+        assert_eq!(sub(12, 20).as_bytes(), b"");
+        assert_eq!(sub(3, -100).as_bytes(), b"");
+
+        assert_eq!(s.len(), 11);
+        assert_eq!(s.byte_at(1), Some(b'h'));
+        assert_eq!(s.byte_at(-1), Some(b'd'));
+        assert_eq!(s.byte_at(-11), Some(b'h'));
+        assert_eq!(s.byte_at(0), None);
+        assert_eq!(s.byte_at(12), None);
+        assert_eq!(s.byte_at(-12), None);
+    });
+}
+
+#[test]
+fn concat() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let long = String::new(mc, &[b'x'; 40]);
+        let joined = String::concat(mc, String::new_static(b"ab"), long);
+        assert_eq!(joined.len(), 42);
+        assert_eq!(&joined.as_bytes()[..3], b"abx");
+
+        let s = String::concat_all(
+            mc,
+            &[
+                Value::String(String::new_static(b"a")),
+                Value::Integer(-1),
+                Value::String(String::new_static(b" ")),
+                Value::Number(2.5),
+                Value::Number(3.0),
+                Value::String(long),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            s.as_bytes(),
+            &[&b"a-1 2.53.0"[..], &[b'x'; 40][..]].concat()[..]
+        );
+        assert_eq!(String::concat_all(mc, &[]).unwrap().as_bytes(), b"");
+
+        for (bad, bad_type) in &[
+            (Value::Nil, "nil"),
+            (Value::Boolean(true), "boolean"),
+            (Value::Table(Table::new(mc)), "table"),
+        ] {
+            match String::concat_all(mc, &[Value::Integer(1), *bad]) {
+                Err(StringError::Concat { bad_type: found }) => assert_eq!(found, *bad_type),
+                res => panic!("expected concat error, got {:?}", res),
+            }
+        }
+    });
+}
+
+#[test]
+fn debug_and_display() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let s = String::new(mc, b"a\0b");
+        assert_eq!(format!("{:?}", s), r#""a\x00b""#);
+        assert_eq!(s.to_string(), "a\0b");
+
+        let s = String::new(mc, b"say \"hi\"\\\n\xff");
+        assert_eq!(format!("{:?}", s), r#""say \"hi\"\\\x0a\xff""#);
+        assert_eq!(s.to_string(), "say \"hi\"\\\n\u{fffd}");
+
+        assert_eq!(format!("{:?}", String::new_static(b"")), r#""""#);
+    });
+}
@@ -2,7 +2,9 @@ use std::fs::{read_dir, File};
 use std::io::{stdout, Write};
 
 use gc_sequence::{self as sequence, SequenceExt, SequenceResultExt};
-use luster::{compile, io, parse_chunk, Closure, Error, Function, Lua, ThreadSequence, Value};
+use luster::{
+    compile, io, parse_chunk, verify, Closure, Error, Function, Lua, ThreadSequence, Value,
+};
 
 fn test_dir(dir: &str, run_code: bool) {
     let mut file_failed = false;
@@ -20,11 +22,9 @@ fn test_dir(dir: &str, run_code: bool) {
                     let mut lua = Lua::new();
                     let r = lua.sequence(|root| {
                         sequence::from_fn_with(root, move |mc, root| {
-                            Ok(Closure::new(
-                                mc,
-                                compile(mc, root.interned_strings, file)?,
-                                Some(root.globals),
-                            )?)
+                            let proto = compile(mc, root.interned_strings, file)?;
+                            verify(&proto).expect("compiled prototype failed verification");
+                            Ok(Closure::new(mc, proto, Some(root.globals))?)
                         })
                         .and_chain_with(root, move |mc, root, closure| {
                             Ok(ThreadSequence::call_function(
@@ -0,0 +1,223 @@
+use luster::{
+    Callback, CallbackResult, CallbackReturn, Function, Lua, MetaError, MetaResult,
+    PositionOutOfBounds, String, Table, Value,
+};
+
+#[test]
+fn float_keys() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let table = Table::new(mc);
+        table.raw_set(mc, 2, 1).unwrap();
+        assert_eq!(table.raw_get(2.0), Value::Integer(1));
+
+        table.raw_set(mc, 2.0, 3).unwrap();
+        assert_eq!(table.raw_get(2), Value::Integer(3));
+
+        table.raw_set(mc, 2.5, 4).unwrap();
+        assert_eq!(table.raw_get(2.5), Value::Integer(4));
+        assert_eq!(table.raw_get(2), Value::Integer(3));
+
+        table.raw_set(mc, -0.0, 5).unwrap();
+        assert_eq!(table.raw_get(0), Value::Integer(5));
+    });
+}
+
+#[test]
+fn index_metamethods() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let index = String::new_static(b"__index");
+        let newindex = String::new_static(b"__newindex");
+
+        let base = Table::new(mc);
+        base.raw_set(mc, "a", 1).unwrap();
+        let middle = Table::new(mc);
+        middle.raw_set(mc, "b", 2).unwrap();
+        let top = Table::new(mc);
+
+        let base_meta = Table::new(mc);
+        base_meta.raw_set(mc, index, base).unwrap();
+        middle.set_metatable(mc, Some(base_meta));
+        let middle_meta = Table::new(mc);
+        middle_meta.raw_set(mc, index, middle).unwrap();
+        assert!(top.set_metatable(mc, Some(middle_meta)).is_none());
+        assert_eq!(top.metatable(), Some(middle_meta));
+
+        assert_eq!(top.get("a").unwrap(), MetaResult::Value(Value::Integer(1)));
+        assert_eq!(top.get("b").unwrap(), MetaResult::Value(Value::Integer(2)));
+        assert_eq!(top.get("c").unwrap(), MetaResult::Value(Value::Nil));
+        assert_eq!(top.raw_get("a"), Value::Nil);
+
+        // `__newindex` tables receive absent keys, present keys are set directly.
+        middle_meta.raw_set(mc, newindex, base).unwrap();
+        top.set(mc, "c", 3).unwrap();
+        assert_eq!(top.raw_get("c"), Value::Nil);
+        assert_eq!(base.raw_get("c"), Value::Integer(3));
+        top.raw_set(mc, "d", 4).unwrap();
+        assert_eq!(
+            top.set(mc, "d", 5).unwrap(),
+            MetaResult::Value(Value::Integer(4))
+        );
+        assert_eq!(top.raw_get("d"), Value::Integer(5));
+
+        let callback = Callback::new_immediate(mc, |_| Ok(CallbackResult::Return(vec![])));
+        base_meta.raw_set(mc, index, callback).unwrap();
+        assert_eq!(
+            top.get("x").unwrap(),
+            MetaResult::Call(Function::Callback(callback), middle)
+        );
+
+        base_meta.raw_set(mc, index, 1).unwrap();
+        match top.get("x") {
+            Err(MetaError::BadMetamethod("number")) => {}
+            res => panic!("expected bad metamethod, got {:?}", res),
+        }
+
+        let looped = Table::new(mc);
+        let looped_meta = Table::new(mc);
+        looped_meta.raw_set(mc, index, looped).unwrap();
+        looped.set_metatable(mc, Some(looped_meta));
+        match looped.get("x") {
+            Err(MetaError::ChainTooLong) => {}
+            res => panic!("expected chain too long, got {:?}", res),
+        }
+    });
+}
+
+#[test]
+fn clear() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let meta = Table::new(mc);
+        let table = Table::new(mc);
+        table.set_metatable(mc, Some(meta));
+        for i in 1..=8 {
+            table.raw_set(mc, i, i).unwrap();
+        }
+        table.raw_set(mc, "a", 1).unwrap();
+        assert_eq!(table.raw_length(), 8);
+        let array_len = table.len_array_part();
+        assert!(array_len >= 8);
+
+        table.clear(mc);
+        assert_eq!(table.raw_length(), 0);
+        assert_eq!(table.raw_get(1), Value::Nil);
+        assert_eq!(table.raw_get("a"), Value::Nil);
+        assert_eq!(table.len_array_part(), array_len);
+        assert_eq!(table.metatable(), Some(meta));
+
+        for i in 1..=4 {
+            table.raw_set(mc, i, i).unwrap();
+        }
+        assert_eq!(table.raw_length(), 4);
+        assert_eq!(table.len_array_part(), array_len);
+    });
+}
+
+#[test]
+fn len_metamethod() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let len = String::new_static(b"__len");
+
+        let table = Table::new(mc);
+        for i in 1..=3 {
+            table.raw_set(mc, i, i).unwrap();
+        }
+        assert_eq!(
+            table.length().unwrap(),
+            MetaResult::Value(Value::Integer(3))
+        );
+
+        let meta = Table::new(mc);
+        table.set_metatable(mc, Some(meta));
+        assert_eq!(
+            table.length().unwrap(),
+            MetaResult::Value(Value::Integer(3))
+        );
+
+        let callback =
+            Callback::new_immediate(mc, |_| Ok(CallbackResult::Return(vec![Value::Integer(42)])));
+        meta.raw_set(mc, len, callback).unwrap();
+        let (function, this) = match table.length().unwrap() {
+            MetaResult::Call(Function::Callback(function), this) => (function, this),
+            res => panic!("expected a call to __len, got {:?}", res),
+        };
+        assert_eq!(function, callback);
+        assert_eq!(this, table);
+        match function.call(vec![Value::Table(this)]) {
+            CallbackReturn::Immediate(Ok(CallbackResult::Return(values))) => {
+                assert_eq!(values, vec![Value::Integer(42)])
+            }
+            _ => panic!("expected an immediate return from __len"),
+        }
+        assert_eq!(table.raw_length(), 3);
+
+        meta.raw_set(mc, len, meta).unwrap();
+        match table.length() {
+            Err(MetaError::BadMetamethod("table")) => {}
+            res => panic!("expected bad metamethod, got {:?}", res),
+        }
+    });
+}
+
+#[test]
+fn insert_remove() {
+    fn contents<'gc>(table: Table<'gc>) -> Vec<Value<'gc>> {
+        (1..=table.raw_length()).map(|i| table.raw_get(i)).collect()
+    }
+
+    fn ints<'gc>(values: &[i64]) -> Vec<Value<'gc>> {
+        values.iter().map(|&i| Value::Integer(i)).collect()
+    }
+
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        // A table with its sequence in the array part, and one with its sequence in the map part.
+        let array = Table::with_capacity(mc, 8, 0);
+        let map = Table::new(mc);
+        map.raw_set(mc, "key", true).unwrap();
+        for table in &[array, map] {
+            for i in 1..=5 {
+                table.raw_set(mc, i, i * 10).unwrap();
+            }
+        }
+        assert_eq!(array.len_array_part(), 8);
+        assert!(map.len_array_part() < 5);
+
+        for &table in &[array, map] {
+            table.insert(mc, 1, 0).unwrap();
+            assert_eq!(contents(table), ints(&[0, 10, 20, 30, 40, 50]));
+            table.insert(mc, 7, 60).unwrap();
+            assert_eq!(contents(table), ints(&[0, 10, 20, 30, 40, 50, 60]));
+
+            assert_eq!(table.remove(mc, 4).unwrap(), Value::Integer(30));
+            assert_eq!(contents(table), ints(&[0, 10, 20, 40, 50, 60]));
+            assert_eq!(table.raw_get(7), Value::Nil);
+            assert_eq!(table.remove(mc, 6).unwrap(), Value::Integer(60));
+            assert_eq!(table.remove(mc, 6).unwrap(), Value::Nil);
+            assert_eq!(contents(table), ints(&[0, 10, 20, 40, 50]));
+
+            for &pos in &[0, 7, -1] {
+                match table.insert(mc, pos, 1) {
+                    Err(PositionOutOfBounds(p)) => assert_eq!(p, pos),
+                    res => panic!("expected position out of bounds, got {:?}", res),
+                }
+            }
+            for &pos in &[0, 7] {
+                match table.remove(mc, pos) {
+                    Err(PositionOutOfBounds(p)) => assert_eq!(p, pos),
+                    res => panic!("expected position out of bounds, got {:?}", res),
+                }
+            }
+            assert_eq!(contents(table), ints(&[0, 10, 20, 40, 50]));
+        }
+        assert_eq!(map.raw_get("key"), Value::Boolean(true));
+
+        let empty = Table::new(mc);
+        assert_eq!(empty.remove(mc, 0).unwrap(), Value::Nil);
+        empty.insert(mc, 1, "first").unwrap();
+        assert_eq!(empty.raw_length(), 1);
+    });
+}
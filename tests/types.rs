@@ -0,0 +1,18 @@
+use luster::{ConstantIndex16, ConstantIndex8, PrototypeIndex, RegisterIndex, UpValueIndex};
+
+#[test]
+fn checked_indexes() {
+    assert_eq!(RegisterIndex::try_new(255), Some(RegisterIndex::new(255)));
+    assert_eq!(RegisterIndex::try_new(256), None);
+    assert_eq!(ConstantIndex8::try_new(256), None);
+    assert_eq!(UpValueIndex::try_new(300), None);
+    assert_eq!(PrototypeIndex::try_new(1000), None);
+    assert_eq!(
+        ConstantIndex16::try_new(65535),
+        Some(ConstantIndex16::new(65535))
+    );
+    assert_eq!(ConstantIndex16::try_new(65536), None);
+
+    assert_eq!(RegisterIndex::new(17).to_usize(), 17);
+    assert_eq!(ConstantIndex16::new(1024).to_usize(), 1024);
+}
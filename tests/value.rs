@@ -0,0 +1,110 @@
+use luster::Value;
+
+#[test]
+fn modulo() {
+    let cases = [
+        (
+            Value::Integer(5),
+            Value::Integer(3),
+            Some(Value::Integer(2)),
+        ),
+        (
+            Value::Integer(5),
+            Value::Integer(-3),
+            Some(Value::Integer(-1)),
+        ),
+        (
+            Value::Integer(-5),
+            Value::Integer(3),
+            Some(Value::Integer(1)),
+        ),
+        (
+            Value::Integer(-5),
+            Value::Integer(-3),
+            Some(Value::Integer(-2)),
+        ),
+        (
+            Value::Integer(6),
+            Value::Integer(-3),
+            Some(Value::Integer(0)),
+        ),
+        (
+            Value::Integer(i64::MIN),
+            Value::Integer(-1),
+            Some(Value::Integer(0)),
+        ),
+        (
+            Value::Integer(i64::MAX),
+            Value::Integer(i64::MIN),
+            Some(Value::Integer(-1)),
+        ),
+        (Value::Integer(1), Value::Integer(0), None),
+        (
+            Value::Number(5.5),
+            Value::Integer(2),
+            Some(Value::Number(1.5)),
+        ),
+        (
+            Value::Number(-5.5),
+            Value::Integer(2),
+            Some(Value::Number(0.5)),
+        ),
+        (
+            Value::Number(5.5),
+            Value::Integer(-2),
+            Some(Value::Number(-0.5)),
+        ),
+        (
+            Value::Integer(5),
+            Value::Number(f64::INFINITY),
+            Some(Value::Number(5.0)),
+        ),
+        (
+            Value::Integer(-5),
+            Value::Number(f64::INFINITY),
+            Some(Value::Number(f64::INFINITY)),
+        ),
+    ];
+
+    for &(a, b, result) in &cases {
+        let m = a.modulo(b);
+        match (m, result) {
+            (Some(Value::Integer(m)), Some(Value::Integer(r))) => assert_eq!(m, r),
+            (Some(Value::Number(m)), Some(Value::Number(r))) => assert_eq!(m, r),
+            (None, None) => {}
+            _ => panic!("{:?} % {:?} gave {:?}, expected {:?}", a, b, m, result),
+        }
+    }
+
+    match Value::Number(1.0).modulo(Value::Integer(0)) {
+        Some(Value::Number(n)) => assert!(n.is_nan()),
+        m => panic!("unexpected float modulo by zero result {:?}", m),
+    }
+}
+
+#[test]
+fn mixed_comparison() {
+    let (i, f) = (Value::Integer, Value::Number);
+    let two_53 = 9007199254740992;
+
+    assert!(i(two_53 + 1) != f(two_53 as f64));
+    assert!(f(two_53 as f64) != i(two_53 + 1));
+    assert!(i(two_53) == f(two_53 as f64));
+    assert_eq!(f(two_53 as f64).less_than(i(two_53 + 1)), Some(true));
+    assert_eq!(i(two_53 + 1).less_equal(f(two_53 as f64)), Some(false));
+    assert_eq!(i(two_53 + 1).less_than(f((two_53 + 2) as f64)), Some(true));
+
+    assert!(i(i64::MAX) != f(i64::MAX as f64));
+    assert_eq!(i(i64::MAX).less_than(f(i64::MAX as f64)), Some(true));
+    assert!(i(i64::MIN) == f(i64::MIN as f64));
+    assert_eq!(f(i64::MIN as f64).less_equal(i(i64::MIN)), Some(true));
+
+    assert_eq!(i(2).less_than(f(2.5)), Some(true));
+    assert_eq!(i(-2).less_than(f(-2.5)), Some(false));
+    assert_eq!(f(-2.5).less_than(i(-2)), Some(true));
+    assert_eq!(i(i64::MAX).less_than(f(f64::INFINITY)), Some(true));
+    assert_eq!(f(f64::NEG_INFINITY).less_equal(i(i64::MIN)), Some(true));
+    assert_eq!(i(0).less_equal(f(f64::NAN)), Some(false));
+    assert_eq!(f(f64::NAN).less_equal(i(0)), Some(false));
+    assert!(i(0) != f(f64::NAN));
+}
@@ -0,0 +1,207 @@
+use gc_arena::Gc;
+
+use luster::{
+    compile, verify, Constant, ConstantIndex16, ConstantIndex8, FunctionProto, Lua, OpCode, Opt254,
+    PrototypeIndex, RegisterIndex, UpValueDescriptor, UpValueIndex, VarCount, VerifyError,
+};
+
+fn proto<'gc>(opcodes: Vec<OpCode>) -> FunctionProto<'gc> {
+    FunctionProto {
+        fixed_params: 0,
+        has_varargs: false,
+        stack_size: 2,
+        constants: vec![Constant::Integer(1)],
+        opcodes,
+        packed_opcodes: vec![],
+        upvalues: vec![],
+        upvalue_names: vec![],
+        local_vars: vec![],
+        prototypes: vec![],
+    }
+}
+
+fn ret() -> OpCode {
+    OpCode::Return {
+        start: RegisterIndex(0),
+        count: VarCount::constant(0),
+    }
+}
+
+#[test]
+fn valid() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, root| {
+        let compiled = compile(
+            mc,
+            root.interned_strings,
+            &b"local t = {} for i = 1, 10 do t[i] = function() return i end end return t"[..],
+        )
+        .unwrap();
+        assert_eq!(verify(&compiled), Ok(()));
+    });
+
+    let p = proto(vec![
+        OpCode::LoadConstant {
+            dest: RegisterIndex(1),
+            constant: ConstantIndex16(0),
+        },
+        ret(),
+    ]);
+    assert_eq!(verify(&p), Ok(()));
+}
+
+#[test]
+fn invalid() {
+    assert_eq!(verify(&proto(vec![])), Err(VerifyError::NoReturn));
+    assert_eq!(
+        verify(&proto(vec![OpCode::NewTable {
+            dest: RegisterIndex(0),
+            array_size: 0,
+            map_size: 0,
+        }])),
+        Err(VerifyError::NoReturn)
+    );
+
+    assert_eq!(
+        verify(&proto(vec![
+            OpCode::Move {
+                dest: RegisterIndex(0),
+                source: RegisterIndex(2),
+            },
+            ret(),
+        ])),
+        Err(VerifyError::BadRegister { pc: 0 })
+    );
+    assert_eq!(
+        verify(&proto(vec![
+            OpCode::LoadNil {
+                dest: RegisterIndex(1),
+                count: 2,
+            },
+            ret(),
+        ])),
+        Err(VerifyError::BadRegister { pc: 0 })
+    );
+
+    assert_eq!(
+        verify(&proto(vec![
+            OpCode::NewTable {
+                dest: RegisterIndex(0),
+                array_size: 0,
+                map_size: 0,
+            },
+            OpCode::GetTableC {
+                dest: RegisterIndex(0),
+                table: RegisterIndex(0),
+                key: ConstantIndex8(1),
+            },
+            ret(),
+        ])),
+        Err(VerifyError::BadConstant { pc: 1 })
+    );
+
+    assert_eq!(
+        verify(&proto(vec![
+            OpCode::GetUpValue {
+                dest: RegisterIndex(0),
+                source: UpValueIndex(0),
+            },
+            ret(),
+        ])),
+        Err(VerifyError::BadUpValue { pc: 0 })
+    );
+
+    assert_eq!(
+        verify(&proto(vec![
+            OpCode::Closure {
+                dest: RegisterIndex(0),
+                proto: PrototypeIndex(0),
+            },
+            ret(),
+        ])),
+        Err(VerifyError::BadPrototype { pc: 0 })
+    );
+
+    for &offset in &[1, -3] {
+        assert_eq!(
+            verify(&proto(vec![
+                OpCode::Jump {
+                    offset,
+                    close_upvalues: Opt254::none(),
+                },
+                ret(),
+            ])),
+            Err(VerifyError::BadJump { pc: 0 })
+        );
+    }
+    assert_eq!(
+        verify(&proto(vec![
+            OpCode::Test {
+                value: RegisterIndex(0),
+                is_true: true,
+            },
+            ret(),
+        ])),
+        Err(VerifyError::BadJump { pc: 0 })
+    );
+}
+
+#[test]
+fn nested() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let mut outer = proto(vec![
+            OpCode::Closure {
+                dest: RegisterIndex(0),
+                proto: PrototypeIndex(0),
+            },
+            ret(),
+        ]);
+        outer.prototypes.push(Gc::allocate(mc, proto(vec![])));
+        assert_eq!(verify(&outer), Err(VerifyError::NoReturn));
+    });
+}
+
+#[test]
+fn nested_upvalues() {
+    let mut lua = Lua::new();
+    lua.mutate(|mc, _| {
+        let with_upvalues = |upvalues: Vec<UpValueDescriptor>| {
+            let mut outer = proto(vec![
+                OpCode::Closure {
+                    dest: RegisterIndex(0),
+                    proto: PrototypeIndex(0),
+                },
+                ret(),
+            ]);
+            outer.upvalues.push(UpValueDescriptor::Environment);
+            let mut inner = proto(vec![ret()]);
+            inner.upvalues = upvalues;
+            outer.prototypes.push(Gc::allocate(mc, inner));
+            verify(&outer)
+        };
+
+        assert_eq!(
+            with_upvalues(vec![
+                UpValueDescriptor::ParentLocal(RegisterIndex(1)),
+                UpValueDescriptor::Outer(UpValueIndex(0)),
+            ]),
+            Ok(())
+        );
+        assert_eq!(
+            with_upvalues(vec![UpValueDescriptor::ParentLocal(RegisterIndex(2))]),
+            Err(VerifyError::BadUpValueDescriptor { index: 0 })
+        );
+        assert_eq!(
+            with_upvalues(vec![
+                UpValueDescriptor::Outer(UpValueIndex(0)),
+                UpValueDescriptor::Outer(UpValueIndex(1)),
+            ]),
+            Err(VerifyError::BadUpValueDescriptor { index: 1 })
+        );
+        assert_eq!(
+            with_upvalues(vec![UpValueDescriptor::Environment]),
+            Err(VerifyError::BadUpValueDescriptor { index: 0 })
+        );
+    });
+}